@@ -0,0 +1,274 @@
+use std::{cmp::Ordering, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    evaluator::evaluator::{negate, ordering, power},
+    parser::parser::ResultEval,
+};
+
+// types of function parameters and results
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Type {
+    Int,
+    Rational,
+    Float,
+    // any kind of number of the numeric tower
+    Number,
+    Bool,
+    Function,
+    Any,
+}
+
+impl Type {
+    // type of a value
+    pub fn of(value: &ResultEval) -> Type {
+        return match value {
+            ResultEval::Int(_) => Type::Int,
+            ResultEval::Rational(_) => Type::Rational,
+            ResultEval::Float(_) => Type::Float,
+            ResultEval::Bool(_) => Type::Bool,
+            ResultEval::Closure(_) => Type::Function,
+        };
+    }
+
+    // true if the value has this type
+    pub fn accepts(&self, value: &ResultEval) -> bool {
+        return match (self, value) {
+            (Type::Any, _) => true,
+            (Type::Int, ResultEval::Int(_)) => true,
+            (Type::Rational, ResultEval::Rational(_)) => true,
+            (Type::Float, ResultEval::Float(_)) => true,
+            (Type::Number, value) => !matches!(value, ResultEval::Bool(_) | ResultEval::Closure(_)),
+            (Type::Bool, ResultEval::Bool(_)) => true,
+            (Type::Function, ResultEval::Closure(_)) => true,
+            _ => false,
+        };
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Type::Int => "int",
+            Type::Rational => "rational",
+            Type::Float => "float",
+            Type::Number => "number",
+            Type::Bool => "bool",
+            Type::Function => "function",
+            Type::Any => "any",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+// implementation of a function, receives the already checked arguments,
+// None if the function is not defined for them
+pub type Body = Rc<dyn Fn(&[ResultEval]) -> Option<ResultEval>>;
+
+// function signature and implementation, the arity is the number of parameters
+#[derive(Clone)]
+pub struct Function {
+    pub parameters: Vec<Type>,
+    pub result: Type,
+    pub body: Body,
+}
+
+// functions callable from expressions by name
+#[derive(Clone, Default)]
+pub struct Registry {
+    functions: HashMap<String, Function>,
+}
+
+impl Registry {
+    // empty registry
+    pub fn new() -> Registry {
+        return Registry::default();
+    }
+
+    // registry with the builtin functions max, min, abs, pow, not, xor and ifte
+    pub fn builtins() -> Registry {
+        let mut registry = Registry::new();
+        registry.register(
+            "max",
+            vec![Type::Number, Type::Number],
+            Type::Number,
+            |args| extremum(args, Ordering::Greater),
+        );
+        registry.register(
+            "min",
+            vec![Type::Number, Type::Number],
+            Type::Number,
+            |args| extremum(args, Ordering::Less),
+        );
+        registry.register("abs", vec![Type::Number], Type::Number, |args| {
+            return match ordering(&args[0], &ResultEval::Int(0))? {
+                Ordering::Less => negate(args[0].clone()),
+                _ => Some(args[0].clone()),
+            };
+        });
+        registry.register(
+            "pow",
+            vec![Type::Number, Type::Number],
+            Type::Number,
+            |args| power(args[0].clone(), args[1].clone()),
+        );
+        registry.register("not", vec![Type::Bool], Type::Bool, |args| {
+            return match args[0] {
+                ResultEval::Bool(value) => Some(ResultEval::Bool(!value)),
+                _ => None,
+            };
+        });
+        registry.register("xor", vec![Type::Bool, Type::Bool], Type::Bool, |args| {
+            return match (&args[0], &args[1]) {
+                (ResultEval::Bool(l), ResultEval::Bool(r)) => Some(ResultEval::Bool(l ^ r)),
+                _ => None,
+            };
+        });
+        // formerly `if`, which is a keyword now: both branches are evaluated before the call
+        registry.register(
+            "ifte",
+            vec![Type::Bool, Type::Any, Type::Any],
+            Type::Any,
+            |args| {
+                return match args[0] {
+                    ResultEval::Bool(true) => Some(args[1].clone()),
+                    ResultEval::Bool(false) => Some(args[2].clone()),
+                    _ => None,
+                };
+            },
+        );
+        return registry;
+    }
+
+    // registers the function under `name`, replacing a function registered before
+    pub fn register(
+        &mut self,
+        name: &str,
+        parameters: Vec<Type>,
+        result: Type,
+        body: impl Fn(&[ResultEval]) -> Option<ResultEval> + 'static,
+    ) {
+        let function = Function {
+            parameters,
+            result,
+            body: Rc::new(body),
+        };
+        self.functions.insert(name.to_string(), function);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Function> {
+        return self.functions.get(name);
+    }
+}
+
+// the first argument if it is ordered `wanted` compared to the second one, else the second one
+fn extremum(args: &[ResultEval], wanted: Ordering) -> Option<ResultEval> {
+    if ordering(&args[0], &args[1])? == wanted {
+        return Some(args[0].clone());
+    }
+    return Some(args[1].clone());
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        evaluator::{
+            builtins::Type,
+            evaluator::{EvalError, Evaluator},
+        },
+        lexer::lexer::Lexer,
+        numeric::numeric::Rational,
+        parser::parser::{ASTNode, Parser, ResultEval, ShuntiyardParser},
+    };
+    use anyhow::Result;
+
+    fn parse(input: &str) -> Result<ASTNode> {
+        return Ok(ShuntiyardParser::new(Lexer::new(input)).parse()?);
+    }
+
+    #[test]
+    fn builtins_test() -> Result<()> {
+        let evaluator = Evaluator::new();
+        let cases = [
+            ("max(1, 2) + min(3, -4)", ResultEval::Int(-2)),
+            (
+                "max(1/2r, 1/4r)",
+                ResultEval::Rational(Rational::new(1, 2).unwrap()),
+            ),
+            ("abs(-3) * abs(2)", ResultEval::Int(6)),
+            ("abs(-0.5)", ResultEval::Float(0.5)),
+            ("pow(2, 10)", ResultEval::Int(1024)),
+            ("not(1 < 2) || xor(true, false)", ResultEval::Bool(true)),
+            ("ifte(1 > 2, 1, 2.5)", ResultEval::Float(2.5)),
+            ("max(min(1, 2), abs(-(3)))", ResultEval::Int(3)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(evaluator.eval(&parse(input)?)?, expected, "{}", input);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn call_errors_test() -> Result<()> {
+        let evaluator = Evaluator::new();
+        let error = evaluator.eval(&parse("1 + foo(2)")?).unwrap_err();
+        assert!(matches!(&error, EvalError::UnknownFunction { name, .. } if name == "foo"));
+        assert_eq!((error.span().start, error.span().end), (4, 10));
+        assert_eq!(error.to_string(), "unknown function 'foo' at 1:5");
+
+        let error = evaluator.eval(&parse("max(1)")?).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "function 'max' at 1:1 expects 2 argument(s) but received 1"
+        );
+
+        let error = evaluator.eval(&parse("xor(true, 1)")?).unwrap_err();
+        assert!(matches!(
+            &error,
+            EvalError::ArgumentType {
+                position: 2,
+                expected: Type::Bool,
+                found: ResultEval::Int(1),
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "argument 2 of 'xor' at 1:11 has to be bool but is 1"
+        );
+        return Ok(());
+    }
+
+    #[test]
+    fn register_test() -> Result<()> {
+        let mut evaluator = Evaluator::new();
+        evaluator.registry().register(
+            "clamp",
+            vec![Type::Int, Type::Int, Type::Int],
+            Type::Int,
+            |args| match args {
+                [ResultEval::Int(x), ResultEval::Int(lo), ResultEval::Int(hi)] if lo <= hi => {
+                    Some(ResultEval::Int(*x.clamp(lo, hi)))
+                }
+                _ => None,
+            },
+        );
+        let result = evaluator.eval(&parse("clamp(12, 0, 2 * 5)")?)?;
+        assert_eq!(result, ResultEval::Int(10));
+        let error = evaluator.eval(&parse("clamp(1, 5, 0)")?).unwrap_err();
+        assert!(matches!(error, EvalError::Undefined { .. }));
+
+        // results are checked against the declared result type
+        evaluator
+            .registry()
+            .register("half", vec![Type::Int], Type::Int, |args| match args[0] {
+                ResultEval::Int(x) => Some(ResultEval::Float(x as f64 / 2.0)),
+                _ => None,
+            });
+        let error = evaluator.eval(&parse("half(3)")?).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "function 'half' at 1:1 has to return int but returned 1.5"
+        );
+        return Ok(());
+    }
+}
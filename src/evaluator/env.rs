@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use crate::parser::parser::{ASTNode, Definition, ResultEval};
+
+// values of the variables visible to an expression
+// bindings are immutable and shared, so extending an environment is cheap
+// and leaves the environment it was extended from unchanged
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Env {
+    head: Option<Arc<Binding>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Binding {
+    name: String,
+    value: Bound,
+    next: Option<Arc<Binding>>,
+}
+
+// what a variable is bound to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bound {
+    Value(ResultEval),
+    // argument passed by name: the expression is evaluated in the environment of the call
+    // each time the variable is used
+    Thunk(Arc<ASTNode>, Env),
+    // function of a program, its closure is built when the variable is used
+    Definition(Arc<Definitions>),
+}
+
+// functions of a program with the environment of the program, every function sees all of them
+// the closures are built on use, so the environments do not have to refer to themselves
+#[derive(Debug, PartialEq)]
+pub struct Definitions {
+    functions: Vec<(String, Vec<String>, Arc<ASTNode>)>,
+    env: Env,
+}
+
+impl Definitions {
+    // closure of the function `name`, later definitions of the same name shadow earlier ones
+    pub fn closure(self: &Arc<Self>, name: &str) -> Option<Closure> {
+        let (_, parameters, body) = self
+            .functions
+            .iter()
+            .rev()
+            .find(|(defined, _, _)| defined == name)?;
+        return Some(Closure {
+            parameters: parameters.clone(),
+            body: body.clone(),
+            env: self.scope(),
+        });
+    }
+
+    // environment of the program extended by all functions
+    fn scope(self: &Arc<Self>) -> Env {
+        let mut env = self.env.clone();
+        for (name, _, _) in &self.functions {
+            env = env.extend(name, Bound::Definition(self.clone()));
+        }
+        return env;
+    }
+}
+
+// value of a function expression: parameters and body together with the environment
+// the function was created in, so free variables of the body keep their values
+#[derive(Debug, Clone, PartialEq)]
+pub struct Closure {
+    pub parameters: Vec<String>,
+    pub body: Arc<ASTNode>,
+    pub env: Env,
+}
+
+impl Env {
+    // environment without any variables
+    pub fn new() -> Env {
+        return Env::default();
+    }
+
+    // environment with `name` bound to `value`, earlier bindings of `name` are shadowed
+    pub fn bind(&self, name: &str, value: ResultEval) -> Env {
+        return self.extend(name, Bound::Value(value));
+    }
+
+    // environment with `name` bound to the unevaluated `expression` and its environment
+    pub fn bind_thunk(&self, name: &str, expression: Arc<ASTNode>, env: Env) -> Env {
+        return self.extend(name, Bound::Thunk(expression, env));
+    }
+
+    // environment with the functions of a program, they can call each other and themselves
+    pub fn define(&self, definitions: &[Definition]) -> Env {
+        let functions = definitions
+            .iter()
+            .map(|definition| {
+                let body = Arc::new(definition.body.clone());
+                (definition.name.clone(), definition.parameters.clone(), body)
+            })
+            .collect();
+        let definitions = Arc::new(Definitions {
+            functions,
+            env: self.clone(),
+        });
+        return definitions.scope();
+    }
+
+    fn extend(&self, name: &str, value: Bound) -> Env {
+        let binding = Binding {
+            name: name.to_string(),
+            value,
+            next: self.head.clone(),
+        };
+        return Env {
+            head: Some(Arc::new(binding)),
+        };
+    }
+
+    // value of the innermost binding of `name`, None if it is unbound or not evaluated yet
+    pub fn get(&self, name: &str) -> Option<&ResultEval> {
+        return match self.lookup(name)? {
+            Bound::Value(value) => Some(value),
+            Bound::Thunk(..) | Bound::Definition(_) => None,
+        };
+    }
+
+    // innermost binding of `name`
+    pub fn lookup(&self, name: &str) -> Option<&Bound> {
+        return self
+            .iter()
+            .find(|(bound, _)| *bound == name)
+            .map(|(_, value)| value);
+    }
+
+    // bindings from the innermost to the outermost one, shadowed bindings included
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Bound)> {
+        let mut next = self.head.as_deref();
+        return std::iter::from_fn(move || {
+            let binding = next?;
+            next = binding.next.as_deref();
+            return Some((binding.name.as_str(), &binding.value));
+        });
+    }
+}
+
+// environment of the host program, later pairs shadow earlier ones
+impl<'a> FromIterator<(&'a str, ResultEval)> for Env {
+    fn from_iter<I: IntoIterator<Item = (&'a str, ResultEval)>>(bindings: I) -> Self {
+        let mut env = Env::new();
+        for (name, value) in bindings {
+            env = env.bind(name, value);
+        }
+        return env;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{evaluator::env::Env, parser::parser::ResultEval};
+
+    #[test]
+    fn env_shadowing_test() {
+        let outer = Env::from_iter([("x", ResultEval::Int(1)), ("y", ResultEval::Bool(true))]);
+        let inner = outer.bind("x", ResultEval::Int(2));
+        assert_eq!(inner.get("x"), Some(&ResultEval::Int(2)));
+        assert_eq!(inner.get("y"), Some(&ResultEval::Bool(true)));
+        assert_eq!(inner.get("z"), None);
+        // the outer environment is unchanged
+        assert_eq!(outer.get("x"), Some(&ResultEval::Int(1)));
+        let names: Vec<&str> = inner.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["x", "y", "x"]);
+    }
+}
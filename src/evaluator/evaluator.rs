@@ -1,95 +1,875 @@
-use crate::parser::parser::{ASTNode, ResultEval};
+use std::{cell::Cell, cmp::Ordering, fmt, sync::Arc};
 
-// evaluation based on AST, returns evaluated result
-pub fn eval(node: &ASTNode) -> Option<ResultEval> {
-    match node {
-        // num & bool: no changes
-        ASTNode::Number(value) => Some(ResultEval::Int(*value)),
-        ASTNode::Bool(value) => Some(ResultEval::Bool(*value)),
-        // Add: Checks if both nodes are int & returns sum, otherwise returns None
-        ASTNode::Add(left, right) => {
-            let l1 = eval(left);
-            let r1 = eval(right);
-            if !l1.is_some() && !r1.is_some() {
-                return None;
-            } else {
-                let val_l1 = match l1.unwrap() {
-                    ResultEval::Int(value) => value,
-                    _ => return None,
-                };
-                let val_l2 = match r1.unwrap() {
-                    ResultEval::Int(value) => value,
-                    _ => return None,
-                };
-                Some(ResultEval::Int(val_l1 + val_l2))
+use crate::{
+    evaluator::{
+        builtins::{Registry, Type},
+        env::{Bound, Closure, Env},
+    },
+    lexer::lexer::Span,
+    numeric::numeric::Rational,
+    parser::parser::{ASTNode, Definition, NodeKind, ResultEval},
+    typechecker::typechecker::{check_conditionals, TypeError},
+};
+
+// errors found while evaluating, each carries the span of the offending sub-expression
+#[derive(Debug, PartialEq, Clone)]
+pub enum EvalError {
+    // operator or function applied to values it is not defined for,
+    // e.g. mixing ints and bools, overflow or division by zero
+    Undefined {
+        span: Span,
+    },
+    // variable that is neither bound by `let` nor by the environment
+    Unbound {
+        name: String,
+        span: Span,
+    },
+    // call of a function that is not registered
+    UnknownFunction {
+        name: String,
+        span: Span,
+    },
+    // application of a value that is not a function, e.g. `1(2)`
+    NotAFunction {
+        found: ResultEval,
+        span: Span,
+    },
+    // function calls nested deeper than the limit of the evaluator, e.g. runaway recursion
+    CallDepth {
+        name: String,
+        limit: usize,
+        span: Span,
+    },
+    // expression nested too deeply for the stack the evaluator may use, e.g. a deep recursion
+    // within the call depth limit through a large function body
+    StackDepth {
+        limit: usize,
+        span: Span,
+    },
+    // call with the wrong number of arguments
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    // argument (counted from 1) that does not have the declared type of its parameter
+    ArgumentType {
+        name: String,
+        position: usize,
+        expected: Type,
+        found: ResultEval,
+        span: Span,
+    },
+    // function result that does not have the declared result type
+    ResultType {
+        name: String,
+        expected: Type,
+        found: ResultEval,
+        span: Span,
+    },
+    // conditional that breaks the type rules, e.g. branches of different types
+    Type(TypeError),
+    // value of a `print` statement that could not be written to the output
+    Output {
+        message: String,
+        span: Span,
+    },
+}
+
+impl EvalError {
+    pub fn span(&self) -> Span {
+        return match self {
+            EvalError::Undefined { span }
+            | EvalError::Unbound { span, .. }
+            | EvalError::UnknownFunction { span, .. }
+            | EvalError::NotAFunction { span, .. }
+            | EvalError::CallDepth { span, .. }
+            | EvalError::StackDepth { span, .. }
+            | EvalError::ArgumentCount { span, .. }
+            | EvalError::ArgumentType { span, .. }
+            | EvalError::ResultType { span, .. }
+            | EvalError::Output { span, .. } => *span,
+            EvalError::Type(error) => error.span(),
+        };
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            EvalError::Undefined { span } => {
+                write!(f, "expression at {} cannot be evaluated", span)
+            }
+            EvalError::Unbound { name, span } => {
+                write!(f, "unbound variable '{}' at {}", name, span)
+            }
+            EvalError::UnknownFunction { name, span } => {
+                write!(f, "unknown function '{}' at {}", name, span)
+            }
+            EvalError::NotAFunction { found, span } => {
+                write!(f, "value {} applied at {} is not a function", found, span)
             }
+            EvalError::CallDepth { name, limit, span } => write!(
+                f,
+                "call of '{}' at {} exceeds the call depth limit of {}",
+                name, span, limit
+            ),
+            EvalError::StackDepth { limit, span } => write!(
+                f,
+                "expression at {} is nested too deeply for the stack limit of {} bytes",
+                span, limit
+            ),
+            EvalError::ArgumentCount {
+                name,
+                expected,
+                found,
+                span,
+            } => write!(
+                f,
+                "function '{}' at {} expects {} argument(s) but received {}",
+                name, span, expected, found
+            ),
+            EvalError::ArgumentType {
+                name,
+                position,
+                expected,
+                found,
+                span,
+            } => write!(
+                f,
+                "argument {} of '{}' at {} has to be {} but is {}",
+                position, name, span, expected, found
+            ),
+            EvalError::ResultType {
+                name,
+                expected,
+                found,
+                span,
+            } => write!(
+                f,
+                "function '{}' at {} has to return {} but returned {}",
+                name, span, expected, found
+            ),
+            EvalError::Type(error) => write!(f, "{}", error),
+            EvalError::Output { message, span } => {
+                write!(f, "value at {} cannot be printed: {}", span, message)
+            }
+        };
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl From<TypeError> for EvalError {
+    fn from(error: TypeError) -> Self {
+        return EvalError::Type(error);
+    }
+}
+
+// how arguments are passed to functions defined in expressions and to `let` bodies
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Strategy {
+    // arguments are evaluated once before the body
+    #[default]
+    ByValue,
+    // arguments are evaluated in the environment of the call each time the parameter is used,
+    // so unused arguments are never evaluated: `(\x -> 1)(1 / 0)` is 1
+    ByName,
+}
+
+// default limit of nested function calls, enough for the 2 MiB stack Rust gives to spawned
+// threads; programs needing deeper recursion raise it and run on a larger stack
+pub const MAX_CALL_DEPTH: usize = 200;
+
+// least stack a nested call takes: `def f(n) = if n == 0 then 0 else f(n - 1)` takes about
+// 4 KiB per call in debug builds and 1.3 KiB in release builds, larger bodies more
+pub const STACK_PER_CALL: usize = 1024;
+
+// default stack an evaluation may use, 1.5 MiB of the 2 MiB stack of a spawned thread; the
+// rest is left to the caller and the type check
+pub const MAX_STACK: usize = 1536 * 1024;
+
+// evaluates ASTs, calls are resolved against the variables in scope and then against the
+// registry of functions
+pub struct Evaluator {
+    registry: Registry,
+    strategy: Strategy,
+    // number of function calls being evaluated and the limit of their nesting
+    depth: Cell<usize>,
+    max_depth: usize,
+    // address of the stack where the evaluation started and the bytes it may use beyond it
+    stack_start: Cell<usize>,
+    max_stack: usize,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        return Evaluator::new();
+    }
+}
+
+impl Evaluator {
+    // evaluator with the builtin functions
+    pub fn new() -> Evaluator {
+        return Evaluator::with_registry(Registry::builtins());
+    }
+
+    pub fn with_registry(registry: Registry) -> Evaluator {
+        return Evaluator {
+            registry,
+            strategy: Strategy::default(),
+            depth: Cell::new(0),
+            max_depth: MAX_CALL_DEPTH,
+            stack_start: Cell::new(0),
+            max_stack: MAX_STACK,
+        };
+    }
+
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+    }
+
+    // deeper nested calls of functions defined in expressions return `EvalError::CallDepth`
+    // instead of overflowing the stack
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    // expressions and calls nested deeper than `max_stack` bytes of stack return
+    // `EvalError::StackDepth` instead of overflowing it, the stack of the thread evaluating
+    // needs some room beyond that
+    pub fn set_max_stack(&mut self, max_stack: usize) {
+        self.max_stack = max_stack;
+    }
+
+    // functions callable from expressions, the host program can register its own
+    pub fn registry(&mut self) -> &mut Registry {
+        return &mut self.registry;
+    }
+
+    // evaluation based on AST, returns evaluated result
+    pub fn eval(&self, node: &ASTNode) -> Result<ResultEval, EvalError> {
+        return self.eval_with(node, &Env::new());
+    }
+
+    // evaluation with the variables bound in `env`, e.g. by the host program
+    // the conditionals of the expression are type-checked once before it is evaluated
+    pub fn eval_with(&self, node: &ASTNode, env: &Env) -> Result<ResultEval, EvalError> {
+        check_conditionals(node, env)?;
+        let start = 0u8;
+        self.stack_start.set(address(&start));
+        return self.evaluate(node, env);
+    }
+
+    fn evaluate(&self, node: &ASTNode, env: &Env) -> Result<ResultEval, EvalError> {
+        // every nested expression and call passes here, so the stack they use is checked once
+        // per level whatever the size of the frames in between
+        let here = 0u8;
+        if address(&here).abs_diff(self.stack_start.get()) > self.max_stack {
+            return Err(EvalError::StackDepth {
+                limit: self.max_stack,
+                span: node.span,
+            });
+        }
+        let undefined = || EvalError::Undefined { span: node.span };
+        return match &node.kind {
+            // num & bool: no changes
+            NodeKind::Number(value) => Ok(ResultEval::Int(*value)),
+            NodeKind::Float(value) => Ok(ResultEval::Float(*value)),
+            NodeKind::Rational(value) => Ok(ResultEval::Rational(*value)),
+            NodeKind::Bool(value) => Ok(ResultEval::Bool(*value)),
+            // placeholder of a recovering parse cannot be evaluated
+            NodeKind::Error => Err(undefined()),
+            // arithmetic, shifts, bitwise operators and comparisons: both operands are evaluated first
+            NodeKind::Add(..)
+            | NodeKind::Subtract(..)
+            | NodeKind::Multiply(..)
+            | NodeKind::Divide(..)
+            | NodeKind::Modulo(..)
+            | NodeKind::Power(..)
+            | NodeKind::ShiftLeft(..)
+            | NodeKind::ShiftRight(..)
+            | NodeKind::BitAnd(..)
+            | NodeKind::BitOr(..)
+            | NodeKind::Equal(..)
+            | NodeKind::NotEqual(..)
+            | NodeKind::Less(..)
+            | NodeKind::LessEqual(..)
+            | NodeKind::Greater(..)
+            | NodeKind::GreaterEqual(..) => self.binary(node, env),
+            NodeKind::Chain(links) => self.chain(env, links),
+            // And & Or: application of short-circuit evaluation
+            NodeKind::And(left, right) => self.logical(node, env, left, right, false),
+            NodeKind::Or(left, right) => self.logical(node, env, left, right, true),
+            NodeKind::Neg(operand) => self.unary(node, env, operand, negate),
+            NodeKind::Not(operand) => self.unary(node, env, operand, not),
+            NodeKind::Factorial(operand) => self.unary(node, env, operand, factorial),
+            NodeKind::Call(name, arguments) => self.call(node, env, name, arguments),
+            NodeKind::Apply(function, arguments) => {
+                self.apply_expression(node, env, function, arguments)
+            }
+            NodeKind::Lambda(parameters, body) => Ok(closure(env, parameters, body)),
+            NodeKind::Var(name) => self.variable(node, env, name),
+            NodeKind::Program(definitions, main) => self.program(env, definitions, main),
+            // Let: the body sees the value under the name, passed like a function argument
+            NodeKind::Let(name, value, body) => self.bind_in(env, name, value, body),
+            NodeKind::If(condition, then, otherwise) => {
+                self.conditional(node, env, condition, then, otherwise)
+            }
+        };
+    }
+
+    // the helpers below keep the stack frame of `evaluate` small, so deeply nested
+    // calls of functions fit on the stack
+
+    // short-circuit evaluation: `short_circuit` is the value of the left operand that
+    // decides the result without evaluating the right one
+    fn logical(
+        &self,
+        node: &ASTNode,
+        env: &Env,
+        left: &ASTNode,
+        right: &ASTNode,
+        short_circuit: bool,
+    ) -> Result<ResultEval, EvalError> {
+        let undefined = || EvalError::Undefined { span: node.span };
+        return match self.evaluate(left, env)? {
+            // true || x is true, false && x is false
+            ResultEval::Bool(value) if value == short_circuit => Ok(ResultEval::Bool(value)),
+            // Rest: the right side decides
+            ResultEval::Bool(_) => match self.evaluate(right, env)? {
+                ResultEval::Bool(value) => Ok(ResultEval::Bool(value)),
+                _ => Err(undefined()),
+            },
+            // numbers cannot be evaluated, bool is expected
+            _ => Err(undefined()),
+        };
+    }
+
+    // If: lazy, only the branch taken is evaluated
+    // the types of both branches were checked by `eval_with` before the evaluation
+    fn conditional(
+        &self,
+        node: &ASTNode,
+        env: &Env,
+        condition: &ASTNode,
+        then: &ASTNode,
+        otherwise: &ASTNode,
+    ) -> Result<ResultEval, EvalError> {
+        return match self.evaluate(condition, env)? {
+            ResultEval::Bool(true) => self.evaluate(then, env),
+            ResultEval::Bool(false) => self.evaluate(otherwise, env),
+            _ => Err(EvalError::Undefined { span: node.span }),
+        };
+    }
+
+    // Program: the main expression sees all functions
+    fn program(
+        &self,
+        env: &Env,
+        definitions: &[Definition],
+        main: &ASTNode,
+    ) -> Result<ResultEval, EvalError> {
+        let scope = env.define(definitions);
+        return self.evaluate(main, &scope);
+    }
+
+    fn bind_in(
+        &self,
+        env: &Env,
+        name: &str,
+        value: &ASTNode,
+        body: &ASTNode,
+    ) -> Result<ResultEval, EvalError> {
+        let scope = self.bind(env, name, value, env)?;
+        return self.evaluate(body, &scope);
+    }
+
+    fn apply_expression(
+        &self,
+        node: &ASTNode,
+        env: &Env,
+        function: &ASTNode,
+        arguments: &[ASTNode],
+    ) -> Result<ResultEval, EvalError> {
+        let function = self.evaluate(function, env)?;
+        return self.apply(node, env, None, function, arguments);
+    }
+
+    // value of the innermost binding of the name, arguments passed by name are evaluated now
+    fn variable(&self, node: &ASTNode, env: &Env, name: &str) -> Result<ResultEval, EvalError> {
+        return match env.lookup(name) {
+            Some(Bound::Value(value)) => Ok(value.clone()),
+            Some(Bound::Thunk(expression, scope)) => self.evaluate(expression, scope),
+            Some(Bound::Definition(definitions)) => {
+                Ok(ResultEval::Closure(definitions.closure(name).unwrap()))
+            }
+            None => Err(EvalError::Unbound {
+                name: name.to_string(),
+                span: node.span,
+            }),
+        };
+    }
+
+    // `scope` extended with `name` bound to the argument from `env`, according to the strategy
+    fn bind(
+        &self,
+        scope: &Env,
+        name: &str,
+        argument: &ASTNode,
+        env: &Env,
+    ) -> Result<Env, EvalError> {
+        return Ok(match self.strategy {
+            Strategy::ByValue => scope.bind(name, self.evaluate(argument, env)?),
+            Strategy::ByName => scope.bind_thunk(name, Arc::new(argument.clone()), env.clone()),
+        });
+    }
+
+    // applies a function value to the arguments, the body is evaluated in the environment of
+    // the function extended by its parameters
+    fn apply(
+        &self,
+        node: &ASTNode,
+        env: &Env,
+        name: Option<&str>,
+        function: ResultEval,
+        arguments: &[ASTNode],
+    ) -> Result<ResultEval, EvalError> {
+        let (body, scope) = self.enter(node, env, name, function, arguments)?;
+        let depth = self.depth.get();
+        self.depth.set(depth + 1);
+        let result = self.evaluate(&body, &scope);
+        self.depth.set(depth);
+        return result;
+    }
+
+    // body of the function and the environment to evaluate it in, after checking the
+    // arguments and the depth of nested calls
+    fn enter(
+        &self,
+        node: &ASTNode,
+        env: &Env,
+        name: Option<&str>,
+        function: ResultEval,
+        arguments: &[ASTNode],
+    ) -> Result<(Arc<ASTNode>, Env), EvalError> {
+        let ResultEval::Closure(closure) = &function else {
+            return Err(EvalError::NotAFunction {
+                found: function,
+                span: node.span,
+            });
+        };
+        let name = || name.map_or_else(|| function.to_string(), str::to_string);
+        if arguments.len() != closure.parameters.len() {
+            return Err(EvalError::ArgumentCount {
+                name: name(),
+                expected: closure.parameters.len(),
+                found: arguments.len(),
+                span: node.span,
+            });
+        }
+        let mut scope = closure.env.clone();
+        for (parameter, argument) in closure.parameters.iter().zip(arguments) {
+            scope = self.bind(&scope, parameter, argument, env)?;
         }
-        // Multiplication: Checks if both nodes are int & returns product, otherwise returns None
-        ASTNode::Multiply(left, right) => {
-            let l1 = eval(left);
-            let r1 = eval(right);
-            if !l1.is_some() && !r1.is_some() {
-                None
-            } else {
-                let val_l1 = match l1.unwrap() {
-                    ResultEval::Int(value) => value,
-                    _ => return None,
-                };
-                let val_l2 = match r1.unwrap() {
-                    ResultEval::Int(value) => value,
-                    _ => return None,
-                };
-                Some(ResultEval::Int(val_l1 * val_l2))
+        if self.depth.get() >= self.max_depth {
+            return Err(EvalError::CallDepth {
+                name: name(),
+                limit: self.max_depth,
+                span: node.span,
+            });
+        }
+        return Ok((closure.body.clone(), scope));
+    }
+
+    // evaluates both operands and applies `operation` to their values, left nested operators
+    // like `1 + 2 + 3` are evaluated in a loop instead of recursively, so long generated
+    // expressions fit on the stack
+    fn binary(&self, node: &ASTNode, env: &Env) -> Result<ResultEval, EvalError> {
+        let mut spine = Vec::new();
+        let mut leftmost = node;
+        while let Some((left, right, operation)) = binary_operation(&leftmost.kind) {
+            spine.push((leftmost.span, right, operation));
+            leftmost = left;
+        }
+        let mut value = self.evaluate(leftmost, env)?;
+        for (span, right, operation) in spine.into_iter().rev() {
+            let right = self.evaluate(right, env)?;
+            value = operation(value, right).ok_or(EvalError::Undefined { span })?;
+        }
+        return Ok(value);
+    }
+
+    // comparisons from left to right, each operand is evaluated once and the first false
+    // comparison decides the result without evaluating the operands after it
+    fn chain(&self, env: &Env, links: &[ASTNode]) -> Result<ResultEval, EvalError> {
+        let Some((first, _)) = links.first().and_then(|link| link.kind.comparison()) else {
+            unreachable!("Comparison expected")
+        };
+        let mut left = self.evaluate(first, env)?;
+        for link in links {
+            let Some((_, right, operation)) = binary_operation(&link.kind) else {
+                unreachable!("Comparison expected")
+            };
+            let right = self.evaluate(right, env)?;
+            match operation(left, right.clone()) {
+                Some(ResultEval::Bool(true)) => left = right,
+                Some(ResultEval::Bool(false)) => return Ok(ResultEval::Bool(false)),
+                _ => return Err(EvalError::Undefined { span: link.span }),
             }
         }
-        // Or: application of short-circuit evaluation
-        ASTNode::Or(left, right) => {
-            let l1 = eval(left);
-            // check if left side is not none
-            if !l1.is_some() {
-                None
-            } else {
-                match l1.unwrap() {
-                    // Int type returns none, bool is expected
-                    ResultEval::Int(_) => return None,
-                    // True returns true, OR operation is satisfied
-                    ResultEval::Bool(true) => return Some(ResultEval::Bool(true)),
-                    // Rest: recursively calling eval function on right side
-                    _ => {
-                        let r1 = eval(right);
-                        if !r1.is_some() {
-                            return None;
-                        } else {
-                            match r1.unwrap() {
-                                ResultEval::Int(_) => return None,
-                                ResultEval::Bool(true) => return Some(ResultEval::Bool(true)),
-                                _ => return Some(ResultEval::Bool(false)),
-                            }
-                        }
-                    }
-                }
+        return Ok(ResultEval::Bool(true));
+    }
+
+    fn unary(
+        &self,
+        node: &ASTNode,
+        env: &Env,
+        operand: &ASTNode,
+        operation: fn(ResultEval) -> Option<ResultEval>,
+    ) -> Result<ResultEval, EvalError> {
+        let value = self.evaluate(operand, env)?;
+        return operation(value).ok_or(EvalError::Undefined { span: node.span });
+    }
+
+    // a variable holding a function shadows the registered function of the same name
+    fn call(
+        &self,
+        node: &ASTNode,
+        env: &Env,
+        name: &str,
+        arguments: &[ASTNode],
+    ) -> Result<ResultEval, EvalError> {
+        if env.lookup(name).is_none() {
+            return self.call_registered(node, env, name, arguments);
+        }
+        let function = self.variable(node, env, name)?;
+        return self.apply(node, env, Some(name), function, arguments);
+    }
+
+    // calls a registered function after checking the number and types of the arguments,
+    // the arguments are evaluated from left to right
+    fn call_registered(
+        &self,
+        node: &ASTNode,
+        env: &Env,
+        name: &str,
+        arguments: &[ASTNode],
+    ) -> Result<ResultEval, EvalError> {
+        let span = node.span;
+        let Some(function) = self.registry.get(name) else {
+            return Err(EvalError::UnknownFunction {
+                name: name.to_string(),
+                span,
+            });
+        };
+        if arguments.len() != function.parameters.len() {
+            return Err(EvalError::ArgumentCount {
+                name: name.to_string(),
+                expected: function.parameters.len(),
+                found: arguments.len(),
+                span,
+            });
+        }
+        let mut values = Vec::new();
+        for (i, (argument, expected)) in arguments.iter().zip(&function.parameters).enumerate() {
+            let value = self.evaluate(argument, env)?;
+            if !expected.accepts(&value) {
+                return Err(EvalError::ArgumentType {
+                    name: name.to_string(),
+                    position: i + 1,
+                    expected: *expected,
+                    found: value,
+                    span: argument.span,
+                });
             }
+            values.push(value);
+        }
+        let result = (function.body)(&values).ok_or(EvalError::Undefined { span })?;
+        if !function.result.accepts(&result) {
+            return Err(EvalError::ResultType {
+                name: name.to_string(),
+                expected: function.result,
+                found: result,
+                span,
+            });
         }
+        return Ok(result);
     }
 }
 
+// operands and operation of a binary operator node, None for all other nodes
+fn binary_operation(kind: &NodeKind) -> Option<(&ASTNode, &ASTNode, Operation)> {
+    let operation: Operation = match kind {
+        NodeKind::Add(..) => add,
+        NodeKind::Subtract(..) => subtract,
+        NodeKind::Multiply(..) => multiply,
+        NodeKind::Divide(..) => divide,
+        NodeKind::Modulo(..) => modulo,
+        NodeKind::Power(..) => power,
+        NodeKind::ShiftLeft(..) => shift_left,
+        NodeKind::ShiftRight(..) => shift_right,
+        NodeKind::BitAnd(..) => bit_and,
+        NodeKind::BitOr(..) => bit_or,
+        // Comparisons: both sides need to be numbers or bools, false < true for bools
+        NodeKind::Equal(..) => |l, r| compare(&l, &r, Ordering::is_eq),
+        NodeKind::NotEqual(..) => |l, r| compare(&l, &r, Ordering::is_ne),
+        NodeKind::Less(..) => |l, r| compare(&l, &r, Ordering::is_lt),
+        NodeKind::LessEqual(..) => |l, r| compare(&l, &r, Ordering::is_le),
+        NodeKind::Greater(..) => |l, r| compare(&l, &r, Ordering::is_gt),
+        NodeKind::GreaterEqual(..) => |l, r| compare(&l, &r, Ordering::is_ge),
+        _ => return None,
+    };
+    let (NodeKind::Add(left, right)
+    | NodeKind::Subtract(left, right)
+    | NodeKind::Multiply(left, right)
+    | NodeKind::Divide(left, right)
+    | NodeKind::Modulo(left, right)
+    | NodeKind::Power(left, right)
+    | NodeKind::ShiftLeft(left, right)
+    | NodeKind::ShiftRight(left, right)
+    | NodeKind::BitAnd(left, right)
+    | NodeKind::BitOr(left, right)
+    | NodeKind::Equal(left, right)
+    | NodeKind::NotEqual(left, right)
+    | NodeKind::Less(left, right)
+    | NodeKind::LessEqual(left, right)
+    | NodeKind::Greater(left, right)
+    | NodeKind::GreaterEqual(left, right)) = kind
+    else {
+        unreachable!("Binary operator expected")
+    };
+    return Some((left, right, operation));
+}
+
+// operation of a binary operator on the values of its operands,
+// None if the operator is not defined for them
+type Operation = fn(ResultEval, ResultEval) -> Option<ResultEval>;
+
+// Lambda: the function keeps the environment it was created in
+fn closure(env: &Env, parameters: &[String], body: &ASTNode) -> ResultEval {
+    return ResultEval::Closure(Closure {
+        parameters: parameters.to_vec(),
+        body: Arc::new(body.clone()),
+        env: env.clone(),
+    });
+}
+
+// evaluation with the builtin functions, returns None if the AST cannot be evaluated
+pub fn eval(node: &ASTNode) -> Option<ResultEval> {
+    return Evaluator::new().eval(node).ok();
+}
+
+// evaluation with the builtin functions and the variables bound in `env`
+// address of a local variable, i.e. how deep the stack of the caller is
+fn address(local: &u8) -> usize {
+    return local as *const u8 as usize;
+}
+
+pub fn eval_with(node: &ASTNode, env: &Env) -> Result<ResultEval, EvalError> {
+    return Evaluator::new().eval_with(node, env);
+}
+
+// Add: returns the sum of two numbers, overflowing the 64 bit range cannot be evaluated
+pub fn add(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    return match promote(left, right)? {
+        Numbers::Ints(l, r) => Some(ResultEval::Int(l.checked_add(r)?)),
+        Numbers::Rationals(l, r) => Some(ResultEval::Rational(l.checked_add(r)?)),
+        Numbers::Floats(l, r) => float(l + r),
+    };
+}
+
+pub fn subtract(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    return match promote(left, right)? {
+        Numbers::Ints(l, r) => Some(ResultEval::Int(l.checked_sub(r)?)),
+        Numbers::Rationals(l, r) => Some(ResultEval::Rational(l.checked_sub(r)?)),
+        Numbers::Floats(l, r) => float(l - r),
+    };
+}
+
+// Multiplication: returns the product of two numbers
+pub fn multiply(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    return match promote(left, right)? {
+        Numbers::Ints(l, r) => Some(ResultEval::Int(l.checked_mul(r)?)),
+        Numbers::Rationals(l, r) => Some(ResultEval::Rational(l.checked_mul(r)?)),
+        Numbers::Floats(l, r) => float(l * r),
+    };
+}
+
+// Division & modulo: truncating on ints, exact on rationals,
+// division by zero cannot be evaluated
+pub fn divide(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    return match promote(left, right)? {
+        Numbers::Ints(l, r) => Some(ResultEval::Int(l.checked_div(r)?)),
+        Numbers::Rationals(l, r) => Some(ResultEval::Rational(l.checked_div(r)?)),
+        Numbers::Floats(_, 0.0) => None,
+        Numbers::Floats(l, r) => float(l / r),
+    };
+}
+
+pub fn modulo(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    return match promote(left, right)? {
+        Numbers::Ints(l, r) => Some(ResultEval::Int(l.checked_rem(r)?)),
+        Numbers::Rationals(l, r) => Some(ResultEval::Rational(l.checked_rem(r)?)),
+        Numbers::Floats(_, 0.0) => None,
+        Numbers::Floats(l, r) => float(l % r),
+    };
+}
+
+// Power: negative exponents cannot be evaluated on integers,
+// rationals stay exact for integer exponents only
+pub fn power(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    return match promote(left, right)? {
+        Numbers::Ints(l, r) => Some(ResultEval::Int(l.checked_pow(u32::try_from(r).ok()?)?)),
+        Numbers::Rationals(l, r) if r.is_integer() => {
+            Some(ResultEval::Rational(l.checked_pow(r.numerator())?))
+        }
+        Numbers::Rationals(l, r) => float(l.to_f64().powf(r.to_f64())),
+        Numbers::Floats(l, r) => float(l.powf(r)),
+    };
+}
+
+// Shifts: shift amount has to be in 0..64
+pub fn shift_left(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    let (ResultEval::Int(l), ResultEval::Int(r)) = (left, right) else {
+        return None;
+    };
+    return Some(ResultEval::Int(l.checked_shl(u32::try_from(r).ok()?)?));
+}
+
+pub fn shift_right(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    let (ResultEval::Int(l), ResultEval::Int(r)) = (left, right) else {
+        return None;
+    };
+    return Some(ResultEval::Int(l.checked_shr(u32::try_from(r).ok()?)?));
+}
+
+// Bitwise and/or: bitwise on ints, logical without short-circuit on bools
+pub fn bit_and(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    return match (left, right) {
+        (ResultEval::Int(l), ResultEval::Int(r)) => Some(ResultEval::Int(l & r)),
+        (ResultEval::Bool(l), ResultEval::Bool(r)) => Some(ResultEval::Bool(l & r)),
+        _ => None,
+    };
+}
+
+pub fn bit_or(left: ResultEval, right: ResultEval) -> Option<ResultEval> {
+    return match (left, right) {
+        (ResultEval::Int(l), ResultEval::Int(r)) => Some(ResultEval::Int(l | r)),
+        (ResultEval::Bool(l), ResultEval::Bool(r)) => Some(ResultEval::Bool(l | r)),
+        _ => None,
+    };
+}
+
+// Neg: negation of numbers, -i64::MIN overflows
+pub fn negate(value: ResultEval) -> Option<ResultEval> {
+    return match value {
+        ResultEval::Int(value) => Some(ResultEval::Int(value.checked_neg()?)),
+        ResultEval::Rational(value) => Some(ResultEval::Rational(value.checked_neg()?)),
+        ResultEval::Float(value) => Some(ResultEval::Float(-value)),
+        ResultEval::Bool(_) | ResultEval::Closure(_) => None,
+    };
+}
+
+// Not: negation of bool
+pub fn not(value: ResultEval) -> Option<ResultEval> {
+    return match value {
+        ResultEval::Bool(value) => Some(ResultEval::Bool(!value)),
+        _ => None,
+    };
+}
+
+// Factorial: product 1 * 2 * ... * n of a non-negative int
+pub fn factorial(value: ResultEval) -> Option<ResultEval> {
+    return match value {
+        ResultEval::Int(value) if value >= 0 => {
+            let product = (1..=value).try_fold(1i64, |acc, n| acc.checked_mul(n))?;
+            Some(ResultEval::Int(product))
+        }
+        _ => None,
+    };
+}
+
+// operands of an arithmetic operation after promotion along the numeric tower
+// Int -> Rational -> Float: both operands get the kind of the higher one
+enum Numbers {
+    Ints(i64, i64),
+    Rationals(Rational, Rational),
+    Floats(f64, f64),
+}
+
+// promotes two numbers to their common kind, None if one of them is no number
+fn promote(left: ResultEval, right: ResultEval) -> Option<Numbers> {
+    let rational = |value: &ResultEval| match value {
+        ResultEval::Int(value) => Some(Rational::from_int(*value)),
+        ResultEval::Rational(value) => Some(*value),
+        _ => None,
+    };
+    let float = |value: &ResultEval| match value {
+        ResultEval::Float(value) => Some(*value),
+        value => rational(value).map(|value| value.to_f64()),
+    };
+    return match (&left, &right) {
+        (ResultEval::Int(l), ResultEval::Int(r)) => Some(Numbers::Ints(*l, *r)),
+        (ResultEval::Float(_), _) | (_, ResultEval::Float(_)) => {
+            Some(Numbers::Floats(float(&left)?, float(&right)?))
+        }
+        _ => Some(Numbers::Rationals(rational(&left)?, rational(&right)?)),
+    };
+}
+
+// float results that are infinite or not a number cannot be evaluated
+fn float(value: f64) -> Option<ResultEval> {
+    if value.is_finite() {
+        return Some(ResultEval::Float(value));
+    }
+    return None;
+}
+
+// ordering of two numbers (of any kind) or two bools
+pub fn ordering(left: &ResultEval, right: &ResultEval) -> Option<Ordering> {
+    return match (left, right) {
+        (ResultEval::Bool(l), ResultEval::Bool(r)) => Some(l.cmp(r)),
+        (l, r) => match promote(l.clone(), r.clone())? {
+            Numbers::Ints(l, r) => Some(l.cmp(&r)),
+            Numbers::Rationals(l, r) => Some(l.cmp(&r)),
+            Numbers::Floats(l, r) => l.partial_cmp(&r),
+        },
+    };
+}
+
+// checks the ordering of two values
+fn compare(
+    left: &ResultEval,
+    right: &ResultEval,
+    check: fn(Ordering) -> bool,
+) -> Option<ResultEval> {
+    return Some(ResultEval::Bool(check(ordering(left, right)?)));
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
-        evaluator::evaluator::eval,
-        parser::parser::{ASTNode, ResultEval},
+        evaluator::{
+            builtins::Type,
+            env::Env,
+            evaluator::{eval, eval_with, EvalError, Evaluator, Strategy, MAX_CALL_DEPTH},
+        },
+        lexer::lexer::Lexer,
+        numeric::numeric::Rational,
+        parser::parser::{ASTNode, NodeKind, Parser, ResultEval, ShuntiyardParser},
+        typechecker::typechecker::TypeError,
     };
     use anyhow::Result;
+    use std::{cell::Cell, rc::Rc};
 
     #[test]
     fn eval_test() -> Result<()> {
-        let ast1 = ASTNode::Add(
-            Box::new(ASTNode::Number(1)),
-            Box::new(ASTNode::Multiply(
-                Box::new(ASTNode::Number(1)),
-                Box::new(ASTNode::Number(1)),
-            )),
-        );
+        let ast1 = ASTNode::from(NodeKind::Add(
+            NodeKind::Number(1).into(),
+            NodeKind::Multiply(NodeKind::Number(1).into(), NodeKind::Number(1).into()).into(),
+        ));
         let result = eval(&ast1).unwrap();
         let val_eval = match result {
             ResultEval::Int(value) => value,
@@ -97,13 +877,10 @@ mod test {
         };
         assert_eq!(2, val_eval);
 
-        let ast2 = ASTNode::Add(
-            Box::new(ASTNode::Number(0)),
-            Box::new(ASTNode::Multiply(
-                Box::new(ASTNode::Number(0)),
-                Box::new(ASTNode::Number(0)),
-            )),
-        );
+        let ast2 = ASTNode::from(NodeKind::Add(
+            NodeKind::Number(0).into(),
+            NodeKind::Multiply(NodeKind::Number(0).into(), NodeKind::Number(0).into()).into(),
+        ));
         let result = eval(&ast2).unwrap();
         let val_eval = match result {
             ResultEval::Int(value) => value,
@@ -111,13 +888,10 @@ mod test {
         };
         assert_eq!(0, val_eval);
 
-        let ast3 = ASTNode::Add(
-            Box::new(ASTNode::Number(0)),
-            Box::new(ASTNode::Multiply(
-                Box::new(ASTNode::Number(1)),
-                Box::new(ASTNode::Number(0)),
-            )),
-        );
+        let ast3 = ASTNode::from(NodeKind::Add(
+            NodeKind::Number(0).into(),
+            NodeKind::Multiply(NodeKind::Number(1).into(), NodeKind::Number(0).into()).into(),
+        ));
 
         let result = eval(&ast3).unwrap();
         let val_eval = match result {
@@ -126,13 +900,10 @@ mod test {
         };
         assert_eq!(0, val_eval);
 
-        let ast4 = ASTNode::Add(
-            Box::new(ASTNode::Number(0)),
-            Box::new(ASTNode::Multiply(
-                Box::new(ASTNode::Number(1)),
-                Box::new(ASTNode::Number(0)),
-            )),
-        );
+        let ast4 = ASTNode::from(NodeKind::Add(
+            NodeKind::Number(0).into(),
+            NodeKind::Multiply(NodeKind::Number(1).into(), NodeKind::Number(0).into()).into(),
+        ));
         let result = eval(&ast4).unwrap();
         let val_eval = match result {
             ResultEval::Int(value) => value,
@@ -146,11 +917,17 @@ mod test {
     fn parse_expression_bool_none_test() {
         let inputs = vec![
             (
-                ASTNode::Add(Box::new(ASTNode::Bool(true)), Box::new(ASTNode::Number(1))),
+                ASTNode::from(NodeKind::Add(
+                    NodeKind::Bool(true).into(),
+                    NodeKind::Number(1).into(),
+                )),
                 None::<ResultEval>,
             ),
             (
-                ASTNode::Or(Box::new(ASTNode::Bool(false)), Box::new(ASTNode::Number(1))),
+                ASTNode::from(NodeKind::Or(
+                    NodeKind::Bool(false).into(),
+                    NodeKind::Number(1).into(),
+                )),
                 None::<ResultEval>,
             ),
         ];
@@ -170,14 +947,17 @@ mod test {
     fn parse_expression_bool_valid_test() {
         let inputs = vec![
             (
-                ASTNode::Or(
-                    Box::new(ASTNode::Bool(true)),
-                    Box::new(ASTNode::Bool(false)),
-                ),
+                ASTNode::from(NodeKind::Or(
+                    NodeKind::Bool(true).into(),
+                    NodeKind::Bool(false).into(),
+                )),
                 ResultEval::Bool(true),
             ),
             (
-                ASTNode::Or(Box::new(ASTNode::Bool(true)), Box::new(ASTNode::Number(1))),
+                ASTNode::from(NodeKind::Or(
+                    NodeKind::Bool(true).into(),
+                    NodeKind::Number(1).into(),
+                )),
                 ResultEval::Bool(true),
             ),
         ];
@@ -192,4 +972,451 @@ mod test {
             assert_eq!(result_eval, exp_result);
         }
     }
+
+    #[test]
+    fn eval_overflow_test() {
+        let ast = ASTNode::from(NodeKind::Multiply(
+            NodeKind::Number(i64::MAX).into(),
+            NodeKind::Number(2).into(),
+        ));
+        assert_eq!(eval(&ast), None);
+
+        let ast = ASTNode::from(NodeKind::Add(
+            NodeKind::Number(1_000_000_000_000).into(),
+            NodeKind::Number(1).into(),
+        ));
+        assert_eq!(eval(&ast), Some(ResultEval::Int(1_000_000_000_001)));
+    }
+
+    #[test]
+    fn eval_long_chain_test() -> Result<()> {
+        // left nested operators are evaluated without recursion, so long generated
+        // expressions fit on the stack of a test thread
+        let input = vec!["1"; 10_000].join(" + ");
+        let ast = ShuntiyardParser::new(Lexer::new(&input)).parse()?;
+        assert_eq!(eval(&ast), Some(ResultEval::Int(10_000)));
+        return Ok(());
+    }
+
+    #[test]
+    fn eval_operators_test() -> Result<()> {
+        let inputs = vec![
+            ("10 - 2 - 3", Some(ResultEval::Int(5))),
+            ("7 / 2 + 7 % 2", Some(ResultEval::Int(4))),
+            ("2 ^ 10", Some(ResultEval::Int(1024))),
+            ("1 << 4 | 3 >> 1", Some(ResultEval::Int(17))),
+            ("6 & 3", Some(ResultEval::Int(2))),
+            (
+                "1 < 2 && 2 <= 2 && 3 > 2 && 2 >= 3",
+                Some(ResultEval::Bool(false)),
+            ),
+            ("1 + 1 == 2 && true != false", Some(ResultEval::Bool(true))),
+            ("!true | false & true", Some(ResultEval::Bool(false))),
+            ("false && 1", Some(ResultEval::Bool(false))),
+            ("1 / 0", None),
+            ("2 ^ 64", None),
+            ("1 << 64", None),
+            ("1 == true", None),
+            ("!1", None),
+            ("3! + 1", Some(ResultEval::Int(7))),
+            ("0!", Some(ResultEval::Int(1))),
+            ("21!", None),
+            ("(-1)!", None),
+        ];
+
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            let result_eval = eval(&parser.parse()?);
+            println!(
+                "Expression {:?} Evaluation {:?} expected value {:?}",
+                input, result_eval, exp_result
+            );
+            assert_eq!(result_eval, exp_result);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn eval_numeric_tower_test() -> Result<()> {
+        let rational = |num, den| Some(ResultEval::Rational(Rational::new(num, den).unwrap()));
+        let inputs = vec![
+            ("1 / 3r", rational(1, 3)),
+            ("0.1r + 0.2r == 0.3r", Some(ResultEval::Bool(true))),
+            ("0.1 + 0.2 == 0.3", Some(ResultEval::Bool(false))),
+            ("2 * 0.25r", rational(1, 2)),
+            ("0.5r ^ 2", rational(1, 4)),
+            ("1 + 0.5r + 0.25", Some(ResultEval::Float(1.75))),
+            ("7 / 2", Some(ResultEval::Int(3))),
+            ("7r / 2", rational(7, 2)),
+            ("7.5 % 2", Some(ResultEval::Float(1.5))),
+            ("1 < 1.5 && 1.5 < 2r", Some(ResultEval::Bool(true))),
+            ("1 == 1.0 && 1.0 == 1r", Some(ResultEval::Bool(true))),
+            ("4 ^ 0.5r", Some(ResultEval::Float(2.0))),
+            ("1.0 / 0", None),
+            ("1e300 * 1e300", None),
+            ("1.5 << 1", None),
+            ("true + 1.5", None),
+        ];
+
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            let result_eval = eval(&parser.parse()?);
+            println!(
+                "Expression {:?} Evaluation {:?} expected value {:?}",
+                input, result_eval, exp_result
+            );
+            assert_eq!(result_eval, exp_result);
+        }
+        Ok(())
+    }
+
+    // printed results parse back to the same value
+    #[test]
+    fn display_round_trip_test() -> Result<()> {
+        let inputs = vec![
+            "42", "1 / 3r", "0.1r", "5r", "3.14", "1e-9", "1e300", "2.0", "true", "-7", "-1 / 3r",
+            "-0.25r", "-2.5", "-1e-9",
+        ];
+        // i64::MIN has no literal, alone and as numerator of rationals
+        let min = [
+            "-9223372036854775807 - 1",
+            "(-9223372036854775807 - 1) / 1r",
+            "(-9223372036854775807 - 1) / 3r",
+            "(-9223372036854775807 - 1) / 5r",
+        ];
+
+        for input in inputs.into_iter().chain(min) {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            let result_eval = eval(&parser.parse()?).unwrap();
+            let printed = result_eval.to_string();
+            let mut parser = ShuntiyardParser::new(Lexer::new(&printed));
+            let reparsed = eval(&parser.parse()?).unwrap();
+            println!("Expression {:?} printed as {:?}", input, printed);
+            assert_eq!(result_eval, reparsed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn eval_prefix_test() -> Result<()> {
+        let inputs = vec![
+            ("-3 + 5", Some(ResultEval::Int(2))),
+            ("-2 ^ 2", Some(ResultEval::Int(-4))),
+            ("(-2) ^ 2", Some(ResultEval::Int(4))),
+            (
+                "- -0.5r",
+                ResultEval::Rational(Rational::new(1, 2).unwrap()).into(),
+            ),
+            ("-1.5 * 2", Some(ResultEval::Float(-3.0))),
+            ("!(1 < 2)", Some(ResultEval::Bool(false))),
+            ("-true", None),
+            ("!1", None),
+            ("3! + 1", Some(ResultEval::Int(7))),
+            ("0!", Some(ResultEval::Int(1))),
+            ("21!", None),
+            ("(-1)!", None),
+            ("-(-9223372036854775807 - 1)", None),
+        ];
+
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            let result_eval = eval(&parser.parse()?);
+            println!(
+                "Expression {:?} Evaluation {:?} expected value {:?}",
+                input, result_eval, exp_result
+            );
+            assert_eq!(result_eval, exp_result);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn eval_conditional_test() -> Result<()> {
+        let inputs = vec![
+            ("if 1 < 2 then 3 else 4", Some(ResultEval::Int(3))),
+            ("1 > 2 ? 3 : 4", Some(ResultEval::Int(4))),
+            (
+                "1 + (false ? 1 : true ? 2 : 3) * 10",
+                Some(ResultEval::Int(21)),
+            ),
+            // only the branch taken is evaluated
+            ("true ? 1 : 1 / 0", Some(ResultEval::Int(1))),
+            ("if false then 21! else 0", Some(ResultEval::Int(0))),
+            ("if 1 then 2 else 3", None),
+            ("if true then 1 else false", None),
+            // conditionals are checked before the evaluation, even if they are not reached
+            ("false && (if true then 1 else false)", None),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(eval(&parser.parse()?), exp_result, "{}", input);
+        }
+
+        let mut parser = ShuntiyardParser::new(Lexer::new("1 + (true ? 1 : 1.5)"));
+        let error = Evaluator::new().eval(&parser.parse()?).unwrap_err();
+        assert!(matches!(error, EvalError::Type(TypeError::Branches { .. })));
+        assert_eq!(
+            error.to_string(),
+            "branches of the conditional at 1:6 have different types int and float"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn eval_let_test() -> Result<()> {
+        let inputs = vec![
+            ("let x = 2 in x * x", ResultEval::Int(4)),
+            ("let x = 1 in let y = x + 1 in x + y", ResultEval::Int(3)),
+            // inner bindings shadow outer ones only inside their body
+            ("let x = 1 in (let x = 10 in x) + x", ResultEval::Int(11)),
+            ("let x = 1 in let x = x + 1 in x", ResultEval::Int(2)),
+            ("let b = 1 < 2 in b ? 0.5 : 1.5", ResultEval::Float(0.5)),
+            // host variables, shadowed by let
+            ("n * 2 + (let n = 0 in n)", ResultEval::Int(42)),
+        ];
+        let env = Env::from_iter([("n", ResultEval::Int(21))]);
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(eval_with(&parser.parse()?, &env)?, exp_result, "{}", input);
+        }
+
+        // a binding is not visible outside of its body
+        let mut parser = ShuntiyardParser::new(Lexer::new("(let x = 1 in x) + x"));
+        let error = eval_with(&parser.parse()?, &Env::new()).unwrap_err();
+        assert!(matches!(&error, EvalError::Unbound { name, .. } if name == "x"));
+        assert_eq!(error.to_string(), "unbound variable 'x' at 1:20");
+
+        // the branches of a conditional are checked with the types of the variables
+        let mut parser = ShuntiyardParser::new(Lexer::new("if true then n else b"));
+        let env = Env::from_iter([("n", ResultEval::Int(1)), ("b", ResultEval::Bool(true))]);
+        let error = eval_with(&parser.parse()?, &env).unwrap_err();
+        assert!(matches!(error, EvalError::Type(TypeError::Branches { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn eval_lambda_test() -> Result<()> {
+        let inputs = vec![
+            ("(\\x -> x + 1)(2)", ResultEval::Int(3)),
+            (
+                "let add = fn(x, y) => x + y in add(1, 2)",
+                ResultEval::Int(3),
+            ),
+            ("(fn() => true)()", ResultEval::Bool(true)),
+            ("(\\x -> \\y -> x - y)(5)(3)", ResultEval::Int(2)),
+            // functions are values: passed as arguments and returned from calls
+            (
+                "let twice = \\f -> \\x -> f(f(x)) in twice(\\x -> x * 3)(2)",
+                ResultEval::Int(18),
+            ),
+            // closures keep the variables of the environment they were created in
+            (
+                "let k = (let y = 10 in \\x -> x + y) in let y = 1 in k(y)",
+                ResultEval::Int(11),
+            ),
+            ("(\\x -> x * n)(2)", ResultEval::Int(42)),
+            // variables holding functions shadow builtins of the same name
+            ("let max = \\x -> x in max(7)", ResultEval::Int(7)),
+        ];
+        let env = Env::from_iter([("n", ResultEval::Int(21))]);
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(eval_with(&parser.parse()?, &env)?, exp_result, "{}", input);
+        }
+
+        let mut parser = ShuntiyardParser::new(Lexer::new("fn(x, y) => x"));
+        assert_eq!(eval_with(&parser.parse()?, &env)?.to_string(), "<fn(x, y)>");
+
+        let errors = [
+            ("1(2)", "value 1 applied at 1:1 is not a function"),
+            (
+                "let max = 1 in max(1, 2)",
+                "value 1 applied at 1:16 is not a function",
+            ),
+            (
+                "(\\x -> x)(1, 2)",
+                "function '<fn(x)>' at 1:2 expects 1 argument(s) but received 2",
+            ),
+            (
+                "(\\x -> x) == (\\x -> x)",
+                "expression at 1:2 cannot be evaluated",
+            ),
+        ];
+        for (input, exp_error) in errors {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            let error = eval_with(&parser.parse()?, &env).unwrap_err();
+            assert_eq!(error.to_string(), exp_error, "{}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn eval_program_test() -> Result<()> {
+        let fact = "def fact(n) = if n == 0 then 1 else n * fact(n - 1); ";
+        let inputs =
+            vec![
+            (format!("{}fact(5)", fact), ResultEval::Int(120)),
+            // definitions may call the ones defined after them
+            (
+                "def even(n) = n == 0 || odd(n - 1); def odd(n) = n != 0 && even(n - 1); odd(7)"
+                    .to_string(),
+                ResultEval::Bool(true),
+            ),
+            // definitions see the host variables and are values themselves
+            ("def scale(x) = x * n; scale(2)".to_string(), ResultEval::Int(42)),
+            (
+                "def inc(x) = x + 1; def twice(f, x) = f(f(x)); twice(inc, 0)".to_string(),
+                ResultEval::Int(2),
+            ),
+            (
+                "def adder(n) = \\x -> x + n; adder(1)(2)".to_string(),
+                ResultEval::Int(3),
+            ),
+            // parameters and let bindings shadow definitions
+            ("def f() = 1; let f = \\x -> x in f(2)".to_string(), ResultEval::Int(2)),
+        ];
+        let env = Env::from_iter([("n", ResultEval::Int(21))]);
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(&input));
+            assert_eq!(eval_with(&parser.parse()?, &env)?, exp_result, "{}", input);
+        }
+
+        // runaway recursion stops at the call depth limit
+        let mut parser = ShuntiyardParser::new(Lexer::new("def loop(n) = loop(n + 1); loop(0)"));
+        let error = eval_with(&parser.parse()?, &env).unwrap_err();
+        assert!(matches!(
+            error,
+            EvalError::CallDepth {
+                limit: MAX_CALL_DEPTH,
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "call of 'loop' at 1:15 exceeds the call depth limit of 200"
+        );
+
+        let mut evaluator = Evaluator::new();
+        evaluator.set_max_depth(5);
+        let parse = |input: String| ShuntiyardParser::new(Lexer::new(&input)).parse();
+        let error = evaluator
+            .eval(&parse(format!("{}fact(5)", fact))?)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "call of 'fact' at 1:41 exceeds the call depth limit of 5"
+        );
+        // the depth is reset after an error
+        let ast = parse(format!("{}fact(4)", fact))?;
+        assert_eq!(evaluator.eval(&ast)?, ResultEval::Int(24));
+
+        // recursion within the call depth limit and nested expressions stop at the stack limit
+        let mut evaluator = Evaluator::new();
+        evaluator.set_max_depth(1_000_000);
+        evaluator.set_max_stack(64 * 1024);
+        let deep = "def f(n) = if n == 0 then 0 else 1 + f(n - 1); f(100000)";
+        let error = evaluator.eval(&parse(deep.to_string())?).unwrap_err();
+        assert!(matches!(error, EvalError::StackDepth { limit: 65536, .. }));
+        let nested = format!("{}1{}", "-(".repeat(500), ")".repeat(500));
+        let error = evaluator.eval(&parse(nested)?).unwrap_err();
+        assert!(matches!(error, EvalError::StackDepth { .. }));
+        assert_eq!(
+            evaluator.eval(&parse(format!("{}fact(4)", fact))?)?,
+            ResultEval::Int(24)
+        );
+
+        let mut parser = ShuntiyardParser::new(Lexer::new("def f(x) = x; f(1, 2)"));
+        let error = eval_with(&parser.parse()?, &env).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "function 'f' at 1:15 expects 1 argument(s) but received 2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn eval_chain_test() -> Result<()> {
+        let inputs = vec![
+            ("0 < x <= 10", ResultEval::Bool(true)),
+            ("0 < x < 5", ResultEval::Bool(false)),
+            ("1 <= 1 < 2 != 3", ResultEval::Bool(true)),
+            ("false < true != false", ResultEval::Bool(true)),
+            ("(3 > 2 > 1) == (3 > 2 && 2 > 1)", ResultEval::Bool(true)),
+            ("0.5 < 1 < 3/2r", ResultEval::Bool(true)),
+        ];
+        let env = Env::from_iter([("x", ResultEval::Int(7))]);
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(eval_with(&parser.parse()?, &env)?, exp_result, "{}", input);
+        }
+
+        // the operand shared by two comparisons is evaluated once,
+        // the operands after the first false comparison not at all
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let mut evaluator = Evaluator::new();
+        evaluator
+            .registry()
+            .register("count", vec![], Type::Int, move |_| {
+                counter.set(counter.get() + 1);
+                return Some(ResultEval::Int(counter.get()));
+            });
+        let parse = |input| ShuntiyardParser::new(Lexer::new(input)).parse();
+        assert_eq!(
+            evaluator.eval(&parse("0 < count() <= 1")?)?,
+            ResultEval::Bool(true)
+        );
+        assert_eq!(calls.get(), 1);
+        assert_eq!(
+            evaluator.eval(&parse("1 < 0 < count()")?)?,
+            ResultEval::Bool(false)
+        );
+        assert_eq!(calls.get(), 1);
+
+        let mut parser = ShuntiyardParser::new(Lexer::new("1 < 2 < true"));
+        let error = eval_with(&parser.parse()?, &env).unwrap_err();
+        assert_eq!(error.to_string(), "expression at 1:5 cannot be evaluated");
+        Ok(())
+    }
+
+    #[test]
+    fn eval_strategy_test() -> Result<()> {
+        // evaluator with a function counting how often it is called
+        let evaluator = |strategy| {
+            let calls = Rc::new(Cell::new(0));
+            let counter = calls.clone();
+            let mut evaluator = Evaluator::new();
+            evaluator.set_strategy(strategy);
+            evaluator
+                .registry()
+                .register("count", vec![], Type::Int, move |_| {
+                    counter.set(counter.get() + 1);
+                    return Some(ResultEval::Int(counter.get()));
+                });
+            return (evaluator, calls);
+        };
+        let parse = |input| ShuntiyardParser::new(Lexer::new(input)).parse();
+
+        // by value the argument is evaluated once, by name once per use
+        let ast = parse("(\\x -> x + x)(count())")?;
+        let (by_value, calls) = evaluator(Strategy::ByValue);
+        assert_eq!(by_value.eval(&ast)?, ResultEval::Int(2));
+        assert_eq!(calls.get(), 1);
+        let (by_name, calls) = evaluator(Strategy::ByName);
+        assert_eq!(by_name.eval(&ast)?, ResultEval::Int(3));
+        assert_eq!(calls.get(), 2);
+
+        // unused arguments and bindings are not evaluated by name
+        for input in ["(\\x -> 1)(1 / 0)", "let x = 1 / 0 in 1"] {
+            let ast = parse(input)?;
+            assert!(by_value.eval(&ast).is_err(), "{}", input);
+            assert_eq!(by_name.eval(&ast)?, ResultEval::Int(1), "{}", input);
+        }
+
+        // arguments passed by name are evaluated with the variables of the caller
+        let ast = parse("let y = 1 in (\\x -> let y = 2 in x + y)(y)")?;
+        assert_eq!(by_name.eval(&ast)?, ResultEval::Int(3));
+        assert_eq!(by_value.eval(&ast)?, ResultEval::Int(3));
+        Ok(())
+    }
 }
@@ -0,0 +1,213 @@
+use std::io;
+
+use crate::{
+    evaluator::{
+        env::Env,
+        evaluator::{EvalError, Evaluator},
+    },
+    parser::parser::{ResultEval, Statement},
+};
+
+// destination of the values printed by scripts, every value is written on its own line
+// of anything implementing `io::Write`, e.g. `io::stdout()` or a `Vec<u8>` in tests
+pub trait Writer {
+    fn print(&mut self, value: &ResultEval) -> io::Result<()>;
+}
+
+impl<W: io::Write> Writer for W {
+    fn print(&mut self, value: &ResultEval) -> io::Result<()> {
+        return writeln!(self, "{}", value);
+    }
+}
+
+// runs the statements of scripts one after the other
+// the store holds the variables and functions of the script: assignments and definitions
+// replace it, so they are visible to the statements after them
+pub struct Interpreter<W: Writer> {
+    evaluator: Evaluator,
+    store: Env,
+    output: W,
+}
+
+impl<W: Writer> Interpreter<W> {
+    // interpreter with the builtin functions, printing to `output`
+    pub fn new(output: W) -> Interpreter<W> {
+        return Interpreter::with_evaluator(Evaluator::new(), output);
+    }
+
+    pub fn with_evaluator(evaluator: Evaluator, output: W) -> Interpreter<W> {
+        return Interpreter {
+            evaluator,
+            store: Env::new(),
+            output,
+        };
+    }
+
+    // current value of a variable of the script, None if it was never assigned
+    pub fn get(&self, name: &str) -> Option<&ResultEval> {
+        return self.store.get(name);
+    }
+
+    // output the printed values were written to
+    pub fn output(&self) -> &W {
+        return &self.output;
+    }
+
+    // runs the statements in order and stops at the first error,
+    // the assignments before it stay in the store
+    pub fn run(&mut self, statements: &[Statement]) -> Result<(), EvalError> {
+        let mut statements = statements.iter().peekable();
+        while let Some(statement) = statements.next() {
+            match statement {
+                // adjacent definitions are defined together, so they can call each other
+                Statement::Definition(definition) => {
+                    let mut definitions = vec![definition.clone()];
+                    while let Some(Statement::Definition(next)) = statements.peek() {
+                        definitions.push(next.clone());
+                        statements.next();
+                    }
+                    self.store = self.store.define(&definitions);
+                }
+                Statement::Assign(name, value) => {
+                    let value = self.evaluator.eval_with(value, &self.store)?;
+                    self.store = self.store.bind(name, value);
+                }
+                Statement::Print(value) => {
+                    let span = value.span;
+                    let value = self.evaluator.eval_with(value, &self.store)?;
+                    self.output
+                        .print(&value)
+                        .map_err(|error| EvalError::Output {
+                            message: error.to_string(),
+                            span,
+                        })?;
+                }
+                Statement::Expression(value) => {
+                    self.evaluator.eval_with(value, &self.store)?;
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use anyhow::Result;
+
+    use crate::{
+        evaluator::{
+            evaluator::EvalError,
+            interpreter::{Interpreter, Writer},
+        },
+        lexer::lexer::Lexer,
+        parser::{
+            parser::{ResultEval, Statement},
+            pratt::PrattParser,
+        },
+    };
+
+    fn parse(input: &str) -> Result<Vec<Statement>> {
+        return Ok(PrattParser::new(Lexer::new(input)).parse_script()?);
+    }
+
+    // runs the script and returns what it printed
+    fn run(input: &str) -> Result<String> {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter.run(&parse(input)?)?;
+        return Ok(String::from_utf8(interpreter.output().clone())?);
+    }
+
+    #[test]
+    fn run_script_test() -> Result<()> {
+        let inputs = [
+            ("x = 1 + 1; y = x * x; print(y);", "4\n"),
+            (
+                "print 1; print 0.5r; print true; print \\x -> x;",
+                "1\n0.5r\ntrue\n<fn(x)>\n",
+            ),
+            // assignments replace the value for the statements after them
+            ("x = 1; print x; x = x + 1; print x;", "1\n2\n"),
+            // closures keep the value a variable had when they were created
+            ("n = 1; add = \\x -> x + n; n = 10; print add(n);", "11\n"),
+            // adjacent definitions can call each other
+            (
+                "def even(n) = n == 0 || odd(n - 1);\n\
+                 def odd(n) = n != 0 && even(n - 1);\n\
+                 print even(10); print odd(10);",
+                "true\nfalse\n",
+            ),
+            ("k = 3; def scale(x) = k * x; print scale(2);", "6\n"),
+            // expression statements are evaluated, but print nothing
+            ("1 + 1; max(1, 2);", ""),
+        ];
+        for (input, exp_output) in inputs {
+            assert_eq!(run(input)?, exp_output, "{}", input);
+        }
+
+        let mut interpreter = Interpreter::new(io::sink());
+        interpreter.run(&parse("x = 2; y = x < 3;")?)?;
+        assert_eq!(interpreter.get("x"), Some(&ResultEval::Int(2)));
+        assert_eq!(interpreter.get("y"), Some(&ResultEval::Bool(true)));
+        assert_eq!(interpreter.get("z"), None);
+        Ok(())
+    }
+
+    // writer collecting the printed values instead of their text
+    struct Values(Vec<ResultEval>);
+
+    impl Writer for Values {
+        fn print(&mut self, value: &ResultEval) -> io::Result<()> {
+            self.0.push(value.clone());
+            return Ok(());
+        }
+    }
+
+    // writer whose output is gone
+    struct Closed;
+
+    impl Writer for Closed {
+        fn print(&mut self, _: &ResultEval) -> io::Result<()> {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+        }
+    }
+
+    #[test]
+    fn run_script_errors_test() -> Result<()> {
+        // the statements before an error have been run
+        let mut interpreter = Interpreter::new(Values(Vec::new()));
+        let error = interpreter
+            .run(&parse("x = 1; print x; print y; x = 2;")?)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "unbound variable 'y' at 1:23");
+        assert_eq!(interpreter.output().0, vec![ResultEval::Int(1)]);
+        assert_eq!(interpreter.get("x"), Some(&ResultEval::Int(1)));
+
+        let errors = [
+            ("x = 1 / 0;", "expression at 1:5 cannot be evaluated"),
+            // definitions separated by other statements cannot call the later ones
+            (
+                "def f() = g(); x = 1; def g() = 1; f();",
+                "unknown function 'g' at 1:11",
+            ),
+            (
+                "x = \\y -> y; x + 1;",
+                "expression at 1:14 cannot be evaluated",
+            ),
+        ];
+        for (input, exp_error) in errors {
+            let mut interpreter = Interpreter::new(io::sink());
+            let error = interpreter.run(&parse(input)?).unwrap_err();
+            assert_eq!(error.to_string(), exp_error, "{}", input);
+        }
+
+        let error = Interpreter::new(Closed)
+            .run(&parse("print 1 + 1;")?)
+            .unwrap_err();
+        assert!(matches!(error, EvalError::Output { .. }));
+        assert_eq!(error.to_string(), "value at 1:7 cannot be printed: closed");
+        Ok(())
+    }
+}
@@ -1 +1,4 @@
+pub mod builtins;
+pub mod env;
 pub mod evaluator;
+pub mod interpreter;
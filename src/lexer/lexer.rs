@@ -1,33 +1,109 @@
-use std::fmt::Display;
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    io::{self, Read},
+    str::Chars,
+};
 
-use anyhow::Result;
+use crate::numeric::numeric::Rational;
 
 /// represents different types of tokens recognized by the lexer
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Token {
-    One,
-    Zero,
+    Int(i64), // number literals, store their value
+    Float(f64),
+    Rational(Rational),
+    Ident(String), // names, e.g. of functions
     LPar,
     RPar,
+    Comma,
     Mult(u8), // stores precedence of Operation
+    Div(u8),
+    Mod(u8),
     Add(u8),
+    Sub(u8),
+    Pow(u8),
+    Shl(u8),
+    Shr(u8),
+    BitAnd(u8),
+    BitOr(u8),
+    Eq(u8),
+    Ne(u8),
+    Lt(u8),
+    Le(u8),
+    Gt(u8),
+    Ge(u8),
+    And(u8),
     Or(u8),
+    Not(u8), // prefix operator
     True,
     False,
-    Eof, // end of file
+    If, // conditional `if c then a else b` or `c ? a : b`
+    Then,
+    Else,
+    Question,
+    Colon,
+    Let, // binding `let x = e1 in e2`
+    In,
+    Assign,
+    Lambda, // function `\x -> e` or `fn(x, y) => e`
+    Fn,
+    Arrow,
+    FatArrow,
+    Def, // program `def f(x) = e; main`
+    Semicolon,
+    Print, // statement `print e;` of a script
+    Eof,   // end of file
 }
 
 impl Display for Token {
     /// Pretty printing
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         return match self {
-            Token::One => write!(f, "1"),
-            Token::Zero => write!(f, "0"),
+            Token::Int(value) => write!(f, "{}", value),
+            Token::Float(value) => write!(f, "{:?}", value),
+            Token::Rational(value) => match value.to_decimal() {
+                Some(decimal) => write!(f, "{}r", decimal),
+                None => write!(f, "{}r", value),
+            },
+            Token::Ident(name) => write!(f, "{}", name),
             Token::LPar => write!(f, "("),
             Token::RPar => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+            Token::If => write!(f, "if"),
+            Token::Then => write!(f, "then"),
+            Token::Else => write!(f, "else"),
+            Token::Question => write!(f, "?"),
+            Token::Colon => write!(f, ":"),
+            Token::Let => write!(f, "let"),
+            Token::In => write!(f, "in"),
+            Token::Assign => write!(f, "="),
+            Token::Lambda => write!(f, "\\"),
+            Token::Fn => write!(f, "fn"),
+            Token::Arrow => write!(f, "->"),
+            Token::FatArrow => write!(f, "=>"),
+            Token::Def => write!(f, "def"),
+            Token::Semicolon => write!(f, ";"),
+            Token::Print => write!(f, "print"),
             Token::Mult(_) => write!(f, "*"),
+            Token::Div(_) => write!(f, "/"),
+            Token::Mod(_) => write!(f, "%"),
             Token::Add(_) => write!(f, "+"),
+            Token::Sub(_) => write!(f, "-"),
+            Token::Pow(_) => write!(f, "^"),
+            Token::Shl(_) => write!(f, "<<"),
+            Token::Shr(_) => write!(f, ">>"),
+            Token::BitAnd(_) => write!(f, "&"),
+            Token::BitOr(_) => write!(f, "|"),
+            Token::Eq(_) => write!(f, "=="),
+            Token::Ne(_) => write!(f, "!="),
+            Token::Lt(_) => write!(f, "<"),
+            Token::Le(_) => write!(f, "<="),
+            Token::Gt(_) => write!(f, ">"),
+            Token::Ge(_) => write!(f, ">="),
+            Token::And(_) => write!(f, "&&"),
             Token::Or(_) => write!(f, "||"),
+            Token::Not(_) => write!(f, "!"),
             Token::True => write!(f, "true"),
             Token::False => write!(f, "false"),
             Token::Eof => write!(f, "Eof"),
@@ -35,89 +111,797 @@ impl Display for Token {
     }
 }
 
+impl Token {
+    /// precedence of an operator token, None for all other tokens
+    pub fn precedence(&self) -> Option<u8> {
+        return match self {
+            Token::Mult(prec)
+            | Token::Div(prec)
+            | Token::Mod(prec)
+            | Token::Add(prec)
+            | Token::Sub(prec)
+            | Token::Pow(prec)
+            | Token::Shl(prec)
+            | Token::Shr(prec)
+            | Token::BitAnd(prec)
+            | Token::BitOr(prec)
+            | Token::Eq(prec)
+            | Token::Ne(prec)
+            | Token::Lt(prec)
+            | Token::Le(prec)
+            | Token::Gt(prec)
+            | Token::Ge(prec)
+            | Token::And(prec)
+            | Token::Or(prec)
+            | Token::Not(prec) => Some(*prec),
+            _ => None,
+        };
+    }
+}
+
+/// spellings of all operators and symbolic constants with their tokens, longer spellings
+/// are listed first so that the scanner always takes the longest match (`<=` before `<`)
+/// Unicode spellings map onto the same tokens as their ASCII forms
+const SYMBOLS: [(&str, Token); 36] = [
+    ("&&", Token::And(1)),
+    ("||", Token::Or(0)),
+    ("==", Token::Eq(2)),
+    ("!=", Token::Ne(2)),
+    ("<=", Token::Le(2)),
+    (">=", Token::Ge(2)),
+    ("<<", Token::Shl(5)),
+    (">>", Token::Shr(5)),
+    ("->", Token::Arrow),
+    ("=>", Token::FatArrow),
+    ("<", Token::Lt(2)),
+    (">", Token::Gt(2)),
+    ("|", Token::BitOr(3)),
+    ("&", Token::BitAnd(4)),
+    ("+", Token::Add(6)),
+    ("-", Token::Sub(6)),
+    ("*", Token::Mult(7)),
+    ("/", Token::Div(7)),
+    ("%", Token::Mod(7)),
+    ("^", Token::Pow(9)),
+    ("!", Token::Not(8)),
+    (",", Token::Comma),
+    ("=", Token::Assign),
+    ("?", Token::Question),
+    (":", Token::Colon),
+    (";", Token::Semicolon),
+    ("\\", Token::Lambda),
+    ("\u{2228}", Token::Or(0)),  // ∨
+    ("\u{2227}", Token::And(1)), // ∧
+    ("\u{ac}", Token::Not(8)),   // ¬
+    ("\u{d7}", Token::Mult(7)),  // ×
+    ("\u{b7}", Token::Mult(7)),  // ·
+    ("\u{22a4}", Token::True),   // ⊤
+    ("\u{22a5}", Token::False),  // ⊥
+    ("\u{3bb}", Token::Lambda),  // λ
+    ("\u{2192}", Token::Arrow),  // →
+];
+
+/// location of a token or AST node in the input string
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Span {
+    /// byte range `start..end` in the input
+    pub start: usize,
+    pub end: usize,
+    /// line and column of `start`, both starting at 1, the column counts characters
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// smallest span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Span {
+        let (first, last) = if self.start <= other.start {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        return Span {
+            start: first.start,
+            end: first.end.max(last.end),
+            line: first.line,
+            column: first.column,
+        };
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}:{}", self.line, self.column);
+    }
+}
+
+/// whitespace and comments between tokens, stores the exact source text
+#[derive(Debug, PartialEq, Clone)]
+pub enum Trivia {
+    Whitespace(String),
+    LineComment(String),  // `# ...` up to the end of the line
+    BlockComment(String), // `/* ... */`
+}
+
+impl Trivia {
+    pub fn text(&self) -> &str {
+        return match self {
+            Trivia::Whitespace(text) | Trivia::LineComment(text) | Trivia::BlockComment(text) => {
+                text
+            }
+        };
+    }
+}
+
+/// token together with its location in the input string
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+    /// lossless mode only: trivia in front of the token & source text of the token itself,
+    /// trivia at the end of the input is attached to Eof
+    pub trivia: Vec<Trivia>,
+    pub text: String,
+}
+
+impl SpannedToken {
+    pub fn new(token: Token, span: Span) -> SpannedToken {
+        return SpannedToken {
+            token,
+            span,
+            trivia: Vec::new(),
+            text: String::new(),
+        };
+    }
+}
+
+/// rebuilds the exact source text from tokens read in lossless mode
+pub fn source_text(tokens: &[SpannedToken]) -> String {
+    let mut text = String::new();
+    for spanned in tokens {
+        for trivia in &spanned.trivia {
+            text.push_str(trivia.text());
+        }
+        text.push_str(&spanned.text);
+    }
+    return text;
+}
+
+/// errors found while tokenizing, each carries the span of the offending input
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexError {
+    /// character that cannot start any token, e.g. `$`
+    UnexpectedCharacter { ch: char, span: Span },
+    /// number literal with digits that are not allowed for its radix or trailing letters
+    InvalidNumber { literal: String, span: Span },
+    /// number literal that does not fit into its 64 bit type
+    NumberOverflow { literal: String, span: Span },
+    /// block comment without closing `*/`
+    UnterminatedComment { span: Span },
+    /// reading the input failed, the input ends at this position
+    Io { message: String, span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        return match self {
+            LexError::UnexpectedCharacter { span, .. }
+            | LexError::InvalidNumber { span, .. }
+            | LexError::NumberOverflow { span, .. }
+            | LexError::UnterminatedComment { span }
+            | LexError::Io { span, .. } => *span,
+        };
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            LexError::UnexpectedCharacter { ch, span } => {
+                write!(f, "unexpected character '{}' at {}", ch, span)
+            }
+            LexError::InvalidNumber { literal, span } => {
+                write!(f, "invalid number literal '{}' at {}", literal, span)
+            }
+            LexError::NumberOverflow { literal, span } => write!(
+                f,
+                "number literal '{}' at {} does not fit into 64 bits",
+                literal, span
+            ),
+            LexError::UnterminatedComment { span } => {
+                write!(f, "unterminated block comment starting at {}", span)
+            }
+            LexError::Io { message, span } => {
+                write!(f, "error reading the input at {}: {}", span, message)
+            }
+        };
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// number of bytes the lexer reads at once from a `std::io::Read` input
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// where the lexer reads its characters from
+enum Source<'a> {
+    /// borrowed input string
+    Str(Chars<'a>),
+    /// reader that is consumed in chunks, `bytes` holds the undecoded rest of the last chunk
+    Reader {
+        reader: Box<dyn Read + 'a>,
+        bytes: Vec<u8>,
+        done: bool,
+    },
+}
+
+impl<'a> Source<'a> {
+    /// appends the next character(s) of the input to `chars`, returns false at the end of the input
+    fn fill(&mut self, chars: &mut VecDeque<char>) -> io::Result<bool> {
+        match self {
+            Source::Str(iter) => {
+                return Ok(iter.next().map(|ch| chars.push_back(ch)).is_some());
+            }
+            Source::Reader {
+                reader,
+                bytes,
+                done,
+            } => loop {
+                if *done {
+                    // incomplete UTF-8 sequence at the end of the input
+                    if bytes.is_empty() {
+                        return Ok(false);
+                    }
+                    bytes.clear();
+                    chars.push_back(char::REPLACEMENT_CHARACTER);
+                    return Ok(true);
+                }
+                let mut chunk = [0; CHUNK_SIZE];
+                let read = match reader.read(&mut chunk) {
+                    Ok(read) => read,
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(error) => return Err(error),
+                };
+                if read == 0 {
+                    *done = true;
+                    continue;
+                }
+                bytes.extend_from_slice(&chunk[..read]);
+                let before = chars.len();
+                decode_utf8(bytes, chars);
+                if chars.len() > before {
+                    return Ok(true);
+                }
+            },
+        }
+    }
+}
+
+/// moves all complete characters from the front of `bytes` to `chars`, invalid
+/// sequences become U+FFFD, an incomplete sequence at the end stays in `bytes`
+fn decode_utf8(bytes: &mut Vec<u8>, chars: &mut VecDeque<char>) {
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => {
+                chars.extend(text.chars());
+                bytes.clear();
+                return;
+            }
+            Err(error) => {
+                let valid = error.valid_up_to();
+                // the prefix is valid UTF-8 by definition of `valid_up_to`
+                chars.extend(String::from_utf8_lossy(&bytes[..valid]).chars());
+                match error.error_len() {
+                    Some(invalid) => {
+                        chars.push_back(char::REPLACEMENT_CHARACTER);
+                        bytes.drain(..valid + invalid);
+                    }
+                    None => {
+                        bytes.drain(..valid);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// position in the token stream a lexer can be rewound to, see `Lexer::checkpoint`
+#[derive(Debug)]
+pub struct Checkpoint {
+    consumed: usize,
+}
+
 /// Lexer struct that tokenizes the input string
-pub struct Lexer {
-    /// holds state of the lexer: current position + character in the input stream
-    position: usize,
-    read_position: usize,
-    ch: u8,
-    input: Vec<u8>,
+/// iterating over the lexer yields every token up to and including Eof
+pub struct Lexer<'a> {
+    source: Source<'a>,
+    /// holds state of the lexer: current character + characters read ahead from the source
+    ch: char,
+    lookahead: VecDeque<char>,
+    /// byte offset, line and column of the current character
+    offset: usize,
+    line: usize,
+    column: usize,
+    /// byte offset, line and column of the first character of the current token
+    token_start: (usize, usize, usize),
+    /// source text read since the start of the current token or trivia
+    lexeme: String,
+    /// lossless mode: keeps whitespace and comments as trivia of the tokens
+    lossless: bool,
+    /// error of the reader, reported as the next token
+    io_error: Option<String>,
+    /// tokens scanned ahead by `peek`, the front is returned next
+    buffer: VecDeque<Result<SpannedToken, LexError>>,
+    /// tokens returned since the oldest active checkpoint, `history[0]` was the
+    /// `history_start`-th token returned, `consumed` counts all returned tokens
+    history: Vec<Result<SpannedToken, LexError>>,
+    history_start: usize,
+    consumed: usize,
+    checkpoints: usize,
+    /// the iterator stops after Eof
+    finished: bool,
 }
 
 /// Lexer struct that tokenizes the input string
-impl Lexer {
-    /// creates a new lexer instance, initializes lexer state
-    pub fn new(input: String) -> Lexer {
+impl<'a> Lexer<'a> {
+    /// creates a new lexer instance that borrows the input, initializes lexer state
+    pub fn new(input: &'a str) -> Lexer<'a> {
+        return Lexer::with_source(Source::Str(input.chars()));
+    }
+
+    /// creates a lexer in lossless mode: every token keeps its source text and the
+    /// whitespace and comments in front of it, see `source_text`
+    pub fn new_lossless(input: &'a str) -> Lexer<'a> {
+        let mut lex = Lexer::new(input);
+        lex.lossless = true;
+        return lex;
+    }
+
+    /// creates a lexer that reads its input in chunks from `reader`, so the whole
+    /// input never has to be in memory at once
+    pub fn from_reader(reader: impl Read + 'a) -> Lexer<'a> {
+        return Lexer::with_source(Source::Reader {
+            reader: Box::new(reader),
+            bytes: Vec::new(),
+            done: false,
+        });
+    }
+
+    fn with_source(source: Source<'a>) -> Lexer<'a> {
         let mut lex = Lexer {
-            position: 0,
-            read_position: 0,
-            ch: 0,
-            input: input.into_bytes(),
+            source,
+            ch: '\0',
+            lookahead: VecDeque::new(),
+            offset: 0,
+            line: 1,
+            column: 1,
+            token_start: (0, 1, 1),
+            lexeme: String::new(),
+            lossless: false,
+            io_error: None,
+            buffer: VecDeque::new(),
+            history: Vec::new(),
+            history_start: 0,
+            consumed: 0,
+            checkpoints: 0,
+            finished: false,
         };
-        lex.read_char();
+        lex.ch = lex.char_at(0).unwrap_or('\0');
         return lex;
     }
 
-    /// retrieves the next token from the input string until EOF
-    pub fn next_token(&mut self) -> Result<Token> {
+    /// retrieves the next token together with its span from the input string until EOF
+    /// after an error the lexer continues behind the offending input
+    pub fn next_token(&mut self) -> Result<SpannedToken, LexError> {
+        let next = match self.buffer.pop_front() {
+            Some(next) => next,
+            None => self.scan_token(),
+        };
+        if self.checkpoints > 0 {
+            self.history.push(next.clone());
+        }
+        self.consumed += 1;
+        return next;
+    }
+
+    /// returns the next token without consuming it
+    pub fn peek(&mut self) -> Result<&SpannedToken, LexError> {
+        return self.peek_nth(0);
+    }
 
-        // skip any whitespace characters
-        self.skip_whitespace();
+    /// returns the token `n` positions ahead without consuming anything, `peek_nth(0)` is `peek`
+    pub fn peek_nth(&mut self, n: usize) -> Result<&SpannedToken, LexError> {
+        while self.buffer.len() <= n {
+            let next = self.scan_token();
+            self.buffer.push_back(next);
+        }
+        return self.buffer[n].as_ref().map_err(|error| error.clone());
+    }
 
+    /// marks the current position in the token stream, tokens are recorded until the
+    /// checkpoint is passed to `rewind` or `release`
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        if self.checkpoints == 0 {
+            self.history.clear();
+            self.history_start = self.consumed;
+        }
+        self.checkpoints += 1;
+        return Checkpoint {
+            consumed: self.consumed,
+        };
+    }
+
+    /// resets the lexer to the position of the checkpoint
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        let replay = self
+            .history
+            .split_off(checkpoint.consumed - self.history_start);
+        for next in replay.into_iter().rev() {
+            self.buffer.push_front(next);
+        }
+        self.consumed = checkpoint.consumed;
+        self.finished = false;
+        self.release(checkpoint);
+    }
+
+    /// keeps the current position and stops recording for the checkpoint
+    pub fn release(&mut self, _checkpoint: Checkpoint) {
+        self.checkpoints -= 1;
+        if self.checkpoints == 0 {
+            self.history.clear();
+        }
+    }
+
+    /// recovery mode: tokenizes the whole input and collects every lexical error
+    /// instead of stopping at the first one, the returned tokens end with Eof
+    pub fn tokenize_recovering(&mut self) -> (Vec<SpannedToken>, Vec<LexError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match self.next_token() {
+                Ok(spanned) => {
+                    let eof = spanned.token == Token::Eof;
+                    tokens.push(spanned);
+                    if eof {
+                        return (tokens, errors);
+                    }
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+    }
+
+    /// scans the next token from the input
+    fn scan_token(&mut self) -> Result<SpannedToken, LexError> {
+        // skip any whitespace characters and comments
+        let trivia = self.read_trivia()?;
+
+        self.start_lexeme();
+        if let Some(message) = self.io_error.take() {
+            return Err(LexError::Io {
+                message,
+                span: self.token_span(),
+            });
+        }
+        let token = self.read_token()?;
+        let mut spanned = SpannedToken::new(token, self.token_span());
+        if self.lossless {
+            spanned.trivia = trivia;
+            spanned.text = std::mem::take(&mut self.lexeme);
+        }
+        return Ok(spanned);
+    }
+
+    /// the current position becomes the start of the next token or trivia
+    fn start_lexeme(&mut self) {
+        self.token_start = (self.offset, self.line, self.column);
+        self.lexeme.clear();
+    }
+
+    /// span from the start of the current token up to the current position
+    fn token_span(&self) -> Span {
+        let (start, line, column) = self.token_start;
+        return Span {
+            start,
+            end: self.offset,
+            line,
+            column,
+        };
+    }
+
+    /// reads the token starting at the current character
+    fn read_token(&mut self) -> Result<Token, LexError> {
         // assign input string with token type
         let tok = match self.ch {
-            b'(' => Token::LPar,
-            b')' => Token::RPar,
-            b'*' => Token::Mult(2),
-            b'+' => Token::Add(1),
-            b'0' => Token::Zero,
-            b'1' => Token::One,
-            b'a'..=b'z' | b'A'..=b'Z' | b'|' | b'_' => {
+            '(' => Token::LPar,
+            ')' => Token::RPar,
+            '0'..='9' => return self.read_number(),
+            'a'..='z' | 'A'..='Z' | '_' => {
                 let ident = self.read_ident();
                 // match found identifier with existing token types
-                return Ok(match ident.as_str() {
-                    "false" => Token::False,
-                    "true" => Token::True,
-                    "||" => Token::Or(0),
-                    _ => unreachable!("Unallowed character"),
-                });
+                return match ident.as_str() {
+                    "false" => Ok(Token::False),
+                    "true" => Ok(Token::True),
+                    "if" => Ok(Token::If),
+                    "then" => Ok(Token::Then),
+                    "else" => Ok(Token::Else),
+                    "let" => Ok(Token::Let),
+                    "in" => Ok(Token::In),
+                    "fn" => Ok(Token::Fn),
+                    "def" => Ok(Token::Def),
+                    "print" => Ok(Token::Print),
+                    _ => Ok(Token::Ident(ident)),
+                };
             }
-            0 => Token::Eof, // end of file
-            _ => unreachable!("Unallowed character"),
+            '\0' if self.at_end() => return Ok(Token::Eof), // end of file
+            // operators and symbolic constants, in ASCII or Unicode spelling
+            _ => return self.read_symbol(),
         };
 
         self.read_char();
         return Ok(tok);
     }
 
-    /// skips any whitespace characters of input string
-    fn skip_whitespace(&mut self) {
-        while self.ch.is_ascii_whitespace() {
+    /// reads the longest symbol starting at the current character
+    fn read_symbol(&mut self) -> Result<Token, LexError> {
+        let found = SYMBOLS
+            .iter()
+            .find(|(spelling, _)| self.starts_with(spelling));
+        let Some((spelling, token)) = found else {
+            let ch = self.ch;
+            self.read_char();
+            return Err(LexError::UnexpectedCharacter {
+                ch,
+                span: self.token_span(),
+            });
+        };
+
+        for _ in spelling.chars() {
             self.read_char();
         }
+        return Ok(token.clone());
     }
 
-    /// reads an identifier from the input stream
+    /// checks if the input continues with `spelling` at the current character
+    fn starts_with(&mut self, spelling: &str) -> bool {
+        return spelling
+            .chars()
+            .enumerate()
+            .all(|(i, ch)| self.char_at(i) == Some(ch));
+    }
+
+    /// skips any whitespace characters and comments of input string,
+    /// returns them as trivia in lossless mode
+    fn read_trivia(&mut self) -> Result<Vec<Trivia>, LexError> {
+        let mut trivia = Vec::new();
+        loop {
+            self.start_lexeme();
+            let kind: fn(String) -> Trivia = if self.ch.is_whitespace() {
+                while self.ch.is_whitespace() {
+                    self.read_char();
+                }
+                Trivia::Whitespace
+            } else if self.ch == '#' {
+                while self.ch != '\n' && !self.at_end() {
+                    self.read_char();
+                }
+                Trivia::LineComment
+            } else if self.starts_with("/*") {
+                self.read_char();
+                self.read_char();
+                while !self.starts_with("*/") {
+                    if self.at_end() {
+                        return Err(LexError::UnterminatedComment {
+                            span: self.token_span(),
+                        });
+                    }
+                    self.read_char();
+                }
+                self.read_char();
+                self.read_char();
+                Trivia::BlockComment
+            } else {
+                return Ok(trivia);
+            };
+            if self.lossless {
+                trivia.push(kind(std::mem::take(&mut self.lexeme)));
+            }
+        }
+    }
+
+    /// reads an identifier from the input stream, digits are allowed after the first character
     fn read_ident(&mut self) -> String {
-        let pos = self.position;
-        // position advaces until stop 
-        while self.ch.is_ascii_alphabetic() || self.ch == b'_' || self.ch == b'|' {
+        // position advaces until stop
+        while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
             self.read_char();
         }
         // returns string of found identifier
-        return String::from_utf8_lossy(&self.input[pos..self.position]).to_string();
+        return self.lexeme.clone();
+    }
+
+    /// reads a number literal: integer (decimal, `0x` hex or `0b` binary), float (`3.14`, `1e-9`)
+    /// or exact decimal with `r` suffix (`0.1r`, `5r`), `_` is allowed as digit separator
+    fn read_number(&mut self) -> Result<Token, LexError> {
+        if self.ch == '0' && matches!(self.char_at(1), Some('x' | 'X' | 'b' | 'B')) {
+            return self.read_radix_int();
+        }
+
+        let mut mantissa = self.read_digits();
+        let mut exponent: i64 = 0;
+        let mut is_float = false;
+        if self.ch == '.' && self.char_at(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.read_char();
+            let fraction = self.read_digits();
+            exponent -= fraction.len() as i64;
+            mantissa.push_str(&fraction);
+            is_float = true;
+        }
+        let signed = matches!(self.char_at(1), Some('+' | '-'));
+        let exponent_digit = self.char_at(if signed { 2 } else { 1 });
+        if matches!(self.ch, 'e' | 'E') && exponent_digit.is_some_and(|c| c.is_ascii_digit()) {
+            self.read_char();
+            let negative = self.ch == '-';
+            if signed {
+                self.read_char();
+            }
+            // exponents beyond the range of i64 overflow every numeric type anyway
+            let digits = self.read_digits();
+            let value = digits.parse::<i64>().unwrap_or(i64::MAX / 2);
+            exponent += if negative { -value } else { value };
+            is_float = true;
+        }
+        let exact = self.ch == 'r';
+        if exact {
+            self.read_char();
+        }
+        // the literal must not run into letters, e.g. `12ab`
+        if self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+            while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+                self.read_char();
+            }
+            return Err(LexError::InvalidNumber {
+                literal: self.lexeme.clone(),
+                span: self.token_span(),
+            });
+        }
+
+        let overflow = |lexer: &Lexer| LexError::NumberOverflow {
+            literal: lexer.lexeme.clone(),
+            span: lexer.token_span(),
+        };
+        if exact {
+            // value = mantissa * 10^exponent as reduced fraction
+            let mantissa: i128 = mantissa.parse().map_err(|_| overflow(self))?;
+            let scale = u32::try_from(exponent.unsigned_abs())
+                .ok()
+                .and_then(|exp| 10_i128.checked_pow(exp))
+                .ok_or_else(|| overflow(self))?;
+            let value = if exponent >= 0 {
+                mantissa
+                    .checked_mul(scale)
+                    .and_then(|num| Rational::new(num, 1))
+            } else {
+                Rational::new(mantissa, scale)
+            };
+            return value.map(Token::Rational).ok_or_else(|| overflow(self));
+        }
+        if is_float {
+            let value: f64 = self
+                .lexeme
+                .replace('_', "")
+                .parse()
+                .unwrap_or(f64::INFINITY);
+            if !value.is_finite() {
+                return Err(overflow(self));
+            }
+            return Ok(Token::Float(value));
+        }
+        return mantissa.parse().map(Token::Int).map_err(|_| overflow(self));
+    }
+
+    /// reads `0x` hex or `0b` binary integer literal
+    fn read_radix_int(&mut self) -> Result<Token, LexError> {
+        let radix = if matches!(self.char_at(1), Some('x' | 'X')) {
+            16
+        } else {
+            2
+        };
+        // skip the radix prefix
+        self.read_char();
+        self.read_char();
+
+        let mut digits = String::new();
+        while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+            if self.ch != '_' {
+                digits.push(self.ch);
+            }
+            self.read_char();
+        }
+        let literal = self.lexeme.clone();
+
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Err(LexError::InvalidNumber {
+                literal,
+                span: self.token_span(),
+            });
+        }
+        // digits are validated, so the only remaining failure is overflow
+        let value = i64::from_str_radix(&digits, radix).map_err(|_| LexError::NumberOverflow {
+            literal,
+            span: self.token_span(),
+        })?;
+        return Ok(Token::Int(value));
+    }
+
+    /// reads decimal digits, skips `_` separators
+    fn read_digits(&mut self) -> String {
+        let mut digits = String::new();
+        while self.ch.is_ascii_digit() || self.ch == '_' {
+            if self.ch != '_' {
+                digits.push(self.ch);
+            }
+            self.read_char();
+        }
+        return digits;
+    }
+
+    /// returns the character `n` positions ahead of the current one, None past the end
+    fn char_at(&mut self, n: usize) -> Option<char> {
+        while self.lookahead.len() <= n {
+            match self.source.fill(&mut self.lookahead) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(error) => {
+                    // the input ends here, the error is reported instead of the next token
+                    self.io_error = Some(error.to_string());
+                    self.source = Source::Str("".chars());
+                    return None;
+                }
+            }
+        }
+        return Some(self.lookahead[n]);
+    }
+
+    /// checks if all characters of the input are read, the current character
+    /// is always loaded into `lookahead` unless the input has ended
+    fn at_end(&self) -> bool {
+        return self.lookahead.is_empty();
     }
 
     /// reads the next character from the input stream & updates the lexer's state accordingly
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = 0
-        } else {
-            self.ch = self.input[self.read_position];
+        // advance offset, line and column past the current character
+        if let Some(ch) = self.lookahead.pop_front() {
+            self.offset += ch.len_utf8();
+            self.lexeme.push(ch);
+            if ch == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
         }
+        self.ch = self.char_at(0).unwrap_or('\0');
+    }
+}
 
-        self.position = self.read_position;
-        self.read_position += 1
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<SpannedToken, LexError>;
+
+    /// yields every token up to and including Eof, lexical errors are yielded in between
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let next = self.next_token();
+        if let Ok(SpannedToken {
+            token: Token::Eof, ..
+        }) = next
+        {
+            self.finished = true;
+        }
+        return Some(next);
     }
 }
 
@@ -125,22 +909,25 @@ impl Lexer {
 mod test {
     use anyhow::Result;
 
-    use super::{Lexer, Token};
+    use std::io::Read;
+
+    use super::{source_text, LexError, Lexer, Span, SpannedToken, Token, Trivia};
+    use crate::numeric::numeric::Rational;
 
     #[test]
     fn get_next_token() -> Result<()> {
         let input = "(0 * 1) + true * false || true";
-        let mut lexer = Lexer::new(input.into());
+        let mut lexer = Lexer::new(input);
 
         let tokens = vec![
             Token::LPar,
-            Token::Zero,
-            Token::Mult(2),
-            Token::One,
+            Token::Int(0),
+            Token::Mult(7),
+            Token::Int(1),
             Token::RPar,
-            Token::Add(1),
+            Token::Add(6),
             Token::True,
-            Token::Mult(2),
+            Token::Mult(7),
             Token::False,
             Token::Or(0),
             Token::True,
@@ -148,11 +935,479 @@ mod test {
         ];
 
         for token in tokens {
-            let next_token = lexer.next_token()?;
+            let next_token = lexer.next_token()?.token;
             println!("expected: {:?}, received {:?}", token, next_token);
             assert_eq!(token, next_token);
         }
 
         return Ok(());
     }
+
+    #[test]
+    fn integer_literals() -> Result<()> {
+        let input = "42 1_000_000 0x2A 0b1010 9223372036854775807";
+        let mut lexer = Lexer::new(input);
+
+        let tokens = vec![
+            Token::Int(42),
+            Token::Int(1_000_000),
+            Token::Int(0x2A),
+            Token::Int(0b1010),
+            Token::Int(i64::MAX),
+            Token::Eof,
+        ];
+
+        for token in tokens {
+            assert_eq!(token, lexer.next_token()?.token);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn identifiers() -> Result<()> {
+        let mut lexer = Lexer::new("max(x_1, true,falsey) if then else elsewhere ? : let x=1 in");
+
+        let tokens = vec![
+            Token::Ident("max".into()),
+            Token::LPar,
+            Token::Ident("x_1".into()),
+            Token::Comma,
+            Token::True,
+            Token::Comma,
+            Token::Ident("falsey".into()),
+            Token::RPar,
+            Token::If,
+            Token::Then,
+            Token::Else,
+            Token::Ident("elsewhere".into()),
+            Token::Question,
+            Token::Colon,
+            Token::Let,
+            Token::Ident("x".into()),
+            Token::Assign,
+            Token::Int(1),
+            Token::In,
+            Token::Eof,
+        ];
+
+        for token in tokens {
+            assert_eq!(token, lexer.next_token()?.token);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn functions() -> Result<()> {
+        let mut lexer = Lexer::new("\\x -> x-1 fn(f) => f(x)>=1 λy → y; def print");
+
+        let tokens = vec![
+            Token::Lambda,
+            Token::Ident("x".into()),
+            Token::Arrow,
+            Token::Ident("x".into()),
+            Token::Sub(6),
+            Token::Int(1),
+            Token::Fn,
+            Token::LPar,
+            Token::Ident("f".into()),
+            Token::RPar,
+            Token::FatArrow,
+            Token::Ident("f".into()),
+            Token::LPar,
+            Token::Ident("x".into()),
+            Token::RPar,
+            Token::Ge(2),
+            Token::Int(1),
+            Token::Lambda,
+            Token::Ident("y".into()),
+            Token::Arrow,
+            Token::Ident("y".into()),
+            Token::Semicolon,
+            Token::Def,
+            Token::Print,
+            Token::Eof,
+        ];
+
+        for token in tokens {
+            assert_eq!(token, lexer.next_token()?.token);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn invalid_integer_literals() {
+        for input in ["9223372036854775808", "0x", "0b102", "12ab"] {
+            let mut lexer = Lexer::new(input);
+            let result = lexer.next_token();
+            println!("input: {:?}, received {:?}", input, result);
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn token_spans() -> Result<()> {
+        let input = "(12 *\n  true)";
+        let mut lexer = Lexer::new(input);
+
+        let spans = vec![
+            (
+                Token::LPar,
+                Span {
+                    start: 0,
+                    end: 1,
+                    line: 1,
+                    column: 1,
+                },
+            ),
+            (
+                Token::Int(12),
+                Span {
+                    start: 1,
+                    end: 3,
+                    line: 1,
+                    column: 2,
+                },
+            ),
+            (
+                Token::Mult(7),
+                Span {
+                    start: 4,
+                    end: 5,
+                    line: 1,
+                    column: 5,
+                },
+            ),
+            (
+                Token::True,
+                Span {
+                    start: 8,
+                    end: 12,
+                    line: 2,
+                    column: 3,
+                },
+            ),
+            (
+                Token::RPar,
+                Span {
+                    start: 12,
+                    end: 13,
+                    line: 2,
+                    column: 7,
+                },
+            ),
+            (
+                Token::Eof,
+                Span {
+                    start: 13,
+                    end: 13,
+                    line: 2,
+                    column: 8,
+                },
+            ),
+        ];
+
+        for (token, span) in spans {
+            let next_token = lexer.next_token()?;
+            assert_eq!(token, next_token.token);
+            assert_eq!(span, next_token.span);
+        }
+        assert_eq!(&input[8..12], "true");
+        return Ok(());
+    }
+
+    #[test]
+    fn lex_errors() {
+        let inputs = vec![(
+            "1 $ 1",
+            LexError::UnexpectedCharacter {
+                ch: '$',
+                span: Span {
+                    start: 2,
+                    end: 3,
+                    line: 1,
+                    column: 3,
+                },
+            },
+        )];
+
+        for (input, exp_error) in inputs {
+            let mut lexer = Lexer::new(input);
+            let error = loop {
+                match lexer.next_token() {
+                    Ok(spanned) => assert_ne!(spanned.token, Token::Eof),
+                    Err(error) => break error,
+                }
+            };
+            println!("input: {:?}, received {}", input, error);
+            assert_eq!(error, exp_error);
+        }
+    }
+
+    #[test]
+    fn lex_recovering() {
+        let mut lexer = Lexer::new("1 $ 2 @ foo + ä");
+        let (tokens, errors) = lexer.tokenize_recovering();
+
+        let tokens: Vec<Token> = tokens.iter().map(|spanned| spanned.token.clone()).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Int(1),
+                Token::Int(2),
+                Token::Ident("foo".into()),
+                Token::Add(6),
+                Token::Eof
+            ]
+        );
+        let columns: Vec<usize> = errors.iter().map(|error| error.span().column).collect();
+        assert_eq!(columns, vec![3, 7, 15]);
+        assert!(matches!(
+            errors[2],
+            LexError::UnexpectedCharacter { ch: 'ä', .. }
+        ));
+    }
+
+    #[test]
+    fn operators_longest_match() -> Result<()> {
+        let input = "a<=b<<c<d!=e&&f&g||h|i==j>=k>>l>m-n/o%p^q";
+        let input = input.replace(char::is_alphabetic, " 1 ");
+        let mut lexer = Lexer::new(&input);
+
+        let operators = vec![
+            Token::Le(2),
+            Token::Shl(5),
+            Token::Lt(2),
+            Token::Ne(2),
+            Token::And(1),
+            Token::BitAnd(4),
+            Token::Or(0),
+            Token::BitOr(3),
+            Token::Eq(2),
+            Token::Ge(2),
+            Token::Shr(5),
+            Token::Gt(2),
+            Token::Sub(6),
+            Token::Div(7),
+            Token::Mod(7),
+            Token::Pow(9),
+        ];
+
+        for operator in operators {
+            assert_eq!(Token::Int(1), lexer.next_token()?.token);
+            let next_token = lexer.next_token()?;
+            assert_eq!(operator, next_token.token);
+            assert_eq!(
+                next_token.span.end - next_token.span.start,
+                operator.to_string().len()
+            );
+        }
+        assert_eq!(Token::Int(1), lexer.next_token()?.token);
+        assert_eq!(Token::Eof, lexer.next_token()?.token);
+
+        let mut lexer = Lexer::new("!!=");
+        assert_eq!(Token::Not(8), lexer.next_token()?.token);
+        assert_eq!(Token::Ne(2), lexer.next_token()?.token);
+        return Ok(());
+    }
+
+    #[test]
+    fn unicode_spellings() -> Result<()> {
+        let input = "¬⊤ ∨ (2 × 3 · 4 == 24) ∧ ⊥";
+        let mut lexer = Lexer::new(input);
+
+        let tokens = vec![
+            Token::Not(8),
+            Token::True,
+            Token::Or(0),
+            Token::LPar,
+            Token::Int(2),
+            Token::Mult(7),
+            Token::Int(3),
+            Token::Mult(7),
+            Token::Int(4),
+            Token::Eq(2),
+            Token::Int(24),
+            Token::RPar,
+            Token::And(1),
+            Token::False,
+            Token::Eof,
+        ];
+
+        for token in tokens {
+            let next_token = lexer.next_token()?;
+            assert_eq!(token, next_token.token);
+            // spans are byte ranges into the input
+            assert!(input.is_char_boundary(next_token.span.start));
+            assert!(input.is_char_boundary(next_token.span.end));
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn error_positions_in_characters() {
+        let mut lexer = Lexer::new("⊤ ∨ ⊥ ∨ €");
+        let (_, errors) = lexer.tokenize_recovering();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "unexpected character '€' at 1:9");
+    }
+
+    #[test]
+    fn comments_are_skipped() -> Result<()> {
+        let input = "# leading comment\n1 /* block\n comment */ + 2 # trailing";
+        let mut lexer = Lexer::new(input);
+        let (tokens, errors) = lexer.tokenize_recovering();
+        let tokens: Vec<Token> = tokens.iter().map(|spanned| spanned.token.clone()).collect();
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens,
+            vec![Token::Int(1), Token::Add(6), Token::Int(2), Token::Eof]
+        );
+
+        let mut lexer = Lexer::new("1 / 2 /* open");
+        let (tokens, errors) = lexer.tokenize_recovering();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(
+            errors[0],
+            LexError::UnterminatedComment {
+                span: Span {
+                    start: 6,
+                    end: 13,
+                    line: 1,
+                    column: 7
+                }
+            }
+        );
+        return Ok(());
+    }
+
+    #[test]
+    fn lossless_source_text() -> Result<()> {
+        let input = "  # add\n0x2A ∨/* or */(true || ⊥)\t\n# done\n";
+        let mut lexer = Lexer::new_lossless(input);
+        let (tokens, errors) = lexer.tokenize_recovering();
+        assert!(errors.is_empty());
+        assert_eq!(source_text(&tokens), input);
+
+        assert_eq!(
+            tokens[0].trivia,
+            vec![
+                Trivia::Whitespace("  ".into()),
+                Trivia::LineComment("# add".into()),
+                Trivia::Whitespace("\n".into()),
+            ]
+        );
+        assert_eq!(tokens[0].text, "0x2A");
+        assert_eq!(tokens[1].text, "∨");
+        assert_eq!(
+            tokens[2].trivia,
+            vec![Trivia::BlockComment("/* or */".into())]
+        );
+        return Ok(());
+    }
+
+    #[test]
+    fn iterator_peek_and_checkpoints() -> Result<(), LexError> {
+        let tokens: Vec<Token> = Lexer::new("1 + (2)")
+            .map(|next| next.map(|spanned| spanned.token))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Int(1),
+                Token::Add(6),
+                Token::LPar,
+                Token::Int(2),
+                Token::RPar,
+                Token::Eof
+            ]
+        );
+
+        let mut lexer = Lexer::new("1 * 2 || true");
+        assert_eq!(lexer.peek_nth(2)?.token, Token::Int(2));
+        assert_eq!(lexer.peek()?.token, Token::Int(1));
+        assert_eq!(lexer.next_token()?.token, Token::Int(1));
+
+        let checkpoint = lexer.checkpoint();
+        assert_eq!(lexer.next_token()?.token, Token::Mult(7));
+        let inner = lexer.checkpoint();
+        assert_eq!(lexer.next_token()?.token, Token::Int(2));
+        lexer.release(inner);
+        assert_eq!(lexer.next_token()?.token, Token::Or(0));
+        lexer.rewind(checkpoint);
+
+        let rest: Vec<Token> = lexer
+            .map(|next| next.map(|spanned| spanned.token))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            rest,
+            vec![
+                Token::Mult(7),
+                Token::Int(2),
+                Token::Or(0),
+                Token::True,
+                Token::Eof
+            ]
+        );
+        return Ok(());
+    }
+
+    /// reader that hands out a single byte per read to split multi-byte characters
+    struct ByteReader<'a>(&'a [u8]);
+
+    impl<'a> Read for ByteReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            return Ok(1);
+        }
+    }
+
+    #[test]
+    fn lex_from_reader() -> Result<(), LexError> {
+        let input = "¬⊤ ∨ (0x2A × 3 /* ∧ */ == 126)";
+        let from_str: Vec<SpannedToken> = Lexer::new(input).collect::<Result<_, _>>()?;
+        let from_reader: Vec<SpannedToken> =
+            Lexer::from_reader(ByteReader(input.as_bytes())).collect::<Result<_, _>>()?;
+        assert_eq!(from_str, from_reader);
+
+        // input spanning several chunks
+        let input = "1 + ".repeat(5_000) + "1";
+        let count = Lexer::from_reader(input.as_bytes()).count();
+        assert_eq!(count, 10_002);
+        return Ok(());
+    }
+
+    #[test]
+    fn float_and_rational_literals() -> Result<(), LexError> {
+        let input = "3.25 1e-9 2.5E+3 1_000.5 0.1r 5r 1.5e3r 1e-3r 7";
+        let tokens: Vec<Token> = Lexer::new(input)
+            .map(|next| next.map(|spanned| spanned.token))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Float(3.25),
+                Token::Float(1e-9),
+                Token::Float(2500.0),
+                Token::Float(1000.5),
+                Token::Rational(Rational::new(1, 10).unwrap()),
+                Token::Rational(Rational::from_int(5)),
+                Token::Rational(Rational::from_int(1500)),
+                Token::Rational(Rational::new(1, 1000).unwrap()),
+                Token::Int(7),
+                Token::Eof,
+            ]
+        );
+
+        for input in ["1e999", "0.00000000000000000001r", "1.5x", "2r5"] {
+            let mut lexer = Lexer::new(input);
+            let result = lexer.next_token();
+            println!("input: {:?}, received {:?}", input, result);
+            assert!(result.is_err());
+        }
+        return Ok(());
+    }
 }
@@ -1,4 +1,9 @@
+// explicit `return` statements and `module/module.rs` layout are the style of this crate
+#![allow(clippy::needless_return, clippy::module_inception)]
+
 pub mod evaluator;
 pub mod lexer;
+pub mod numeric;
 pub mod parser;
 pub mod simplifier;
+pub mod typechecker;
@@ -1,21 +1,265 @@
-use std::env;
+use std::{env, fs, fs::File, io, panic, process, thread};
+
+use rust_eval::{
+    evaluator::{
+        evaluator::{Evaluator, STACK_PER_CALL},
+        interpreter::Interpreter,
+    },
+    lexer::lexer::Lexer,
+    parser::{
+        derivation::{derive, Order},
+        parser::{Parser, ShuntiyardParser},
+        pratt::PrattParser,
+        unparser::format_source,
+    },
+};
+
+// limit of nested function calls of the command line, `run --max-depth N` changes it
+const MAX_DEPTH: usize = 10_000;
+
+// stack of the thread evaluating expressions and scripts, the evaluation may use all of it
+// but the last MiB
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+// more nested calls never fit on the stack, every call takes at least `STACK_PER_CALL`
+const MAX_DEPTH_LIMIT: usize = STACK_SIZE / STACK_PER_CALL;
 
-use rust_eval::{evaluator::evaluator, lexer::lexer::Lexer, parser::parser::ShuntiyardParser};
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() > 2 {
+    if args.len() > 2 && args[1] == "run" {
+        run_script(&args[2], &args[3..]);
+    } else if args.len() > 2 && args[1] == "fmt" {
+        format_file(&args[2], &args[3..]);
+    } else if args.len() > 2 && args[1] == "derive" {
+        print_derivation(&args[2..args.len() - 1], &args[args.len() - 1]);
+    } else if args.len() > 2 {
         let exp = &args[2];
-        let lexer = Lexer::new(exp.into());
+        let lexer = Lexer::new(exp);
         let mut parser = ShuntiyardParser::new(lexer);
         let result = parser.parse();
         match result {
-            Ok(ast) => match evaluator::eval(&ast) {
-                Some(result) => print!("Result of evaluation: {}", result.to_string()),
-                None => print!("Cannot be evaluated"),
+            Ok(ast) => match with_stack(|| evaluator(MAX_DEPTH).eval(&ast)) {
+                Ok(result) => print!("Result of evaluation: {}", result),
+                Err(error) => print!("Cannot be evaluated: {}", error),
             },
-            Err(_) => panic!("Error while parsing"),
+            Err(error) => print!("Error while parsing: {}", error),
         }
     } else {
         println!("No expression found")
     }
 }
+
+// evaluator allowing `max_depth` nested calls, to be used on the stack of `with_stack`
+fn evaluator(max_depth: usize) -> Evaluator {
+    let mut evaluator = Evaluator::new();
+    evaluator.set_max_depth(max_depth);
+    evaluator.set_max_stack(STACK_SIZE - 1024 * 1024);
+    evaluator
+}
+
+// runs `evaluate` on a thread with a stack of `STACK_SIZE`
+fn with_stack<T: Send>(evaluate: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        let thread = thread::Builder::new().stack_size(STACK_SIZE);
+        match thread.spawn_scoped(scope, evaluate) {
+            Ok(handle) => handle
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload)),
+            Err(error) => {
+                eprintln!("Cannot allocate the stack: {}", error);
+                process::exit(1);
+            }
+        }
+    })
+}
+
+// runs the script in the file at `path`, printed values go to stdout and errors to stderr
+// nested function calls are limited to a depth of 10000 or as many as `--max-depth N` sets
+fn run_script(path: &str, options: &[String]) {
+    let max_depth = match max_depth(options) {
+        Ok(max_depth) => max_depth,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) => {
+            eprintln!("Cannot read {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+    let mut parser = PrattParser::new(Lexer::from_reader(file));
+    let statements = match parser.parse_script() {
+        Ok(statements) => statements,
+        Err(error) => {
+            eprintln!("Error while parsing: {}", error);
+            process::exit(1);
+        }
+    };
+    let run = || Interpreter::with_evaluator(evaluator(max_depth), io::stdout()).run(&statements);
+    if let Err(error) = with_stack(run) {
+        eprintln!("Cannot be evaluated: {}", error);
+        process::exit(1);
+    }
+}
+
+// limit of nested calls set by the options of `run`, at most `MAX_DEPTH_LIMIT`
+fn max_depth(options: &[String]) -> Result<usize, String> {
+    let depth = match options {
+        [] => return Ok(MAX_DEPTH),
+        [option, depth] if option == "--max-depth" => depth,
+        _ => return Err(format!("Unknown options: {}", options.join(" "))),
+    };
+    match depth.parse() {
+        Ok(max_depth) if max_depth <= MAX_DEPTH_LIMIT => Ok(max_depth),
+        Ok(_) => Err(format!(
+            "Invalid depth: {} exceeds the limit of {} nested calls that fit on the stack",
+            depth, MAX_DEPTH_LIMIT
+        )),
+        Err(_) => Err(format!("Invalid depth: {}", depth)),
+    }
+}
+
+// prints the leftmost derivation of `input`, the options `--rightmost`, `--tree` and `--dot`
+// print the rightmost derivation or the derivation tree as text or as Graphviz DOT instead
+fn print_derivation(options: &[String], input: &str) {
+    let rendering = match rendering(options) {
+        Ok(rendering) => rendering,
+        Err(unknown) => {
+            eprintln!("Unknown options: {}", unknown.join(" "));
+            process::exit(1);
+        }
+    };
+    let derivation = match derive(input) {
+        Ok(derivation) => derivation,
+        Err(error) => {
+            eprintln!("Error while parsing: {}", error);
+            process::exit(1);
+        }
+    };
+    match rendering {
+        Rendering::Steps(order) => print!("{}", derivation.to_steps(order)),
+        Rendering::Tree => print!("{}", derivation.to_tree()),
+        Rendering::Dot => print!("{}", derivation.to_dot()),
+    }
+}
+
+// how `derive` prints the derivation
+#[derive(Debug, PartialEq)]
+enum Rendering {
+    Steps(Order),
+    Tree,
+    Dot,
+}
+
+// rendering chosen by the options of `derive`, `--dot` before `--tree` before `--rightmost`
+// the options that are not known are returned as error
+fn rendering(options: &[String]) -> Result<Rendering, Vec<&str>> {
+    let known = ["--rightmost", "--tree", "--dot"];
+    let unknown: Vec<&str> = options
+        .iter()
+        .map(String::as_str)
+        .filter(|option| !known.contains(option))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(unknown);
+    }
+    let option = |name: &str| options.iter().any(|option| option == name);
+    if option("--dot") {
+        Ok(Rendering::Dot)
+    } else if option("--tree") {
+        Ok(Rendering::Tree)
+    } else if option("--rightmost") {
+        Ok(Rendering::Steps(Order::Rightmost))
+    } else {
+        Ok(Rendering::Steps(Order::Leftmost))
+    }
+}
+
+// rewrites the expression file at `path` in canonical form, lines are at most 80 characters
+// wide where possible or as many as `--width N` sets; comments are kept
+fn format_file(path: &str, options: &[String]) {
+    let width = match options {
+        [] => 80,
+        [option, width] if option == "--width" => match width.parse() {
+            Ok(width) => width,
+            Err(_) => {
+                eprintln!("Invalid width: {}", width);
+                process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("Unknown options: {}", options.join(" "));
+            process::exit(1);
+        }
+    };
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("Cannot read {}: {}", path, error);
+            process::exit(1);
+        }
+    };
+    let formatted = match format_source(&source, width) {
+        Ok(formatted) => formatted,
+        Err(error) => {
+            eprintln!("Error while parsing: {}", error);
+            process::exit(1);
+        }
+    };
+    if formatted != source {
+        if let Err(error) = fs::write(path, formatted) {
+            eprintln!("Cannot write {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_eval::parser::derivation::Order;
+
+    use crate::{max_depth, rendering, Rendering, MAX_DEPTH};
+
+    #[test]
+    fn derive_options_test() {
+        let options = |options: &[&str]| -> Vec<String> {
+            options.iter().map(|option| option.to_string()).collect()
+        };
+        let cases = [
+            (vec![], Ok(Rendering::Steps(Order::Leftmost))),
+            (vec!["--rightmost"], Ok(Rendering::Steps(Order::Rightmost))),
+            (vec!["--rightmost", "--tree"], Ok(Rendering::Tree)),
+            (vec!["--dot", "--tree"], Ok(Rendering::Dot)),
+            // misspelled options are reported instead of being ignored
+            (vec!["--rigthmost"], Err(vec!["--rigthmost"])),
+            (vec!["--tree", "-dot"], Err(vec!["-dot"])),
+        ];
+        for (given, exp_rendering) in cases {
+            assert_eq!(rendering(&options(&given)), exp_rendering, "{:?}", given);
+        }
+    }
+
+    #[test]
+    fn max_depth_options_test() {
+        let options = |options: &[&str]| -> Vec<String> {
+            options.iter().map(|option| option.to_string()).collect()
+        };
+        let cases = [
+            (vec![], Ok(MAX_DEPTH)),
+            (vec!["--max-depth", "50000"], Ok(50_000)),
+            (vec!["--max-depth", "x"], Err("Invalid depth: x".to_string())),
+            // depths whose calls cannot fit on the stack are rejected instead of overflowing it
+            (
+                vec!["--max-depth", "1000000"],
+                Err("Invalid depth: 1000000 exceeds the limit of 65536 nested calls that fit on the stack".to_string()),
+            ),
+            (vec!["--depth", "5"], Err("Unknown options: --depth 5".to_string())),
+        ];
+        for (given, exp_depth) in cases {
+            assert_eq!(max_depth(&options(&given)), exp_depth, "{:?}", given);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::{cmp::Ordering, fmt};
+
+/// exact fraction `numerator / denominator`, always reduced with a positive denominator
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct Rational {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Rational {
+    /// creates the reduced fraction, returns None for a zero denominator or
+    /// if the reduced fraction does not fit into 64 bits
+    pub fn new(numerator: i128, denominator: i128) -> Option<Rational> {
+        if denominator == 0 {
+            return None;
+        }
+        let divisor = gcd(numerator, denominator);
+        let sign = if denominator < 0 { -1 } else { 1 };
+        return Some(Rational {
+            numerator: i64::try_from(sign * numerator / divisor).ok()?,
+            denominator: i64::try_from(sign * denominator / divisor).ok()?,
+        });
+    }
+
+    pub fn from_int(value: i64) -> Rational {
+        return Rational {
+            numerator: value,
+            denominator: 1,
+        };
+    }
+
+    pub fn numerator(&self) -> i64 {
+        return self.numerator;
+    }
+
+    pub fn denominator(&self) -> i64 {
+        return self.denominator;
+    }
+
+    pub fn is_integer(&self) -> bool {
+        return self.denominator == 1;
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        return self.numerator as f64 / self.denominator as f64;
+    }
+
+    pub fn checked_add(self, other: Rational) -> Option<Rational> {
+        let (a, b, c, d) = self.widen(other);
+        return Rational::new(a.checked_mul(d)?.checked_add(c.checked_mul(b)?)?, b * d);
+    }
+
+    pub fn checked_sub(self, other: Rational) -> Option<Rational> {
+        let (a, b, c, d) = self.widen(other);
+        return Rational::new(a.checked_mul(d)?.checked_sub(c.checked_mul(b)?)?, b * d);
+    }
+
+    pub fn checked_neg(self) -> Option<Rational> {
+        return Rational::new(-(self.numerator as i128), self.denominator as i128);
+    }
+
+    pub fn checked_mul(self, other: Rational) -> Option<Rational> {
+        let (a, b, c, d) = self.widen(other);
+        return Rational::new(a * c, b * d);
+    }
+
+    /// returns None when dividing by zero
+    pub fn checked_div(self, other: Rational) -> Option<Rational> {
+        let (a, b, c, d) = self.widen(other);
+        return Rational::new(a * d, b * c);
+    }
+
+    /// remainder of the division truncated towards zero, like `%` on integers
+    pub fn checked_rem(self, other: Rational) -> Option<Rational> {
+        let quotient = self.checked_div(other)?;
+        let truncated = Rational::from_int(quotient.numerator / quotient.denominator);
+        return self.checked_sub(truncated.checked_mul(other)?);
+    }
+
+    /// integer powers, negative exponents invert the fraction
+    pub fn checked_pow(self, exponent: i64) -> Option<Rational> {
+        let base = if exponent < 0 {
+            Rational::from_int(1).checked_div(self)?
+        } else {
+            self
+        };
+        let exponent = u32::try_from(exponent.unsigned_abs()).ok()?;
+        return Some(Rational {
+            numerator: base.numerator.checked_pow(exponent)?,
+            denominator: base.denominator.checked_pow(exponent)?,
+        });
+    }
+
+    /// exact decimal notation (`0.125`), None if the fraction has no finite decimal expansion
+    pub fn to_decimal(&self) -> Option<String> {
+        // the denominator has to be of the form 2^a * 5^b
+        let (mut rest, mut twos, mut fives) = (self.denominator, 0, 0);
+        while rest % 2 == 0 {
+            rest /= 2;
+            twos += 1;
+        }
+        while rest % 5 == 0 {
+            rest /= 5;
+            fives += 1;
+        }
+        if rest != 1 {
+            return None;
+        }
+        // None if the scaled numerator does not fit, the caller writes the fraction instead
+        let digits = u32::max(twos, fives);
+        let scale = 10_i128.checked_pow(digits)?;
+        let scaled = (self.numerator as i128).checked_mul(scale)? / self.denominator as i128;
+        let sign = if scaled < 0 { "-" } else { "" };
+        if digits == 0 {
+            return Some(format!("{}{}", sign, scaled.abs()));
+        }
+        return Some(format!(
+            "{}{}.{:0width$}",
+            sign,
+            scaled.abs() / scale,
+            scaled.abs() % scale,
+            width = digits as usize
+        ));
+    }
+
+    /// numerators & denominators of both fractions without risk of overflow
+    fn widen(self, other: Rational) -> (i128, i128, i128, i128) {
+        return (
+            self.numerator as i128,
+            self.denominator as i128,
+            other.numerator as i128,
+            other.denominator as i128,
+        );
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b, c, d) = self.widen(*other);
+        // denominators are positive, so cross-multiplying keeps the ordering
+        return (a * d).cmp(&(c * b));
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_integer() {
+            return write!(f, "{}", self.numerator);
+        }
+        return write!(f, "{}/{}", self.numerator, self.denominator);
+    }
+}
+
+/// greatest common divisor, at least 1
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    return a.max(1);
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rational;
+    use crate::parser::parser::ResultEval;
+
+    #[test]
+    fn rational_arithmetic_test() {
+        let third = Rational::new(1, 3).unwrap();
+        let half = Rational::new(-2, -4).unwrap();
+        assert_eq!(half, Rational::new(1, 2).unwrap());
+        assert_eq!(third.checked_add(half), Rational::new(5, 6));
+        assert_eq!(third.checked_sub(half), Rational::new(-1, 6));
+        assert_eq!(third.checked_mul(half), Rational::new(1, 6));
+        assert_eq!(third.checked_div(half), Rational::new(2, 3));
+        assert_eq!(half.checked_rem(third), Rational::new(1, 6));
+        assert_eq!(half.checked_pow(-3), Rational::new(8, 1));
+        assert_eq!(third.checked_div(Rational::from_int(0)), None);
+        assert_eq!(Rational::from_int(i64::MAX).checked_add(third), None);
+        assert!(third < half);
+    }
+
+    #[test]
+    fn rational_decimal_test() {
+        assert_eq!(
+            Rational::new(1, 8).unwrap().to_decimal(),
+            Some("0.125".into())
+        );
+        assert_eq!(
+            Rational::new(-21, 20).unwrap().to_decimal(),
+            Some("-1.05".into())
+        );
+        assert_eq!(
+            Rational::new(-1, 20).unwrap().to_decimal(),
+            Some("-0.05".into())
+        );
+        assert_eq!(Rational::from_int(42).to_decimal(), Some("42".into()));
+        assert_eq!(Rational::new(1, 3).unwrap().to_decimal(), None);
+        // the decimal expansion of 1/2^40 has 40 digits, too many for 128 bits
+        let tiny = Rational::new(1, 1 << 40).unwrap();
+        assert_eq!(tiny.to_decimal(), None);
+        assert_eq!(ResultEval::Rational(tiny).to_string(), "1/1099511627776r");
+        assert_eq!(Rational::new(1, 3).unwrap().to_string(), "1/3");
+    }
+}
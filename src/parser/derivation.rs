@@ -0,0 +1,507 @@
+use crate::{
+    lexer::lexer::{Lexer, SpannedToken, Token},
+    parser::parser::{ASTNode, NodeKind, ParseError, Parser, ShuntiyardParser},
+};
+
+// derivation of an input from the grammar in the README: every node of the AST is the
+// production it was derived with, productions are named by their line in the README
+#[derive(Debug, PartialEq, Clone)]
+pub struct Derivation {
+    pub production: &'static str,
+    pub children: Vec<Derived>,
+}
+
+// symbol on the right hand side of an applied production
+#[derive(Debug, PartialEq, Clone)]
+pub enum Derived {
+    Nonterminal(Derivation),
+    Terminal(String),
+}
+
+// which nonterminal of a sentential form is replaced next
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Order {
+    Leftmost,
+    Rightmost,
+}
+
+// step of a derivation: the production applied and the sentential form it results in
+#[derive(Debug, PartialEq, Clone)]
+pub struct Step {
+    pub production: &'static str,
+    pub form: Vec<String>,
+}
+
+// symbol of a sentential form, nonterminals still have to be replaced
+enum Form<'d> {
+    Nonterminal(&'d Derivation),
+    Terminal(&'d str),
+}
+
+impl Derivation {
+    // left hand side of the production, `E` or `P`
+    pub fn nonterminal(&self) -> &'static str {
+        return &self.production[..1];
+    }
+
+    // the sentential forms from the start symbol to the input, replacing the leftmost or
+    // rightmost nonterminal in each step
+    pub fn steps(&self, order: Order) -> Vec<Step> {
+        let mut form = vec![Form::Nonterminal(self)];
+        let mut steps = Vec::new();
+        loop {
+            let nonterminals = form
+                .iter()
+                .enumerate()
+                .filter_map(|(i, symbol)| match symbol {
+                    Form::Nonterminal(derivation) => Some((i, *derivation)),
+                    Form::Terminal(_) => None,
+                });
+            let next = match order {
+                Order::Leftmost => nonterminals.min_by_key(|(i, _)| *i),
+                Order::Rightmost => nonterminals.max_by_key(|(i, _)| *i),
+            };
+            let Some((i, derivation)) = next else {
+                return steps;
+            };
+            let replacement = derivation.children.iter().map(|child| match child {
+                Derived::Nonterminal(derivation) => Form::Nonterminal(derivation),
+                Derived::Terminal(text) => Form::Terminal(text),
+            });
+            form.splice(i..=i, replacement);
+            steps.push(Step {
+                production: derivation.production,
+                form: form
+                    .iter()
+                    .map(|symbol| match symbol {
+                        Form::Nonterminal(derivation) => derivation.nonterminal().to_string(),
+                        Form::Terminal(text) => text.to_string(),
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    // the steps one per line, each with the production applied in it:
+    //    E
+    // => E + E        E -> E + E
+    pub fn to_steps(&self, order: Order) -> String {
+        let steps = self.steps(order);
+        let forms: Vec<String> = steps.iter().map(|step| step.form.join(" ")).collect();
+        let width = forms
+            .iter()
+            .map(|form| form.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut text = format!("   {}\n", self.nonterminal());
+        for (step, form) in steps.iter().zip(forms) {
+            text.push_str(&format!("=> {:width$}   {}\n", form, step.production));
+        }
+        return text;
+    }
+
+    // the derivation tree as indented text, nonterminals with their production
+    pub fn to_tree(&self) -> String {
+        let mut text = String::new();
+        self.write_tree(0, &mut text);
+        return text;
+    }
+
+    fn write_tree(&self, depth: usize, text: &mut String) {
+        text.push_str(&format!("{}{}\n", "  ".repeat(depth), self.production));
+        for child in &self.children {
+            match child {
+                Derived::Nonterminal(derivation) => derivation.write_tree(depth + 1, text),
+                Derived::Terminal(terminal) => {
+                    text.push_str(&format!("{}{}\n", "  ".repeat(depth + 1), terminal));
+                }
+            }
+        }
+    }
+
+    // the derivation tree as Graphviz DOT graph, terminals are boxed leaves
+    pub fn to_dot(&self) -> String {
+        let mut text = String::from("digraph derivation {\n");
+        self.write_dot(&mut 0, &mut text);
+        text.push_str("}\n");
+        return text;
+    }
+
+    // writes the node and its subtree, `next` is the number of the node
+    fn write_dot(&self, next: &mut usize, text: &mut String) {
+        let id = *next;
+        *next += 1;
+        text.push_str(&format!(
+            "    n{} [label=\"{}\", tooltip=\"{}\"];\n",
+            id,
+            self.nonterminal(),
+            escape(self.production)
+        ));
+        for child in &self.children {
+            text.push_str(&format!("    n{} -> n{};\n", id, next));
+            match child {
+                Derived::Nonterminal(derivation) => derivation.write_dot(next, text),
+                Derived::Terminal(terminal) => {
+                    text.push_str(&format!(
+                        "    n{} [label=\"{}\", shape=box];\n",
+                        next,
+                        escape(terminal)
+                    ));
+                    *next += 1;
+                }
+            }
+        }
+    }
+}
+
+fn escape(label: &str) -> String {
+    return label.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+// parses `input` with the ShuntiyardParser and derives its tree from the grammar
+pub fn derive(input: &str) -> Result<Derivation, ParseError> {
+    let mut tokens = Vec::new();
+    for spanned in Lexer::new(input) {
+        let spanned = spanned?;
+        if spanned.token != Token::Eof {
+            tokens.push(spanned);
+        }
+    }
+    let ast = ShuntiyardParser::new(Lexer::new(input)).parse()?;
+    let deriver = Deriver::new(tokens);
+    if let NodeKind::Program(definitions, main) = &ast.kind {
+        let defs = deriver.tokens.iter().enumerate();
+        let starts = defs.filter(|(_, t)| t.token == Token::Def).map(|(i, _)| i);
+        let mut program = Derivation {
+            production: "P -> E",
+            children: vec![Derived::Nonterminal(deriver.derive(main).0)],
+        };
+        // the definitions wrap the rest of the program from the last one to the first
+        let definitions: Vec<(usize, &ASTNode)> =
+            starts.zip(definitions.iter().map(|d| &d.body)).collect();
+        for (start, body) in definitions.into_iter().rev() {
+            let (body, lo, hi) = deriver.derive(body);
+            let mut children = deriver.terminals(start, lo);
+            children.push(Derived::Nonterminal(body));
+            children.extend(deriver.terminals(hi + 1, hi + 2));
+            children.push(Derived::Nonterminal(program));
+            program = Derivation {
+                production: "P -> def f(x, ..., x) = E; P",
+                children,
+            };
+        }
+        return Ok(program);
+    }
+    return Ok(deriver.derive(&ast).0);
+}
+
+// finds the tokens of the AST nodes: a node spans the tokens between its first and last
+// token, the children of a node are the derivations of its operands and the tokens of
+// the node outside of its operands are its terminals
+struct Deriver {
+    tokens: Vec<SpannedToken>,
+    // index of the matching `)` for every `(` that groups an expression, None for the
+    // parentheses of argument lists
+    groups: Vec<Option<usize>>,
+}
+
+impl Deriver {
+    fn new(tokens: Vec<SpannedToken>) -> Deriver {
+        let mut groups = vec![None; tokens.len()];
+        let mut open: Vec<(usize, bool)> = Vec::new();
+        // like the parser: `(` right after an operand starts an argument list
+        let mut after_operand = false;
+        for (i, spanned) in tokens.iter().enumerate() {
+            match spanned.token {
+                Token::LPar => {
+                    let parameters = i > 0 && tokens[i - 1].token == Token::Fn;
+                    open.push((i, !after_operand && !parameters));
+                    after_operand = false;
+                }
+                Token::RPar => {
+                    if let Some((start, true)) = open.pop() {
+                        groups[start] = Some(i);
+                    }
+                    after_operand = true;
+                }
+                Token::Int(_)
+                | Token::Float(_)
+                | Token::Rational(_)
+                | Token::True
+                | Token::False
+                | Token::Ident(_) => after_operand = true,
+                // postfix factorial after an operand
+                Token::Not(_) if after_operand => {}
+                _ => after_operand = false,
+            }
+        }
+        return Deriver { tokens, groups };
+    }
+
+    // the terminals of the tokens `start..end`
+    fn terminals(&self, start: usize, end: usize) -> Vec<Derived> {
+        let end = end.min(self.tokens.len());
+        return self.tokens[start.min(end)..end]
+            .iter()
+            .map(|spanned| Derived::Terminal(spanned.token.to_string()))
+            .collect();
+    }
+
+    // derivation of the node together with the indices of its first and last token,
+    // parentheses around the node are derived with `E -> (E)`
+    fn derive(&self, node: &ASTNode) -> (Derivation, usize, usize) {
+        let first = self
+            .tokens
+            .partition_point(|t| t.span.start < node.span.start);
+        let last = self
+            .tokens
+            .partition_point(|t| t.span.start < node.span.end);
+        let (mut lo, mut hi) = (first, last.max(first + 1) - 1);
+
+        let binary = |op: &str| match op {
+            "+" => "E -> E + E",
+            "*" => "E -> E * E",
+            "||" => "E -> E || E",
+            _ => "E -> E op E",
+        };
+        let mut operands: Vec<(Derivation, usize, usize)> = Vec::new();
+        let production = match &node.kind {
+            NodeKind::Number(_) | NodeKind::Float(_) | NodeKind::Rational(_) => "E -> n",
+            NodeKind::Bool(true) => "E -> True",
+            NodeKind::Bool(false) => "E -> False",
+            NodeKind::Var(_) => "E -> x",
+            // the links share their operands: `0 < x <= 10` derives `E op E` from `0 < x`
+            // and `10`
+            NodeKind::Chain(links) => {
+                let mut chain: Option<(Derivation, usize, usize)> = None;
+                for link in links {
+                    let (left, right) = link.kind.comparison().unwrap();
+                    let left = chain.take().unwrap_or_else(|| self.derive(left));
+                    let right = self.derive(right);
+                    let (lo, hi) = (left.1, right.2);
+                    let children = self.children(vec![left, right], lo, hi);
+                    chain = Some((
+                        Derivation {
+                            production: "E -> E op E",
+                            children,
+                        },
+                        lo,
+                        hi,
+                    ));
+                }
+                let (derivation, lo, hi) = chain.unwrap();
+                return self.group(derivation, lo, hi);
+            }
+            NodeKind::Neg(operand) | NodeKind::Not(operand) | NodeKind::Factorial(operand) => {
+                operands.push(self.derive(operand));
+                match &node.kind {
+                    NodeKind::Neg(_) => "E -> -E",
+                    NodeKind::Not(_) => "E -> !E",
+                    _ => "E -> E!",
+                }
+            }
+            NodeKind::Call(_, arguments) => {
+                operands.extend(arguments.iter().map(|argument| self.derive(argument)));
+                "E -> f(E, ..., E)"
+            }
+            NodeKind::Apply(function, arguments) => {
+                operands.push(self.derive(function));
+                operands.extend(arguments.iter().map(|argument| self.derive(argument)));
+                "E -> E(E, ..., E)"
+            }
+            NodeKind::If(condition, then, other) => {
+                operands.extend([condition, then, other].map(|operand| self.derive(operand)));
+                let keyword = operands.iter().map(|o| o.1).chain([lo]).min().unwrap();
+                match self.tokens[keyword].token {
+                    Token::If => "E -> if E then E else E",
+                    _ => "E -> E ? E : E",
+                }
+            }
+            NodeKind::Let(_, value, body) => {
+                operands.extend([value, body].map(|operand| self.derive(operand)));
+                "E -> let x = E in E"
+            }
+            NodeKind::Lambda(_, body) => {
+                operands.push(self.derive(body));
+                match self.tokens[lo].token {
+                    Token::Lambda => "E -> \\x -> E",
+                    _ => "E -> fn(x, ..., x) => E",
+                }
+            }
+            kind => match kind.binary() {
+                Some((op, left, right)) => {
+                    operands.extend([left, right].map(|operand| self.derive(operand)));
+                    binary(&op.token.to_string())
+                }
+                None => unreachable!("the shunting yard parser builds no programs or errors here"),
+            },
+        };
+        for (_, first, last) in &operands {
+            lo = lo.min(*first);
+            hi = hi.max(*last);
+        }
+        let children = self.children(operands, lo, hi);
+        return self.group(
+            Derivation {
+                production,
+                children,
+            },
+            lo,
+            hi,
+        );
+    }
+
+    // the operands in source order with the tokens between them as terminals
+    fn children(
+        &self,
+        mut operands: Vec<(Derivation, usize, usize)>,
+        lo: usize,
+        hi: usize,
+    ) -> Vec<Derived> {
+        operands.sort_by_key(|operand| operand.1);
+        let mut children = Vec::new();
+        let mut next = lo;
+        for (derivation, first, last) in operands {
+            children.extend(self.terminals(next, first));
+            children.push(Derived::Nonterminal(derivation));
+            next = last + 1;
+        }
+        children.extend(self.terminals(next, hi + 1));
+        return children;
+    }
+
+    // wraps the derivation of the tokens `lo..=hi` in the parentheses around them
+    fn group(
+        &self,
+        mut derivation: Derivation,
+        mut lo: usize,
+        mut hi: usize,
+    ) -> (Derivation, usize, usize) {
+        while lo > 0 && self.groups[lo - 1] == Some(hi + 1) {
+            derivation = Derivation {
+                production: "E -> (E)",
+                children: vec![
+                    Derived::Terminal("(".to_string()),
+                    Derived::Nonterminal(derivation),
+                    Derived::Terminal(")".to_string()),
+                ],
+            };
+            lo -= 1;
+            hi += 1;
+        }
+        return (derivation, lo, hi);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::parser::derivation::{derive, Order};
+
+    #[test]
+    fn derivation_steps_test() -> Result<()> {
+        let derivation = derive("1 * 0 + 1")?;
+        assert_eq!(
+            derivation.to_steps(Order::Leftmost),
+            "   E\n\
+             => E + E       E -> E + E\n\
+             => E * E + E   E -> E * E\n\
+             => 1 * E + E   E -> n\n\
+             => 1 * 0 + E   E -> n\n\
+             => 1 * 0 + 1   E -> n\n"
+        );
+        assert_eq!(
+            derivation.to_steps(Order::Rightmost),
+            "   E\n\
+             => E + E       E -> E + E\n\
+             => E + 1       E -> n\n\
+             => E * E + 1   E -> E * E\n\
+             => E * 0 + 1   E -> n\n\
+             => 1 * 0 + 1   E -> n\n"
+        );
+
+        // every step replaces one nonterminal, the last form is the input
+        let inputs = [
+            ("((1 + 1)) * -x!", 9, "( ( 1 + 1 ) ) * - x !"),
+            ("0 < x <= (10)", 6, "0 < x <= ( 10 )"),
+            (
+                "f(1, (2)) + (\\x -> x)(3)",
+                10,
+                "f ( 1 , ( 2 ) ) + ( \\ x -> x ) ( 3 )",
+            ),
+            (
+                "if a then 1 else b ? 2 : 3",
+                7,
+                "if a then 1 else b ? 2 : 3",
+            ),
+            (
+                "let y = 1 in fn(a, b) => a",
+                4,
+                "let y = 1 in fn ( a , b ) => a",
+            ),
+            (
+                "def f(x) = x + 1; def g() = 2; f(g())",
+                9,
+                "def f ( x ) = x + 1 ; def g ( ) = 2 ; f ( g ( ) )",
+            ),
+        ];
+        for (input, exp_steps, exp_form) in inputs {
+            let derivation = derive(input)?;
+            for order in [Order::Leftmost, Order::Rightmost] {
+                let steps = derivation.steps(order);
+                assert_eq!(steps.len(), exp_steps, "{}", input);
+                assert_eq!(steps.last().unwrap().form.join(" "), exp_form);
+            }
+        }
+        let steps = derive("def f() = 1; f()")?.steps(Order::Rightmost);
+        assert_eq!(steps[0].production, "P -> def f(x, ..., x) = E; P");
+        assert_eq!(steps[1].production, "P -> E");
+        Ok(())
+    }
+
+    #[test]
+    fn derivation_tree_test() -> Result<()> {
+        assert_eq!(
+            derive("(1) * -x")?.to_tree(),
+            "E -> E * E\n  E -> (E)\n    (\n    E -> n\n      1\n    )\n  *\n  \
+             E -> -E\n    -\n    E -> x\n      x\n"
+        );
+        // chains derive `E op E` with the comparisons before the last one on the left
+        assert_eq!(
+            derive("0 < x <= 10")?.to_tree(),
+            "E -> E op E\n  E -> E op E\n    E -> n\n      0\n    <\n    E -> x\n      x\n  \
+             <=\n  E -> n\n    10\n"
+        );
+        assert_eq!(
+            derive("\\x -> x")?.to_dot(),
+            "digraph derivation {\n\
+             \x20   n0 [label=\"E\", tooltip=\"E -> \\\\x -> E\"];\n\
+             \x20   n0 -> n1;\n\
+             \x20   n1 [label=\"\\\\\", shape=box];\n\
+             \x20   n0 -> n2;\n\
+             \x20   n2 [label=\"x\", shape=box];\n\
+             \x20   n0 -> n3;\n\
+             \x20   n3 [label=\"->\", shape=box];\n\
+             \x20   n0 -> n4;\n\
+             \x20   n4 [label=\"E\", tooltip=\"E -> x\"];\n\
+             \x20   n4 -> n5;\n\
+             \x20   n5 [label=\"x\", shape=box];\n\
+             }\n"
+        );
+
+        let errors = [
+            ("1 +", "missing operand for '+' at 1:4"),
+            ("1 $ 2", "unexpected character '$' at 1:3"),
+        ];
+        for (input, exp_error) in errors {
+            assert_eq!(
+                derive(input).unwrap_err().to_string(),
+                exp_error,
+                "{}",
+                input
+            );
+        }
+        Ok(())
+    }
+}
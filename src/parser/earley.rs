@@ -0,0 +1,688 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    evaluator::evaluator::eval,
+    lexer::lexer::{Lexer, Span, SpannedToken, Token},
+    parser::parser::{
+        binary_operator, literal, postfix_operator, prefix_operator, ASTNode, NodeKind, ParseError,
+        ResultEval,
+    },
+};
+
+// the original grammar of the course, with the literals 0 and 1 instead of `n`: it has no
+// precedences, so `1 + 1 * 0` has two trees
+pub const ARITHMETIC: &str = "E -> E + E | E * E | E || E | (E) | 0 | 1 | True | False";
+
+// symbol on the right hand side of a production
+#[derive(Debug, PartialEq, Clone)]
+pub enum Symbol {
+    Nonterminal(String),
+    // matches exactly this token
+    Terminal(Token),
+    // `n` in the README: matches every integer literal
+    Number,
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Symbol::Nonterminal(name) => write!(f, "{}", name),
+            Symbol::Terminal(Token::True) => write!(f, "True"),
+            Symbol::Terminal(Token::False) => write!(f, "False"),
+            Symbol::Terminal(token) => write!(f, "{}", token),
+            Symbol::Number => write!(f, "n"),
+        };
+    }
+}
+
+// production `lhs -> rhs`, an empty right hand side derives the empty input
+#[derive(Debug, PartialEq, Clone)]
+pub struct Production {
+    pub lhs: String,
+    pub rhs: Vec<Symbol>,
+}
+
+impl fmt::Display for Production {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ->", self.lhs)?;
+        if self.rhs.is_empty() {
+            return write!(f, " ε");
+        }
+        for symbol in &self.rhs {
+            write!(f, " {}", symbol)?;
+        }
+        return Ok(());
+    }
+}
+
+// context-free grammar, the start symbol is the left hand side of the first production
+#[derive(Debug, PartialEq, Clone)]
+pub struct Grammar {
+    pub start: String,
+    pub productions: Vec<Production>,
+}
+
+impl Grammar {
+    pub fn new(start: &str, productions: Vec<Production>) -> Grammar {
+        return Grammar {
+            start: start.to_string(),
+            productions,
+        };
+    }
+
+    // reads a grammar written like the README: `E -> E + E | (E) | n` with `|` between the
+    // alternatives, names on a left hand side are nonterminals, `n` is every integer literal,
+    // `True` and `False` are the bool literals and every other token stands for itself
+    pub fn from_text(text: &str) -> Result<Grammar, ParseError> {
+        let mut lexer = Lexer::new(text);
+        let mut rules: Vec<(String, Vec<Vec<SpannedToken>>)> = Vec::new();
+        loop {
+            let spanned = lexer.next_token()?;
+            match spanned.token {
+                Token::Eof => break,
+                // a name followed by `->` starts the next rule
+                Token::Ident(ref name) if lexer.peek()?.token == Token::Arrow => {
+                    lexer.next_token()?;
+                    rules.push((name.clone(), vec![Vec::new()]));
+                }
+                _ => {
+                    let Some((_, alternatives)) = rules.last_mut() else {
+                        return Err(ParseError::ExpectedName { span: spanned.span });
+                    };
+                    if matches!(spanned.token, Token::BitOr(_)) {
+                        alternatives.push(Vec::new());
+                    } else if let Some(alternative) = alternatives.last_mut() {
+                        alternative.push(spanned);
+                    }
+                }
+            }
+        }
+        if rules.is_empty() {
+            return Err(ParseError::EmptyInput {
+                span: lexer.peek()?.span,
+            });
+        }
+
+        let nonterminals: Vec<String> = rules.iter().map(|(lhs, _)| lhs.clone()).collect();
+        let symbol = |token: Token| {
+            return match token {
+                Token::Ident(name) if nonterminals.contains(&name) => Symbol::Nonterminal(name),
+                Token::Ident(name) if name == "n" => Symbol::Number,
+                Token::Ident(name) if name == "True" => Symbol::Terminal(Token::True),
+                Token::Ident(name) if name == "False" => Symbol::Terminal(Token::False),
+                token => Symbol::Terminal(token),
+            };
+        };
+        let mut productions = Vec::new();
+        for (lhs, alternatives) in rules {
+            for alternative in alternatives {
+                productions.push(Production {
+                    lhs: lhs.clone(),
+                    rhs: alternative.into_iter().map(|t| symbol(t.token)).collect(),
+                });
+            }
+        }
+        return Ok(Grammar::new(&nonterminals[0], productions));
+    }
+
+    // nonterminals that derive the empty input
+    fn nullable(&self) -> Vec<&str> {
+        let mut nullable: Vec<&str> = Vec::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.productions {
+                if nullable.contains(&production.lhs.as_str()) {
+                    continue;
+                }
+                let empty = production.rhs.iter().all(|symbol| match symbol {
+                    Symbol::Nonterminal(name) => nullable.contains(&name.as_str()),
+                    _ => false,
+                });
+                if empty {
+                    nullable.push(&production.lhs);
+                    changed = true;
+                }
+            }
+        }
+        return nullable;
+    }
+}
+
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for production in &self.productions {
+            writeln!(f, "{}", production)?;
+        }
+        return Ok(());
+    }
+}
+
+// parse trees listed by `parse_all`, the number of trees grows exponentially with the length
+// of an ambiguous input, e.g. `1 + 1 + … + 1`, so the others are only counted
+pub const MAX_TREES: usize = 100;
+
+// the first MAX_TREES parse trees of an input together with the value of each tree,
+// None for trees that cannot be evaluated, and the number of all parse trees
+#[derive(Debug, PartialEq, Clone)]
+pub struct Parses {
+    pub trees: Vec<ASTNode>,
+    pub results: Vec<Option<ResultEval>>,
+    // saturates at usize::MAX
+    pub count: usize,
+}
+
+impl Parses {
+    // the input has more than one parse tree
+    pub fn is_ambiguous(&self) -> bool {
+        return self.count > 1;
+    }
+
+    // the listed trees of the input do not all evaluate to the same result,
+    // so the ambiguity changes the meaning of the input
+    pub fn is_conflicting(&self) -> bool {
+        return self.results.windows(2).any(|pair| pair[0] != pair[1]);
+    }
+}
+
+// Earley item: production with the dot before `rhs[dot]`, started at input position `origin`
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Item {
+    production: usize,
+    dot: usize,
+    origin: usize,
+}
+
+// child of a parse tree node while it is built
+#[derive(Clone)]
+enum Child {
+    Node(ASTNode),
+    Token(Token),
+}
+
+// (nonterminal, start, end): derivation of tokens[start..end] from the nonterminal
+type Key = (String, usize, usize);
+
+// state of listing the parse trees: the trees and their number for the derivations done so
+// far and the derivations being listed
+#[derive(Default)]
+struct Forest {
+    done: HashMap<Key, (Rc<Vec<ASTNode>>, usize)>,
+    visiting: Vec<Key>,
+    // lowest index in `visiting` at which a cycle was skipped
+    cut: usize,
+}
+
+// general context-free parser (Earley's algorithm): accepts every grammar, including
+// ambiguous and left recursive ones, and returns the parse trees of the input
+pub struct EarleyParser<'g, 'a> {
+    grammar: &'g Grammar,
+    lexer: Lexer<'a>,
+    tokens: Vec<SpannedToken>,
+    // derivations to the productions they start with
+    completed: HashMap<Key, Vec<usize>>,
+}
+
+impl<'g, 'a> EarleyParser<'g, 'a> {
+    pub fn new(grammar: &'g Grammar, lexer: Lexer<'a>) -> EarleyParser<'g, 'a> {
+        return EarleyParser {
+            grammar,
+            lexer,
+            tokens: Vec::new(),
+            completed: HashMap::new(),
+        };
+    }
+
+    // parses the whole input and evaluates the listed trees, input the grammar does not derive
+    // is reported at the first position no derivation can continue from
+    pub fn parse_all(&mut self) -> Result<Parses, ParseError> {
+        let eof = loop {
+            let spanned = self.lexer.next_token()?;
+            if spanned.token == Token::Eof {
+                break spanned;
+            }
+            self.tokens.push(spanned);
+        };
+        let sets = self.recognize();
+
+        let end = self.tokens.len();
+        let start = (self.grammar.start.clone(), 0, end);
+        if !self.completed.contains_key(&start) {
+            let position = (0..=end).rev().find(|&i| !sets[i].is_empty()).unwrap_or(0);
+            return Err(match self.tokens.get(position) {
+                Some(spanned) => ParseError::UnexpectedToken {
+                    token: spanned.token.clone(),
+                    span: spanned.span,
+                },
+                None if end == 0 => ParseError::EmptyInput { span: eof.span },
+                None => self.unfinished(&sets[end], eof.span),
+            });
+        }
+        let (trees, count) = self.trees(&self.grammar.start, 0, end, &mut Forest::default());
+        let results = trees.iter().map(eval).collect();
+        return Ok(Parses {
+            trees: trees.to_vec(),
+            results,
+            count,
+        });
+    }
+
+    // error for input that ends before a derivation is complete, reported like the other
+    // parsers do: an operator without its right operand, an unclosed parenthesis or the
+    // terminal the grammar expects at the end
+    fn unfinished(&self, items: &[Item], eof: Span) -> ParseError {
+        let last = &self.tokens[self.tokens.len() - 1].token;
+        if binary_operator(last).is_some() || prefix_operator(last).is_some() {
+            return ParseError::MissingOperand {
+                operator: last.clone(),
+                span: eof,
+            };
+        }
+        // items between a `(` and its `)`, the innermost one is reported
+        let open = |item: &&Item| match &self.grammar.productions[item.production].rhs[..] {
+            rhs @ [Symbol::Terminal(Token::LPar), ..] => 0 < item.dot && item.dot < rhs.len(),
+            _ => false,
+        };
+        if let Some(item) = items.iter().filter(open).max_by_key(|item| item.origin) {
+            return ParseError::UnbalancedParenthesis {
+                paren: Token::LPar,
+                span: self.tokens[item.origin].span,
+            };
+        }
+        let terminal = items.iter().find_map(|item| {
+            match self.grammar.productions[item.production].rhs.get(item.dot) {
+                Some(Symbol::Terminal(token)) => Some(token.clone()),
+                _ => None,
+            }
+        });
+        return match terminal {
+            Some(expected) => ParseError::Expected {
+                expected,
+                span: eof,
+            },
+            None => ParseError::EmptyInput { span: eof },
+        };
+    }
+
+    fn matches(&self, symbol: &Symbol, position: usize) -> bool {
+        return match (symbol, self.tokens.get(position)) {
+            (Symbol::Terminal(token), Some(spanned)) => spanned.token == *token,
+            (Symbol::Number, Some(spanned)) => matches!(spanned.token, Token::Int(_)),
+            _ => false,
+        };
+    }
+
+    // builds the Earley sets, sets[i] holds the items that are valid after i tokens,
+    // and records every completed item in `completed`
+    fn recognize(&mut self) -> Vec<Vec<Item>> {
+        let grammar = self.grammar;
+        let nullable = grammar.nullable();
+        let mut sets: Vec<Vec<Item>> = vec![Vec::new(); self.tokens.len() + 1];
+        let add = |set: &mut Vec<Item>, item: Item| {
+            if !set.contains(&item) {
+                set.push(item);
+            }
+        };
+        for (index, production) in grammar.productions.iter().enumerate() {
+            if production.lhs == grammar.start {
+                add(
+                    &mut sets[0],
+                    Item {
+                        production: index,
+                        dot: 0,
+                        origin: 0,
+                    },
+                );
+            }
+        }
+
+        for i in 0..sets.len() {
+            let mut k = 0;
+            while k < sets[i].len() {
+                let item = sets[i][k];
+                k += 1;
+                let production = &grammar.productions[item.production];
+                let advanced = Item {
+                    dot: item.dot + 1,
+                    ..item
+                };
+                match production.rhs.get(item.dot) {
+                    // complete: advance the items waiting for the nonterminal
+                    None => {
+                        let key = (production.lhs.clone(), item.origin, i);
+                        let productions = self.completed.entry(key).or_default();
+                        if !productions.contains(&item.production) {
+                            productions.push(item.production);
+                        }
+                        let waiting: Vec<Item> = sets[item.origin]
+                            .iter()
+                            .filter(|waiting| {
+                                let rhs = &grammar.productions[waiting.production].rhs;
+                                rhs.get(waiting.dot)
+                                    == Some(&Symbol::Nonterminal(production.lhs.clone()))
+                            })
+                            .map(|waiting| Item {
+                                dot: waiting.dot + 1,
+                                ..*waiting
+                            })
+                            .collect();
+                        for waiting in waiting {
+                            add(&mut sets[i], waiting);
+                        }
+                    }
+                    // predict: start the productions of the nonterminal here, a nullable one
+                    // may also be skipped
+                    Some(Symbol::Nonterminal(name)) => {
+                        for (index, next) in grammar.productions.iter().enumerate() {
+                            if next.lhs == *name {
+                                add(
+                                    &mut sets[i],
+                                    Item {
+                                        production: index,
+                                        dot: 0,
+                                        origin: i,
+                                    },
+                                );
+                            }
+                        }
+                        if nullable.contains(&name.as_str()) {
+                            add(&mut sets[i], advanced);
+                        }
+                    }
+                    // scan: the terminal matches the next token
+                    Some(symbol) => {
+                        if self.matches(symbol, i) {
+                            add(&mut sets[i + 1], advanced);
+                        }
+                    }
+                }
+            }
+        }
+        return sets;
+    }
+
+    // trees deriving tokens[start..end] from `lhs`, at most MAX_TREES of them, and the number
+    // of all of them; derivations that reach the same nonterminal over the same tokens again
+    // (cycles such as `E -> (E) | E`) are skipped
+    fn trees(
+        &self,
+        lhs: &str,
+        start: usize,
+        end: usize,
+        forest: &mut Forest,
+    ) -> (Rc<Vec<ASTNode>>, usize) {
+        let key = (lhs.to_string(), start, end);
+        if let Some((trees, count)) = forest.done.get(&key) {
+            return (trees.clone(), *count);
+        }
+        let Some(productions) = self.completed.get(&key) else {
+            return (Rc::default(), 0);
+        };
+        if let Some(index) = forest.visiting.iter().position(|visiting| *visiting == key) {
+            forest.cut = forest.cut.min(index);
+            return (Rc::default(), 0);
+        }
+        // trees in the order of the productions of the grammar
+        let mut productions = productions.clone();
+        productions.sort();
+        let depth = forest.visiting.len();
+        forest.visiting.push(key.clone());
+        let outer_cut = std::mem::replace(&mut forest.cut, usize::MAX);
+        let (mut trees, mut count) = (Vec::new(), 0_usize);
+        for index in productions {
+            let rhs = &self.grammar.productions[index].rhs;
+            let needed = MAX_TREES - trees.len();
+            let (sequences, found) = self.sequences(rhs, start, end, needed, forest);
+            count = count.saturating_add(found);
+            for children in sequences {
+                trees.push(self.build(children, start, end));
+            }
+        }
+        forest.visiting.pop();
+        let trees = Rc::new(trees);
+        // without a skipped cycle through a derivation that is still being listed, the trees
+        // are the same wherever the derivation is reached from
+        if forest.cut >= depth {
+            forest.done.insert(key, (trees.clone(), count));
+        }
+        forest.cut = forest.cut.min(outer_cut);
+        return (trees, count);
+    }
+
+    // ways the symbols `rhs` derive tokens[start..end] as lists of children, at most `limit`
+    // of them, and the number of all of them
+    fn sequences(
+        &self,
+        rhs: &[Symbol],
+        start: usize,
+        end: usize,
+        limit: usize,
+        forest: &mut Forest,
+    ) -> (Vec<Vec<Child>>, usize) {
+        let Some((first, rest)) = rhs.split_first() else {
+            return if start == end {
+                (vec![Vec::new(); limit.min(1)], 1)
+            } else {
+                (Vec::new(), 0)
+            };
+        };
+        let (mut sequences, mut count) = (Vec::new(), 0_usize);
+        match first {
+            Symbol::Nonterminal(name) => {
+                // the last symbol derives all remaining tokens
+                let middles = if rest.is_empty() {
+                    end..=end
+                } else {
+                    start..=end
+                };
+                for middle in middles {
+                    let (trees, found) = self.trees(name, start, middle, forest);
+                    if found == 0 {
+                        continue;
+                    }
+                    // once enough sequences are listed the others are only counted
+                    let needed = limit - sequences.len();
+                    let (tails, tails_found) = self.sequences(rest, middle, end, needed, forest);
+                    count = count.saturating_add(found.saturating_mul(tails_found));
+                    for tree in trees.iter() {
+                        for tail in &tails {
+                            if sequences.len() == limit {
+                                break;
+                            }
+                            let mut sequence = vec![Child::Node(tree.clone())];
+                            sequence.extend(tail.iter().cloned());
+                            sequences.push(sequence);
+                        }
+                    }
+                }
+            }
+            symbol => {
+                if start < end && self.matches(symbol, start) {
+                    let (tails, found) = self.sequences(rest, start + 1, end, limit, forest);
+                    count = found;
+                    for mut sequence in tails {
+                        sequence.insert(0, Child::Token(self.tokens[start].token.clone()));
+                        sequences.push(sequence);
+                    }
+                }
+            }
+        }
+        return (sequences, count);
+    }
+
+    // node of a production spanning tokens[start..end]: a literal becomes a leaf, an
+    // operator token is applied to the nonterminals around it and a single nonterminal
+    // without operator, e.g. in `(E)`, is passed through; anything else is an Error node
+    fn build(&self, children: Vec<Child>, start: usize, end: usize) -> ASTNode {
+        let span = if start < end {
+            self.tokens[start].span.to(self.tokens[end - 1].span)
+        } else {
+            Span::default()
+        };
+        let first_is_token = matches!(children.first(), Some(Child::Token(_)));
+        let last_is_token = matches!(children.last(), Some(Child::Token(_)));
+        let mut operands = Vec::new();
+        let mut tokens = Vec::new();
+        for child in children {
+            match child {
+                Child::Node(node) => operands.push(node),
+                Child::Token(token) => tokens.push(token),
+            }
+        }
+
+        let kind = match (operands.len(), tokens.as_slice()) {
+            (0, [token]) => literal(token),
+            (2, _) => tokens.iter().find_map(binary_operator).map(|op| {
+                let right = operands.pop().map(Box::new);
+                let left = operands.pop().map(Box::new);
+                (op.build)(left.unwrap(), right.unwrap())
+            }),
+            (1, [token]) if first_is_token => {
+                prefix_operator(token).map(|op| (op.build)(Box::new(operands[0].clone())))
+            }
+            (1, [token]) if last_is_token => {
+                postfix_operator(token).map(|op| (op.build)(Box::new(operands[0].clone())))
+            }
+            _ => None,
+        };
+        return match kind {
+            Some(kind) => ASTNode::new(kind, span),
+            None if operands.len() == 1 => operands.pop().unwrap(),
+            None => ASTNode::new(NodeKind::Error, span),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{
+        lexer::lexer::{Lexer, Token},
+        parser::{
+            earley::{EarleyParser, Grammar, Production, Symbol, ARITHMETIC, MAX_TREES},
+            parser::{ASTNode, Parser, ResultEval, ShuntiyardParser},
+        },
+    };
+
+    fn parse(input: &str) -> Result<ASTNode> {
+        return Ok(ShuntiyardParser::new(Lexer::new(input)).parse()?);
+    }
+
+    #[test]
+    fn grammar_from_text_test() -> Result<()> {
+        let grammar = Grammar::from_text(ARITHMETIC)?;
+        assert_eq!(grammar.start, "E");
+        assert_eq!(grammar.productions.len(), 8);
+        assert_eq!(
+            grammar.productions[2],
+            Production {
+                lhs: "E".to_string(),
+                rhs: vec![
+                    Symbol::Nonterminal("E".to_string()),
+                    Symbol::Terminal(Token::Or(0)),
+                    Symbol::Nonterminal("E".to_string()),
+                ],
+            }
+        );
+        assert_eq!(
+            grammar.productions[7].rhs,
+            vec![Symbol::Terminal(Token::False)]
+        );
+        assert_eq!(
+            grammar.to_string(),
+            "E -> E + E\nE -> E * E\nE -> E || E\nE -> ( E )\nE -> 0\nE -> 1\nE -> True\nE -> False\n"
+        );
+
+        // rules may continue on the next line and have empty alternatives
+        let grammar = Grammar::from_text("S -> ( S ) S\n   | \nT -> n")?;
+        assert_eq!(grammar.start, "S");
+        assert_eq!(grammar.productions[1].to_string(), "S -> ε");
+        assert_eq!(grammar.productions[2].rhs, vec![Symbol::Number]);
+
+        let errors = [
+            ("", "empty input at 1:1"),
+            ("+ E -> 1", "expected a variable name at 1:1"),
+            ("E -> $", "unexpected character '$' at 1:6"),
+        ];
+        for (input, exp_error) in errors {
+            let error = Grammar::from_text(input).unwrap_err();
+            assert_eq!(error.to_string(), exp_error, "{}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn earley_parse_all_test() -> Result<()> {
+        let grammar = Grammar::from_text(ARITHMETIC)?;
+        let parse_all = |input: &str| EarleyParser::new(&grammar, Lexer::new(input)).parse_all();
+
+        // both groupings of `1 + 1 * 0`, and they evaluate differently
+        let parses = parse_all("1 + 1 * 0")?;
+        assert_eq!(
+            parses.trees,
+            vec![parse("1 + (1 * 0)")?, parse("(1 + 1) * 0")?]
+        );
+        assert_eq!(
+            parses.results,
+            vec![Some(ResultEval::Int(1)), Some(ResultEval::Int(0))]
+        );
+        assert!(parses.is_ambiguous() && parses.is_conflicting());
+        assert_eq!(
+            (parses.trees[1].span.start, parses.trees[1].span.end),
+            (0, 9)
+        );
+
+        // every grouping of an associative operator has the same value
+        let parses = parse_all("1 + 1 + 1 + 0")?;
+        assert_eq!((parses.trees.len(), parses.count), (5, 5));
+        assert!(parses.is_ambiguous() && !parses.is_conflicting());
+
+        // the Catalan number of groupings is counted, only the first trees are listed
+        let parses = parse_all(&vec!["1"; 21].join(" + "))?;
+        assert_eq!(
+            (parses.trees.len(), parses.count),
+            (MAX_TREES, 6_564_120_420)
+        );
+        assert!(parses.is_ambiguous() && !parses.is_conflicting());
+
+        let parses = parse_all("((1)) * true")?;
+        assert_eq!(parses.trees, vec![parse("1 * true")?]);
+        assert!(!parses.is_ambiguous() && !parses.is_conflicting());
+
+        // trees that cannot be evaluated have no result
+        let parses = parse_all("true || 1 * 0")?;
+        assert_eq!(parses.results, vec![None, Some(ResultEval::Bool(true))]);
+        assert!(parses.is_conflicting());
+
+        let errors = [
+            ("1 + 2", "unexpected '2' at 1:5"),
+            ("1 + ", "missing operand for '+' at 1:5"),
+            ("(1 + (1)", "unbalanced parenthesis '(' at 1:1"),
+            ("(1 1)", "unexpected '1' at 1:4"),
+            ("", "empty input at 1:1"),
+            ("1 $", "unexpected character '$' at 1:3"),
+        ];
+        for (input, exp_error) in errors {
+            let error = parse_all(input).unwrap_err();
+            assert_eq!(error.to_string(), exp_error, "{}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn earley_general_grammars_test() -> Result<()> {
+        // unit cycles and empty productions terminate
+        let grammar = Grammar::from_text("E -> E | (E) | n | - E | E !")?;
+        let parses = EarleyParser::new(&grammar, Lexer::new("-(3)!")).parse_all()?;
+        assert_eq!(parses.trees, vec![parse("-(3!)")?, parse("(-3)!")?]);
+        assert!(parses.is_conflicting());
+
+        let grammar = Grammar::from_text("S -> A A 1\nA -> 0 |")?;
+        let parses = EarleyParser::new(&grammar, Lexer::new("0 1")).parse_all()?;
+        assert_eq!(parses.trees.len(), 2);
+        let error = EarleyParser::new(&grammar, Lexer::new("0 0")).parse_all();
+        assert_eq!(error.unwrap_err().to_string(), "expected '1' at 1:4");
+        Ok(())
+    }
+}
@@ -1 +1,7 @@
+pub mod derivation;
+pub mod earley;
+pub mod notation;
 pub mod parser;
+pub mod pratt;
+pub mod pretty;
+pub mod unparser;
@@ -0,0 +1,347 @@
+use crate::{
+    lexer::lexer::{Lexer, Span, SpannedToken, Token},
+    parser::parser::{binary_operator, literal, ASTNode, NodeKind, ParseError, Parser},
+};
+
+// Polish (prefix) and reverse Polish (postfix) notation: every operator has a fixed number of
+// operands, so no parentheses or precedences are needed (`+ 1 * 1 0` and `1 1 0 * +` are
+// `1 + 1 * 0`). `-` is always the binary minus and `!` the logical not, negation and factorial
+// are written with the names below. Only literals, variables and operators can be written.
+pub const NEGATION: &str = "neg";
+pub const FACTORIAL: &str = "fact";
+
+// meaning of a token in both notations
+enum Symbol {
+    Operand(NodeKind),
+    Unary(fn(Box<ASTNode>) -> NodeKind),
+    Binary(fn(Box<ASTNode>, Box<ASTNode>) -> NodeKind),
+}
+
+// None for tokens that have no meaning in the notations, e.g. parentheses
+fn symbol(token: &Token) -> Option<Symbol> {
+    if let Some(kind) = literal(token) {
+        return Some(Symbol::Operand(kind));
+    }
+    return match token {
+        Token::Ident(name) if name == NEGATION => Some(Symbol::Unary(NodeKind::Neg)),
+        Token::Ident(name) if name == FACTORIAL => Some(Symbol::Unary(NodeKind::Factorial)),
+        Token::Ident(name) => Some(Symbol::Operand(NodeKind::Var(name.clone()))),
+        Token::Not(_) => Some(Symbol::Unary(NodeKind::Not)),
+        token => binary_operator(token).map(|op| Symbol::Binary(op.build)),
+    };
+}
+
+// Polish notation front end: an operator is followed by its operands
+pub struct PolishParser<'a> {
+    lexer: Lexer<'a>,
+}
+
+impl<'a> PolishParser<'a> {
+    pub fn new(lexer: Lexer<'a>) -> PolishParser<'a> {
+        return PolishParser { lexer };
+    }
+
+    // parses the operand of `operator`, at the end of the input it is missing
+    fn parse_operand(&mut self, operator: &SpannedToken) -> Result<ASTNode, ParseError> {
+        let next = self.lexer.peek()?;
+        if next.token == Token::Eof {
+            return Err(ParseError::MissingOperand {
+                operator: operator.token.clone(),
+                span: next.span,
+            });
+        }
+        return self.parse_expression();
+    }
+
+    // parses an operand or an operator with all its operands
+    fn parse_expression(&mut self) -> Result<ASTNode, ParseError> {
+        let spanned = self.lexer.next_token()?;
+        let span = spanned.span;
+        return match symbol(&spanned.token) {
+            Some(Symbol::Operand(kind)) => Ok(ASTNode::new(kind, span)),
+            Some(Symbol::Unary(build)) => {
+                let operand = self.parse_operand(&spanned)?;
+                let span = span.to(operand.span);
+                Ok(ASTNode::new(build(Box::new(operand)), span))
+            }
+            Some(Symbol::Binary(build)) => {
+                let left = self.parse_operand(&spanned)?;
+                let right = self.parse_operand(&spanned)?;
+                let span = span.to(right.span);
+                Ok(ASTNode::new(build(Box::new(left), Box::new(right)), span))
+            }
+            None => Err(ParseError::UnexpectedToken {
+                token: spanned.token,
+                span,
+            }),
+        };
+    }
+}
+
+impl<'a> Parser for PolishParser<'a> {
+    // parses one expression, the rest of the input has to be empty
+    fn parse(&mut self) -> Result<ASTNode, ParseError> {
+        let first = self.lexer.peek()?;
+        if first.token == Token::Eof {
+            return Err(ParseError::EmptyInput { span: first.span });
+        }
+        let ast = self.parse_expression()?;
+        let next = self.lexer.next_token()?;
+        return match next.token {
+            Token::Eof => Ok(ast),
+            // another complete expression: its operator is missing
+            _ if symbol(&next.token).is_some() => {
+                Err(ParseError::AdjacentOperands { span: next.span })
+            }
+            token => Err(ParseError::UnexpectedToken {
+                token,
+                span: next.span,
+            }),
+        };
+    }
+}
+
+// reverse Polish notation front end: an operator follows its operands
+pub struct RpnParser<'a> {
+    lexer: Lexer<'a>,
+    operands: Vec<ASTNode>,
+}
+
+impl<'a> RpnParser<'a> {
+    pub fn new(lexer: Lexer<'a>) -> RpnParser<'a> {
+        return RpnParser {
+            lexer,
+            operands: Vec::new(),
+        };
+    }
+
+    fn pop_operand(&mut self, operator: &SpannedToken) -> Result<ASTNode, ParseError> {
+        return self
+            .operands
+            .pop()
+            .ok_or_else(|| ParseError::MissingOperand {
+                operator: operator.token.clone(),
+                span: operator.span,
+            });
+    }
+}
+
+impl<'a> Parser for RpnParser<'a> {
+    // operands are pushed on a stack, operators replace their operands on top of it
+    // by their node, at the end exactly one node has to be left
+    fn parse(&mut self) -> Result<ASTNode, ParseError> {
+        let end = loop {
+            let spanned = self.lexer.next_token()?;
+            let span = spanned.span;
+            match symbol(&spanned.token) {
+                Some(Symbol::Operand(kind)) => self.operands.push(ASTNode::new(kind, span)),
+                Some(Symbol::Unary(build)) => {
+                    let operand = self.pop_operand(&spanned)?;
+                    let span = operand.span.to(span);
+                    self.operands
+                        .push(ASTNode::new(build(Box::new(operand)), span));
+                }
+                Some(Symbol::Binary(build)) => {
+                    let right = self.pop_operand(&spanned)?;
+                    let left = self.pop_operand(&spanned)?;
+                    let span = left.span.to(span);
+                    let kind = build(Box::new(left), Box::new(right));
+                    self.operands.push(ASTNode::new(kind, span));
+                }
+                None if spanned.token == Token::Eof => break span,
+                None => {
+                    return Err(ParseError::UnexpectedToken {
+                        token: spanned.token,
+                        span,
+                    })
+                }
+            }
+        };
+        return match self.operands.len() {
+            0 => Err(ParseError::EmptyInput { span: end }),
+            1 => Ok(self.operands.pop().unwrap()),
+            // the second expression left over is missing an operator to combine it with the first
+            _ => Err(ParseError::AdjacentOperands {
+                span: self.operands[1].span,
+            }),
+        };
+    }
+}
+
+// the tree in Polish notation, None if it contains nodes other than literals, variables and
+// operators
+pub fn to_polish(ast: &ASTNode) -> Option<String> {
+    let mut symbols = Vec::new();
+    write(ast, false, &mut symbols).ok()?;
+    return Some(symbols.join(" "));
+}
+
+// the tree in reverse Polish notation, None if it contains nodes other than literals,
+// variables and operators
+pub fn to_rpn(ast: &ASTNode) -> Option<String> {
+    let mut symbols = Vec::new();
+    write(ast, true, &mut symbols).ok()?;
+    return Some(symbols.join(" "));
+}
+
+// checks that the tree can be written in both notations, otherwise returns the span of the
+// outermost node that cannot
+pub fn check(ast: &ASTNode) -> Result<(), Span> {
+    return write(ast, true, &mut Vec::new());
+}
+
+// appends the symbols of the tree in prefix or postfix order
+fn write(node: &ASTNode, postfix: bool, symbols: &mut Vec<String>) -> Result<(), Span> {
+    let (operator, operands): (String, Vec<&ASTNode>) = match &node.kind {
+        NodeKind::Neg(operand) => (NEGATION.to_string(), vec![operand]),
+        NodeKind::Factorial(operand) => (FACTORIAL.to_string(), vec![operand]),
+        NodeKind::Not(operand) => (Token::Not(8).to_string(), vec![operand]),
+        // names of the unary operators would be read as operators
+        NodeKind::Var(name) if name == NEGATION || name == FACTORIAL => return Err(node.span),
+        NodeKind::Var(name) => (name.clone(), vec![]),
+        kind => match (kind.binary(), literal_text(kind)) {
+            (Some((op, left, right)), _) => (op.token.to_string(), vec![left, right]),
+            (None, Some(text)) => (text, vec![]),
+            (None, None) => return Err(node.span),
+        },
+    };
+    if !postfix {
+        symbols.push(operator.clone());
+    }
+    for operand in operands {
+        write(operand, postfix, symbols)?;
+    }
+    if postfix {
+        symbols.push(operator);
+    }
+    return Ok(());
+}
+
+// source text of a literal node, None for other nodes and for values no literal token stands
+// for, e.g. negative numbers
+fn literal_text(kind: &NodeKind) -> Option<String> {
+    let token = match kind {
+        NodeKind::Number(value) if *value >= 0 => Token::Int(*value),
+        NodeKind::Float(value) if value.is_sign_positive() && value.is_finite() => {
+            Token::Float(*value)
+        }
+        NodeKind::Rational(value) if value.numerator() >= 0 && value.to_decimal().is_some() => {
+            Token::Rational(*value)
+        }
+        NodeKind::Bool(true) => Token::True,
+        NodeKind::Bool(false) => Token::False,
+        _ => return None,
+    };
+    return Some(token.to_string());
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{
+        lexer::lexer::Lexer,
+        parser::{
+            notation::{to_polish, to_rpn, PolishParser, RpnParser},
+            parser::{ASTNode, NodeKind, Parser, ShuntiyardParser},
+        },
+    };
+
+    // the same expression in infix, Polish and reverse Polish notation
+    const NOTATIONS: [(&str, &str, &str); 6] = [
+        ("1 + 1 * 0", "+ 1 * 1 0", "1 1 0 * +"),
+        ("(1 - 2) - 3", "- - 1 2 3", "1 2 - 3 -"),
+        ("1 - (2 - 3)", "- 1 - 2 3", "1 2 3 - -"),
+        ("2 ^ 3 ^ 2", "^ 2 ^ 3 2", "2 3 2 ^ ^"),
+        (
+            "-x! + 1 == 0 && !b",
+            "&& == + neg fact x 1 0 ! b",
+            "x fact neg 1 + 0 == b ! &&",
+        ),
+        (
+            "0.5 * 2.5r < y || false",
+            "|| < * 0.5 2.5r y false",
+            "0.5 2.5r * y < false ||",
+        ),
+    ];
+
+    #[test]
+    fn notation_parsers_test() -> Result<()> {
+        for (infix, polish, rpn) in NOTATIONS {
+            let exp_result = ShuntiyardParser::new(Lexer::new(infix)).parse()?;
+            assert_eq!(PolishParser::new(Lexer::new(polish)).parse()?, exp_result);
+            assert_eq!(RpnParser::new(Lexer::new(rpn)).parse()?, exp_result);
+        }
+        // nodes span their operators and operands
+        let ast = RpnParser::new(Lexer::new("1 1 0 * +")).parse()?;
+        assert_eq!((ast.span.start, ast.span.end), (0, 9));
+        let ast = PolishParser::new(Lexer::new("+ 1 * 1 0")).parse()?;
+        assert_eq!((ast.span.start, ast.span.end), (0, 9));
+
+        let errors = [
+            ("+ 1", "", "missing operand for '+' at 1:4"),
+            ("1 2", "", "missing operator before the operand at 1:3"),
+            ("", "", "empty input at 1:1"),
+            ("+ 1 (", "", "unexpected '(' at 1:5"),
+            ("", "1 +", "missing operand for '+' at 1:3"),
+            ("", "1 2 3 +", "missing operator before the operand at 1:3"),
+            ("", "", "empty input at 1:1"),
+            ("", "neg", "missing operand for 'neg' at 1:1"),
+            ("", "1 if", "unexpected 'if' at 1:3"),
+        ];
+        for (polish, rpn, exp_error) in errors {
+            let error = if rpn.is_empty() {
+                PolishParser::new(Lexer::new(polish)).parse()
+            } else {
+                RpnParser::new(Lexer::new(rpn)).parse()
+            };
+            assert_eq!(
+                error.unwrap_err().to_string(),
+                exp_error,
+                "{} {}",
+                polish,
+                rpn
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn notation_printers_test() -> Result<()> {
+        for (infix, polish, rpn) in NOTATIONS {
+            let ast = ShuntiyardParser::new(Lexer::new(infix)).parse()?;
+            assert_eq!(to_polish(&ast).as_deref(), Some(polish));
+            assert_eq!(to_rpn(&ast).as_deref(), Some(rpn));
+
+            // the shunting yard outputs the operands and operators in reverse Polish notation
+            let tokens = ShuntiyardParser::new(Lexer::new(infix)).parse_rpn()?;
+            let symbols: Vec<String> = tokens.iter().map(|t| t.token.to_string()).collect();
+            assert_eq!(symbols.join(" "), rpn);
+        }
+        let tokens = ShuntiyardParser::new(Lexer::new("-2 * 3")).parse_rpn()?;
+        let spans: Vec<usize> = tokens.iter().map(|t| t.span.start).collect();
+        assert_eq!(spans, vec![1, 0, 5, 3]);
+
+        // calls, conditionals and other constructs have no notation
+        for input in [
+            "max(1, 2)",
+            "1 + (if true then 1 else 2)",
+            "0 < x < 1",
+            "neg + 1",
+        ] {
+            let ast = ShuntiyardParser::new(Lexer::new(input)).parse()?;
+            assert_eq!(to_polish(&ast), None, "{}", input);
+            assert_eq!(to_rpn(&ast), None, "{}", input);
+        }
+        let error = ShuntiyardParser::new(Lexer::new("1 + max(1, 2)"))
+            .parse_rpn()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expression at 1:5 cannot be written in reverse Polish notation"
+        );
+        // values without a literal token, e.g. in trees built by hand
+        assert_eq!(to_rpn(&ASTNode::from(NodeKind::Number(-1))), None);
+        Ok(())
+    }
+}
@@ -1,129 +1,1318 @@
 use std::fmt;
 
-use anyhow::Result;
+use crate::{
+    evaluator::env::Closure,
+    lexer::lexer::{LexError, Lexer, Span, SpannedToken, Token},
+    numeric::numeric::Rational,
+    parser::notation::{self, FACTORIAL, NEGATION},
+};
 
-use crate::lexer::lexer::{Lexer, Token};
+// represents nodes of AST: the kind of the node + the span of the source text it was built from
+#[derive(Debug, Clone)]
+pub struct ASTNode {
+    pub kind: NodeKind,
+    pub span: Span,
+}
 
-// represents nodes of AST
-#[allow(dead_code)]
-#[derive(Debug, PartialEq)]
-pub enum ASTNode {
-    Number(u8),
+// kinds of AST nodes
+#[derive(Debug, PartialEq, Clone)]
+pub enum NodeKind {
+    Number(i64),
+    Float(f64),
+    Rational(Rational),
     Bool(bool),
     Add(Box<ASTNode>, Box<ASTNode>),
+    Subtract(Box<ASTNode>, Box<ASTNode>),
     Multiply(Box<ASTNode>, Box<ASTNode>),
+    Divide(Box<ASTNode>, Box<ASTNode>),
+    Modulo(Box<ASTNode>, Box<ASTNode>),
+    Power(Box<ASTNode>, Box<ASTNode>),
+    ShiftLeft(Box<ASTNode>, Box<ASTNode>),
+    ShiftRight(Box<ASTNode>, Box<ASTNode>),
+    BitAnd(Box<ASTNode>, Box<ASTNode>),
+    BitOr(Box<ASTNode>, Box<ASTNode>),
+    Equal(Box<ASTNode>, Box<ASTNode>),
+    NotEqual(Box<ASTNode>, Box<ASTNode>),
+    Less(Box<ASTNode>, Box<ASTNode>),
+    LessEqual(Box<ASTNode>, Box<ASTNode>),
+    Greater(Box<ASTNode>, Box<ASTNode>),
+    GreaterEqual(Box<ASTNode>, Box<ASTNode>),
+    // chained comparison `0 < x <= 10`: comparisons where each operand after the first is the
+    // left operand of the next comparison, so it is shared by both and evaluated once
+    Chain(Vec<ASTNode>),
+    And(Box<ASTNode>, Box<ASTNode>),
     Or(Box<ASTNode>, Box<ASTNode>),
+    Neg(Box<ASTNode>),
+    Not(Box<ASTNode>),
+    Factorial(Box<ASTNode>),
+    // call of a builtin or host function with its arguments
+    Call(String, Vec<ASTNode>),
+    // conditional: condition, then branch, else branch
+    If(Box<ASTNode>, Box<ASTNode>, Box<ASTNode>),
+    // variable, bound by `let` or by the environment of the host program
+    Var(String),
+    // `let name = value in body`: the variable is only visible in the body
+    Let(String, Box<ASTNode>, Box<ASTNode>),
+    // function `\x -> body` or `fn(x, y) => body` with its parameters
+    Lambda(Vec<String>, Box<ASTNode>),
+    // application of the value of an expression to arguments, e.g. `(\x -> x + 1)(2)`
+    Apply(Box<ASTNode>, Vec<ASTNode>),
+    // program: functions that can call each other and themselves, followed by the main expression
+    Program(Vec<Definition>, Box<ASTNode>),
+    // placeholder for a missing operand in a recovering parse
+    Error,
+}
+
+// named function of a program, `def name(parameters) = body;`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: ASTNode,
+}
+
+// statement of a script, every statement ends with `;`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Statement {
+    // `x = e;` binds the value of `e` to `x` for the statements after it
+    Assign(String, ASTNode),
+    // `print e;` writes the value of `e` to the output of the interpreter
+    Print(ASTNode),
+    // `def f(x) = e;`, adjacent definitions can call each other
+    Definition(Definition),
+    // `e;` evaluates `e` and discards its value
+    Expression(ASTNode),
+}
+
+impl NodeKind {
+    // rebuilds the node with `f` applied to each direct child
+    pub fn map_children(&self, mut f: impl FnMut(&ASTNode) -> ASTNode) -> NodeKind {
+        let mut map = |node: &ASTNode| Box::new(f(node));
+        return match self {
+            NodeKind::Number(value) => NodeKind::Number(*value),
+            NodeKind::Float(value) => NodeKind::Float(*value),
+            NodeKind::Rational(value) => NodeKind::Rational(*value),
+            NodeKind::Bool(value) => NodeKind::Bool(*value),
+            NodeKind::Error => NodeKind::Error,
+            NodeKind::Add(l, r) => NodeKind::Add(map(l), map(r)),
+            NodeKind::Subtract(l, r) => NodeKind::Subtract(map(l), map(r)),
+            NodeKind::Multiply(l, r) => NodeKind::Multiply(map(l), map(r)),
+            NodeKind::Divide(l, r) => NodeKind::Divide(map(l), map(r)),
+            NodeKind::Modulo(l, r) => NodeKind::Modulo(map(l), map(r)),
+            NodeKind::Power(l, r) => NodeKind::Power(map(l), map(r)),
+            NodeKind::ShiftLeft(l, r) => NodeKind::ShiftLeft(map(l), map(r)),
+            NodeKind::ShiftRight(l, r) => NodeKind::ShiftRight(map(l), map(r)),
+            NodeKind::BitAnd(l, r) => NodeKind::BitAnd(map(l), map(r)),
+            NodeKind::BitOr(l, r) => NodeKind::BitOr(map(l), map(r)),
+            NodeKind::Equal(l, r) => NodeKind::Equal(map(l), map(r)),
+            NodeKind::NotEqual(l, r) => NodeKind::NotEqual(map(l), map(r)),
+            NodeKind::Less(l, r) => NodeKind::Less(map(l), map(r)),
+            NodeKind::LessEqual(l, r) => NodeKind::LessEqual(map(l), map(r)),
+            NodeKind::Greater(l, r) => NodeKind::Greater(map(l), map(r)),
+            NodeKind::GreaterEqual(l, r) => NodeKind::GreaterEqual(map(l), map(r)),
+            NodeKind::Chain(links) => {
+                NodeKind::Chain(links.iter().map(|link| *map(link)).collect())
+            }
+            NodeKind::And(l, r) => NodeKind::And(map(l), map(r)),
+            NodeKind::Or(l, r) => NodeKind::Or(map(l), map(r)),
+            NodeKind::Neg(operand) => NodeKind::Neg(map(operand)),
+            NodeKind::Not(operand) => NodeKind::Not(map(operand)),
+            NodeKind::Factorial(operand) => NodeKind::Factorial(map(operand)),
+            NodeKind::Call(name, arguments) => NodeKind::Call(
+                name.clone(),
+                arguments.iter().map(|arg| *map(arg)).collect(),
+            ),
+            NodeKind::If(c, t, e) => NodeKind::If(map(c), map(t), map(e)),
+            NodeKind::Var(name) => NodeKind::Var(name.clone()),
+            NodeKind::Let(name, value, body) => NodeKind::Let(name.clone(), map(value), map(body)),
+            NodeKind::Lambda(parameters, body) => NodeKind::Lambda(parameters.clone(), map(body)),
+            NodeKind::Apply(function, arguments) => NodeKind::Apply(
+                map(function),
+                arguments.iter().map(|arg| *map(arg)).collect(),
+            ),
+            NodeKind::Program(definitions, main) => NodeKind::Program(
+                definitions
+                    .iter()
+                    .map(|definition| Definition {
+                        name: definition.name.clone(),
+                        parameters: definition.parameters.clone(),
+                        body: *map(&definition.body),
+                    })
+                    .collect(),
+                map(main),
+            ),
+        };
+    }
+
+    // operator table entry and operands of a binary operator node, None for all other nodes
+    pub fn binary(&self) -> Option<(&'static Operator, &ASTNode, &ASTNode)> {
+        let (index, left, right) = match self {
+            NodeKind::Or(left, right) => (0, left, right),
+            NodeKind::And(left, right) => (1, left, right),
+            NodeKind::Equal(left, right) => (2, left, right),
+            NodeKind::NotEqual(left, right) => (3, left, right),
+            NodeKind::Less(left, right) => (4, left, right),
+            NodeKind::LessEqual(left, right) => (5, left, right),
+            NodeKind::Greater(left, right) => (6, left, right),
+            NodeKind::GreaterEqual(left, right) => (7, left, right),
+            NodeKind::BitOr(left, right) => (8, left, right),
+            NodeKind::BitAnd(left, right) => (9, left, right),
+            NodeKind::ShiftLeft(left, right) => (10, left, right),
+            NodeKind::ShiftRight(left, right) => (11, left, right),
+            NodeKind::Add(left, right) => (12, left, right),
+            NodeKind::Subtract(left, right) => (13, left, right),
+            NodeKind::Multiply(left, right) => (14, left, right),
+            NodeKind::Divide(left, right) => (15, left, right),
+            NodeKind::Modulo(left, right) => (16, left, right),
+            NodeKind::Power(left, right) => (17, left, right),
+            _ => return None,
+        };
+        return Some((&OPERATORS[index], left, right));
+    }
+
+    // operands of a comparison, None for all other nodes
+    pub fn comparison(&self) -> Option<(&ASTNode, &ASTNode)> {
+        return match self {
+            NodeKind::Equal(left, right)
+            | NodeKind::NotEqual(left, right)
+            | NodeKind::Less(left, right)
+            | NodeKind::LessEqual(left, right)
+            | NodeKind::Greater(left, right)
+            | NodeKind::GreaterEqual(left, right) => Some((left, right)),
+            _ => None,
+        };
+    }
+}
+
+impl ASTNode {
+    pub fn new(kind: NodeKind, span: Span) -> ASTNode {
+        return ASTNode { kind, span };
+    }
+}
+
+// trees are compared structurally, spans only describe where a node came from
+impl PartialEq for ASTNode {
+    fn eq(&self, other: &Self) -> bool {
+        return self.kind == other.kind;
+    }
+}
+
+// nodes built without source text (e.g. in tests) get an empty span
+impl From<NodeKind> for ASTNode {
+    fn from(kind: NodeKind) -> Self {
+        return ASTNode::new(kind, Span::default());
+    }
 }
 
-// result evaluations are either int or bool
-#[derive(Debug, PartialEq)]
+impl From<NodeKind> for Box<ASTNode> {
+    fn from(kind: NodeKind) -> Self {
+        return Box::new(ASTNode::from(kind));
+    }
+}
+
+// result evaluations are either a number (int, rational or float), bool or function
+#[derive(Debug, PartialEq, Clone)]
 pub enum ResultEval {
-    Int(u8),
+    Int(i64),
+    Rational(Rational),
+    Float(f64),
     Bool(bool),
+    Closure(Closure),
 }
 
 // allowing instances of ResultEval to be formatted as strings
+// each kind except functions is printed in a form that parses back to the same value
 impl fmt::Display for ResultEval {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            // the literal of i64::MIN is out of range, it is written as a subtraction instead,
+            // also as numerator of rationals
+            ResultEval::Int(i64::MIN) => write!(f, "({} - 1)", i64::MIN + 1),
             ResultEval::Int(value) => write!(f, "{:?}", value),
+            ResultEval::Rational(value) if value.numerator() == i64::MIN => {
+                write!(f, "({}r - 1)", i64::MIN + 1)?;
+                match value.denominator() {
+                    1 => Ok(()),
+                    denominator => write!(f, " / {}", denominator),
+                }
+            }
+            // exact decimal literal if possible, otherwise a division by a rational literal
+            ResultEval::Rational(value) => match value.to_decimal() {
+                Some(decimal) => write!(f, "{}r", decimal),
+                None => write!(f, "{}/{}r", value.numerator(), value.denominator()),
+            },
+            // always contains `.` or an exponent, so it lexes as float again
+            ResultEval::Float(value) => write!(f, "{:?}", value),
             ResultEval::Bool(value) => write!(f, "{:?}", value),
+            ResultEval::Closure(closure) => write!(f, "<fn({})>", closure.parameters.join(", ")),
         }
     }
 }
 
-pub struct ShuntiyardParser {
-    lexer: Lexer,
-    operator_stack: Vec<Token>,
+// errors found while parsing, each carries the span of the offending token
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+    // the lexer could not tokenize the input
+    Lex(LexError),
+    // operator without one of its operands, e.g. `1 +` or `* 1`
+    MissingOperand { operator: Token, span: Span },
+    // `)` without matching `(` or `(` that is never closed
+    UnbalancedParenthesis { paren: Token, span: Span },
+    // two operands without an operator between them, e.g. `1 1`
+    AdjacentOperands { span: Span },
+    // non-associative operator chained with an operator of the same precedence
+    NonAssociative { operator: Token, span: Span },
+    // function argument missing before or after a comma, e.g. `max(1, )`
+    MissingArgument { span: Span },
+    // token that cannot appear at this position, e.g. a comma outside of calls
+    UnexpectedToken { token: Token, span: Span },
+    // conditional or binding without the token that continues it, e.g. `if c 1 else 2`
+    Expected { expected: Token, span: Span },
+    // `let`, `def` or a parameter list without a name, e.g. `let 1 = 2 in 3`
+    ExpectedName { span: Span },
+    // input without any expression
+    EmptyInput { span: Span },
+    // construct that has no reverse Polish notation, e.g. a call or a conditional
+    Notation { span: Span },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        return match self {
+            ParseError::Lex(error) => error.span(),
+            ParseError::MissingOperand { span, .. }
+            | ParseError::UnbalancedParenthesis { span, .. }
+            | ParseError::AdjacentOperands { span }
+            | ParseError::NonAssociative { span, .. }
+            | ParseError::MissingArgument { span }
+            | ParseError::UnexpectedToken { span, .. }
+            | ParseError::Expected { span, .. }
+            | ParseError::ExpectedName { span }
+            | ParseError::EmptyInput { span }
+            | ParseError::Notation { span } => *span,
+        };
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ParseError::Lex(error) => write!(f, "{}", error),
+            ParseError::MissingOperand { operator, span } => {
+                write!(f, "missing operand for '{}' at {}", operator, span)
+            }
+            ParseError::UnbalancedParenthesis { paren, span } => {
+                write!(f, "unbalanced parenthesis '{}' at {}", paren, span)
+            }
+            ParseError::AdjacentOperands { span } => {
+                write!(f, "missing operator before the operand at {}", span)
+            }
+            ParseError::NonAssociative { operator, span } => write!(
+                f,
+                "operator '{}' at {} cannot be chained without parentheses",
+                operator, span
+            ),
+            ParseError::MissingArgument { span } => {
+                write!(f, "missing function argument at {}", span)
+            }
+            ParseError::UnexpectedToken { token, span } => {
+                write!(f, "unexpected '{}' at {}", token, span)
+            }
+            ParseError::Expected { expected, span } => {
+                write!(f, "expected '{}' at {}", expected, span)
+            }
+            ParseError::ExpectedName { span } => {
+                write!(f, "expected a variable name at {}", span)
+            }
+            ParseError::EmptyInput { span } => write!(f, "empty input at {}", span),
+            ParseError::Notation { span } => write!(
+                f,
+                "expression at {} cannot be written in reverse Polish notation",
+                span
+            ),
+        };
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> Self {
+        return ParseError::Lex(error);
+    }
+}
+
+// associativity of binary operators: how a chain of operators with the same precedence groups
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Associativity {
+    // `1 - 2 - 3` is `(1 - 2) - 3`
+    Left,
+    // `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`
+    Right,
+    // operators of the same precedence cannot follow each other without parentheses
+    Non,
+    // `0 < x <= 10` is `0 < x && x <= 10` with `x` evaluated once
+    Chain,
+}
+
+// entry of the operator table: token, precedence, associativity and the node it builds
+pub struct Operator {
+    pub token: Token,
+    pub precedence: u8,
+    pub associativity: Associativity,
+    pub build: fn(Box<ASTNode>, Box<ASTNode>) -> NodeKind,
+}
+
+impl Operator {
+    // whether the operator read at `span` pops an operator of precedence `top` off the stack
+    // same precedence: left associative and chained operators pop, right associative stay
+    // and non-associative ones are an error
+    pub fn pops(&self, top: u8, span: Span) -> Result<bool, ParseError> {
+        return match self.associativity {
+            Associativity::Left | Associativity::Chain => Ok(self.precedence <= top),
+            Associativity::Right => Ok(self.precedence < top),
+            Associativity::Non if self.precedence == top => Err(ParseError::NonAssociative {
+                operator: self.token.clone(),
+                span,
+            }),
+            Associativity::Non => Ok(self.precedence < top),
+        };
+    }
+}
+
+// binary operators from loosest to tightest binding, `NodeKind::binary` refers to them by index
+pub static OPERATORS: [Operator; 18] = [
+    operator(Token::Or(0), 0, Associativity::Left, NodeKind::Or),
+    operator(Token::And(1), 1, Associativity::Left, NodeKind::And),
+    operator(Token::Eq(2), 2, Associativity::Chain, NodeKind::Equal),
+    operator(Token::Ne(2), 2, Associativity::Chain, NodeKind::NotEqual),
+    operator(Token::Lt(2), 2, Associativity::Chain, NodeKind::Less),
+    operator(Token::Le(2), 2, Associativity::Chain, NodeKind::LessEqual),
+    operator(Token::Gt(2), 2, Associativity::Chain, NodeKind::Greater),
+    operator(
+        Token::Ge(2),
+        2,
+        Associativity::Chain,
+        NodeKind::GreaterEqual,
+    ),
+    operator(Token::BitOr(3), 3, Associativity::Left, NodeKind::BitOr),
+    operator(Token::BitAnd(4), 4, Associativity::Left, NodeKind::BitAnd),
+    operator(Token::Shl(5), 5, Associativity::Left, NodeKind::ShiftLeft),
+    operator(Token::Shr(5), 5, Associativity::Left, NodeKind::ShiftRight),
+    operator(Token::Add(6), 6, Associativity::Left, NodeKind::Add),
+    operator(Token::Sub(6), 6, Associativity::Left, NodeKind::Subtract),
+    operator(Token::Mult(7), 7, Associativity::Left, NodeKind::Multiply),
+    operator(Token::Div(7), 7, Associativity::Left, NodeKind::Divide),
+    operator(Token::Mod(7), 7, Associativity::Left, NodeKind::Modulo),
+    operator(Token::Pow(9), 9, Associativity::Right, NodeKind::Power),
+];
+
+const fn operator(
+    token: Token,
+    precedence: u8,
+    associativity: Associativity,
+    build: fn(Box<ASTNode>, Box<ASTNode>) -> NodeKind,
+) -> Operator {
+    return Operator {
+        token,
+        precedence,
+        associativity,
+        build,
+    };
+}
+
+// entry of the prefix and postfix operator tables
+pub struct UnaryOperator {
+    pub token: Token,
+    pub precedence: u8,
+    pub build: fn(Box<ASTNode>) -> NodeKind,
+}
+
+// prefix operators bind tighter than `* / %` but looser than `^`,
+// so `-2 ^ 2` is `-(2 ^ 2)` and `-2 * 3` is `(-2) * 3`
+pub static PREFIX_OPERATORS: [UnaryOperator; 2] = [
+    UnaryOperator {
+        token: Token::Sub(6),
+        precedence: 8,
+        build: NodeKind::Neg,
+    },
+    UnaryOperator {
+        token: Token::Not(8),
+        precedence: 8,
+        build: NodeKind::Not,
+    },
+];
+
+// postfix operators bind tightest: `-3!` is `-(3!)` and `2 ^ 3!` is `2 ^ (3!)`
+pub static POSTFIX_OPERATORS: [UnaryOperator; 1] = [UnaryOperator {
+    token: Token::Not(8),
+    precedence: 10,
+    build: NodeKind::Factorial,
+}];
+
+// looks up a prefix operator, None for all other tokens
+pub fn prefix_operator(token: &Token) -> Option<&'static UnaryOperator> {
+    return PREFIX_OPERATORS.iter().find(|op| op.token == *token);
+}
+
+// looks up a postfix operator, None for all other tokens
+pub fn postfix_operator(token: &Token) -> Option<&'static UnaryOperator> {
+    return POSTFIX_OPERATORS.iter().find(|op| op.token == *token);
+}
+
+// leaf node of a literal token, None for all other tokens
+pub fn literal(token: &Token) -> Option<NodeKind> {
+    return match token {
+        Token::Int(value) => Some(NodeKind::Number(*value)),
+        Token::Float(value) => Some(NodeKind::Float(*value)),
+        Token::Rational(value) => Some(NodeKind::Rational(*value)),
+        Token::True => Some(NodeKind::Bool(true)),
+        Token::False => Some(NodeKind::Bool(false)),
+        _ => None,
+    };
+}
+
+// front ends turning the tokens of a lexer into an AST
+pub trait Parser {
+    // parses the whole input, malformed input is reported as ParseError at the offending token
+    fn parse(&mut self) -> Result<ASTNode, ParseError>;
+}
+
+// looks up a binary operator in the operator table, None for all other tokens
+pub fn binary_operator(token: &Token) -> Option<&'static Operator> {
+    return OPERATORS.iter().find(|op| op.token == *token);
+}
+
+// extends the comparison or chain `left` by the comparison `build` of its last operand with
+// `right`: `0 < x` extended by `<=` and `10` is the chain of `0 < x` and `x <= 10`
+pub fn chain(
+    left: ASTNode,
+    build: fn(Box<ASTNode>, Box<ASTNode>) -> NodeKind,
+    right: ASTNode,
+) -> ASTNode {
+    let span = left.span.to(right.span);
+    let mut links = match left.kind {
+        NodeKind::Chain(links) => links,
+        kind => vec![ASTNode::new(kind, left.span)],
+    };
+    let Some((_, shared)) = links.last().and_then(|link| link.kind.comparison()) else {
+        unreachable!("Comparison expected")
+    };
+    let shared = shared.clone();
+    let link_span = shared.span.to(right.span);
+    links.push(ASTNode::new(
+        build(Box::new(shared), Box::new(right)),
+        link_span,
+    ));
+    return ASTNode::new(NodeKind::Chain(links), span);
+}
+
+// entries of the operator stack, `-` is pushed as prefix or binary operator depending on its position
+#[derive(Debug)]
+enum StackEntry {
+    Prefix(SpannedToken),
+    Binary(SpannedToken),
+    // comparison continuing the comparison before it, e.g. `<=` in `0 < x <= 10`
+    Chained(SpannedToken),
+    LPar(Span),
+    // function name below the `(` of its arguments, counts the commas seen so far
+    // without a name, the arguments are applied to the value of the operand before the `(`
+    Call {
+        name: Option<String>,
+        span: Span,
+        commas: usize,
+    },
+    // function started by `\` or `fn` with its parameters, waiting for the end of its body
+    Lambda {
+        token: SpannedToken,
+        parameters: Vec<String>,
+    },
+    // definition started by `def` at the start of the input or after another definition,
+    // waiting for the `;` after its body
+    Definition {
+        name: String,
+        parameters: Vec<String>,
+    },
+    // conditional started by `if` or `?` or binding started by `let`, waiting for the token that
+    // continues it (`then`, `else`, `:` or `in`) or, once `expected` is None, for the end of its
+    // last part
+    Construct {
+        token: SpannedToken,
+        expected: Option<Token>,
+    },
+}
+
+impl StackEntry {
+    // precedence from the operator tables, None for parentheses, calls and constructs
+    fn precedence(&self) -> Option<u8> {
+        return match self {
+            StackEntry::Prefix(op) => prefix_operator(&op.token).map(|op| op.precedence),
+            StackEntry::Binary(op) | StackEntry::Chained(op) => {
+                binary_operator(&op.token).map(|op| op.precedence)
+            }
+            StackEntry::LPar(_)
+            | StackEntry::Call { .. }
+            | StackEntry::Construct { .. }
+            | StackEntry::Lambda { .. }
+            | StackEntry::Definition { .. } => None,
+        };
+    }
+}
+
+// error for the token at `span` where an operand of `operator` was expected,
+// after a comma a function argument is missing
+fn missing_operand(operator: &SpannedToken, span: Span) -> ParseError {
+    if matches!(operator.token, Token::Comma | Token::LPar) {
+        return ParseError::MissingArgument { span };
+    }
+    return ParseError::MissingOperand {
+        operator: operator.token.clone(),
+        span,
+    };
+}
+
+pub struct ShuntiyardParser<'a> {
+    lexer: Lexer<'a>,
+    operator_stack: Vec<StackEntry>,
     output_queue: Vec<ASTNode>,
+    // complete definitions in front of the main expression and the span of the first `def`
+    definitions: Vec<Definition>,
+    program: Option<Span>,
+    // operands and operators in the order they reach the output_queue
+    rpn: Vec<SpannedToken>,
 }
-impl ShuntiyardParser {
+impl<'a> ShuntiyardParser<'a> {
     // initializing shuntiyard parser with the provided lexer
-    pub fn new(lexer: Lexer) -> ShuntiyardParser {
+    pub fn new(lexer: Lexer<'a>) -> ShuntiyardParser<'a> {
         let parser = ShuntiyardParser {
-            lexer: lexer,
+            lexer,
             operator_stack: Vec::new(),
             output_queue: Vec::new(),
+            definitions: Vec::new(),
+            program: None,
+            rpn: Vec::new(),
         };
         return parser;
     }
 
-    // pops last two nodes from output_queue and performs an operation based on the provided operator
-    pub fn add_node(&mut self, operator: &Token) {
-        let l_node = self.output_queue.pop().unwrap();
-        let r_node = self.output_queue.pop().unwrap();
+    // pops last two nodes from output_queue and builds the node of the provided operator
+    // from the operator table, the new node spans both operands
+    pub fn add_node(&mut self, operator: &SpannedToken) -> Result<(), ParseError> {
+        // the right operand is on top of the output_queue, so the node keeps source order
+        let r_node = self.pop_operand(operator)?;
+        let l_node = self.pop_operand(operator)?;
+        let span = l_node.span.to(r_node.span);
+        let Some(binary) = binary_operator(&operator.token) else {
+            unreachable!("Operator not defined")
+        };
+        let kind = (binary.build)(Box::new(l_node), Box::new(r_node));
+        self.output_queue.push(ASTNode::new(kind, span));
+        self.rpn.push(operator.clone());
+        return Ok(());
+    }
+
+    // pops the right operand and extends the comparison before it to a chain
+    pub fn add_chain_node(&mut self, operator: &SpannedToken) -> Result<(), ParseError> {
+        let r_node = self.pop_operand(operator)?;
+        let l_node = self.pop_operand(operator)?;
+        let Some(binary) = binary_operator(&operator.token) else {
+            unreachable!("Operator not defined")
+        };
+        self.output_queue.push(chain(l_node, binary.build, r_node));
+        return Ok(());
+    }
 
-        let node = match operator {
-            Token::Add(_) => ASTNode::Add(Box::new(l_node), Box::new(r_node)),
-            Token::Mult(_) => ASTNode::Multiply(Box::new(l_node), Box::new(r_node)),
-            Token::Or(_) => ASTNode::Or(Box::new(r_node), Box::new(l_node)),
-            _ => unimplemented!("Operator not defined"),
+    // pops the operand of a prefix operator, the new node spans operator and operand
+    pub fn add_prefix_node(&mut self, operator: &SpannedToken) -> Result<(), ParseError> {
+        let operand = self.pop_operand(operator)?;
+        let span = operator.span.to(operand.span);
+        let Some(prefix) = prefix_operator(&operator.token) else {
+            unreachable!("Prefix operator not defined")
         };
+        let kind = (prefix.build)(Box::new(operand));
+        // `-x` is written `neg`, `-` is the binary minus in reverse Polish notation
+        self.rpn.push(match kind {
+            NodeKind::Neg(_) => SpannedToken::new(Token::Ident(NEGATION.into()), operator.span),
+            _ => operator.clone(),
+        });
+        self.output_queue.push(ASTNode::new(kind, span));
+        return Ok(());
+    }
+
+    // applies a postfix operator to the last node of the output_queue
+    pub fn add_postfix_node(&mut self, operator: &SpannedToken) -> Result<(), ParseError> {
+        let operand = self.pop_operand(operator)?;
+        let span = operand.span.to(operator.span);
+        let Some(postfix) = postfix_operator(&operator.token) else {
+            unreachable!("Postfix operator not defined")
+        };
+        let kind = (postfix.build)(Box::new(operand));
+        self.output_queue.push(ASTNode::new(kind, span));
+        self.rpn.push(SpannedToken::new(
+            Token::Ident(FACTORIAL.into()),
+            operator.span,
+        ));
+        return Ok(());
+    }
+
+    // pops the last `count` nodes from the output_queue as arguments of a call to `name`
+    pub fn add_call_node(
+        &mut self,
+        name: String,
+        count: usize,
+        span: Span,
+    ) -> Result<(), ParseError> {
+        if self.output_queue.len() < count {
+            return Err(ParseError::MissingArgument { span });
+        }
+        let arguments = self.output_queue.split_off(self.output_queue.len() - count);
+        let node = ASTNode::new(NodeKind::Call(name, arguments), span);
+        self.output_queue.push(node);
+        return Ok(());
+    }
+
+    // pops the last `count` nodes from the output_queue as arguments and the function below them
+    pub fn add_apply_node(&mut self, count: usize, span: Span) -> Result<(), ParseError> {
+        if self.output_queue.len() <= count {
+            return Err(ParseError::MissingArgument { span });
+        }
+        let arguments = self.output_queue.split_off(self.output_queue.len() - count);
+        let function = self.output_queue.pop().unwrap();
+        let node = ASTNode::new(NodeKind::Apply(Box::new(function), arguments), span);
         self.output_queue.push(node);
+        return Ok(());
     }
 
+    // pops the body of a complete function, the node spans `\` or `fn` and the body
+    pub fn add_lambda_node(
+        &mut self,
+        token: &SpannedToken,
+        parameters: Vec<String>,
+    ) -> Result<(), ParseError> {
+        let body = self.pop_operand(token)?;
+        let span = token.span.to(body.span);
+        let kind = NodeKind::Lambda(parameters, Box::new(body));
+        self.output_queue.push(ASTNode::new(kind, span));
+        return Ok(());
+    }
+
+    // pops condition and branches of a complete conditional, the node spans `if` and all three parts
+    pub fn add_conditional_node(&mut self, token: &SpannedToken) -> Result<(), ParseError> {
+        let otherwise = self.pop_operand(token)?;
+        let then = self.pop_operand(token)?;
+        let condition = self.pop_operand(token)?;
+        let span = token.span.to(condition.span).to(otherwise.span);
+        let kind = NodeKind::If(Box::new(condition), Box::new(then), Box::new(otherwise));
+        self.output_queue.push(ASTNode::new(kind, span));
+        return Ok(());
+    }
+
+    // pops value and body of a complete binding and the variable in front of them
+    pub fn add_let_node(&mut self, token: &SpannedToken) -> Result<(), ParseError> {
+        let body = self.pop_operand(token)?;
+        let value = self.pop_operand(token)?;
+        let Some(ASTNode {
+            kind: NodeKind::Var(name),
+            ..
+        }) = self.output_queue.pop()
+        else {
+            unreachable!("Variable of let expected")
+        };
+        let span = token.span.to(body.span);
+        let kind = NodeKind::Let(name, Box::new(value), Box::new(body));
+        self.output_queue.push(ASTNode::new(kind, span));
+        return Ok(());
+    }
+
+    fn pop_operand(&mut self, operator: &SpannedToken) -> Result<ASTNode, ParseError> {
+        return self
+            .output_queue
+            .pop()
+            .ok_or_else(|| missing_operand(operator, operator.span));
+    }
+
+    // builds the node of an operator popped from the operator stack by the token at `span`,
+    // a construct still waiting for `then`, `else`, `:` or `in` expects it there
+    fn reduce(&mut self, entry: &StackEntry, span: Span) -> Result<(), ParseError> {
+        return match entry {
+            StackEntry::Prefix(op) => self.add_prefix_node(op),
+            StackEntry::Binary(op) => self.add_node(op),
+            StackEntry::Chained(op) => self.add_chain_node(op),
+            StackEntry::Lambda { token, parameters } => {
+                self.add_lambda_node(token, parameters.clone())
+            }
+            StackEntry::Construct {
+                token,
+                expected: None,
+            } if token.token == Token::Let => self.add_let_node(token),
+            StackEntry::Construct {
+                token,
+                expected: None,
+            } => self.add_conditional_node(token),
+            StackEntry::Construct {
+                expected: Some(expected),
+                ..
+            } => Err(ParseError::Expected {
+                expected: expected.clone(),
+                span,
+            }),
+            // the input ends inside the body of a definition
+            StackEntry::Definition { .. } => Err(ParseError::Expected {
+                expected: Token::Semicolon,
+                span,
+            }),
+            StackEntry::LPar(_) | StackEntry::Call { .. } => {
+                unreachable!("Parenthesis is no operator")
+            }
+        };
+    }
+
+    // reduces all operators up to the innermost `(`, which stays on the operator stack
+    // returns false if there is no `(`
+    fn reduce_group(&mut self, span: Span) -> Result<bool, ParseError> {
+        loop {
+            match self.operator_stack.last() {
+                Some(StackEntry::LPar(_)) => return Ok(true),
+                Some(StackEntry::Call { .. }) | Some(StackEntry::Definition { .. }) | None => {
+                    return Ok(false);
+                }
+                Some(_) => {
+                    let op = self.operator_stack.pop().unwrap();
+                    self.reduce(&op, span)?;
+                }
+            }
+        }
+    }
+
+    // continues the innermost construct with `then`, `else`, `:` or `in`, the operators and
+    // complete constructs of the part before are reduced first
+    fn continue_construct(&mut self, spanned: &SpannedToken) -> Result<(), ParseError> {
+        loop {
+            match self.operator_stack.last_mut() {
+                Some(StackEntry::Construct {
+                    expected: expected @ Some(_),
+                    ..
+                }) => {
+                    if let Some(token) = expected.take_if(|token| *token != spanned.token) {
+                        return Err(ParseError::Expected {
+                            expected: token,
+                            span: spanned.span,
+                        });
+                    }
+                    // `then` is followed by `else`, the else branch or body ends the construct
+                    *expected = match spanned.token {
+                        Token::Then => Some(Token::Else),
+                        _ => None,
+                    };
+                    return Ok(());
+                }
+                Some(StackEntry::LPar(_))
+                | Some(StackEntry::Call { .. })
+                | Some(StackEntry::Definition { .. })
+                | None => {
+                    return Err(ParseError::UnexpectedToken {
+                        token: spanned.token.clone(),
+                        span: spanned.span,
+                    });
+                }
+                Some(_) => {
+                    let op = self.operator_stack.pop().unwrap();
+                    self.reduce(&op, spanned.span)?;
+                }
+            }
+        }
+    }
+
+    // error for an operand at `span` directly after another operand: inside a conditional
+    // or binding the token that continues it is missing
+    fn adjacent_operands(&self, span: Span) -> ParseError {
+        for entry in self.operator_stack.iter().rev() {
+            match entry {
+                StackEntry::Construct {
+                    expected: Some(expected),
+                    ..
+                } => {
+                    return ParseError::Expected {
+                        expected: expected.clone(),
+                        span,
+                    };
+                }
+                StackEntry::LPar(_) | StackEntry::Call { .. } => break,
+                _ => {}
+            }
+        }
+        return ParseError::AdjacentOperands { span };
+    }
+
+    // ends the definition on the operator stack with `;` at `span`, the operators and
+    // constructs of its body are reduced first
+    fn end_definition(&mut self, span: Span) -> Result<(), ParseError> {
+        loop {
+            match self.operator_stack.pop() {
+                Some(StackEntry::Definition { name, parameters }) => {
+                    let body = self.output_queue.pop().unwrap();
+                    self.definitions.push(Definition {
+                        name,
+                        parameters,
+                        body,
+                    });
+                    return Ok(());
+                }
+                Some(StackEntry::LPar(paren)) => {
+                    return Err(ParseError::UnbalancedParenthesis {
+                        paren: Token::LPar,
+                        span: paren,
+                    });
+                }
+                Some(op) => self.reduce(&op, span)?,
+                None => {
+                    return Err(ParseError::UnexpectedToken {
+                        token: Token::Semicolon,
+                        span,
+                    });
+                }
+            }
+        }
+    }
+
+    // reads the parameter list `(x, y)` of a function started by `fn` or `def`
+    fn parse_parameters(&mut self) -> Result<Vec<String>, ParseError> {
+        let open = self.lexer.next_token()?;
+        if open.token != Token::LPar {
+            return Err(ParseError::Expected {
+                expected: Token::LPar,
+                span: open.span,
+            });
+        }
+        let mut parameters = Vec::new();
+        if self.lexer.peek()?.token == Token::RPar {
+            self.lexer.next_token()?;
+            return Ok(parameters);
+        }
+        loop {
+            parameters.push(self.parse_name()?);
+            let separator = self.lexer.next_token()?;
+            match separator.token {
+                Token::Comma => {}
+                Token::RPar => return Ok(parameters),
+                _ => {
+                    return Err(ParseError::Expected {
+                        expected: Token::RPar,
+                        span: separator.span,
+                    });
+                }
+            }
+        }
+    }
+
+    // reads the name of a variable or parameter
+    fn parse_name(&mut self) -> Result<String, ParseError> {
+        let name = self.lexer.next_token()?;
+        let Token::Ident(variable) = name.token else {
+            return Err(ParseError::ExpectedName { span: name.span });
+        };
+        return Ok(variable);
+    }
+
+    // reads the token that separates the parameters of a function from its body
+    fn expect_token(&mut self, expected: Token) -> Result<SpannedToken, ParseError> {
+        let spanned = self.lexer.next_token()?;
+        if spanned.token != expected {
+            return Err(ParseError::Expected {
+                expected,
+                span: spanned.span,
+            });
+        }
+        return Ok(spanned);
+    }
+
+    // parses the input and returns its operands and operators in reverse Polish notation,
+    // the order in which the algorithm outputs them: `1 + 1 * 0` is `1 1 0 * +`
+    // only literals, variables and operators can be written in this notation
+    pub fn parse_rpn(&mut self) -> Result<Vec<SpannedToken>, ParseError> {
+        let ast = self.parse()?;
+        notation::check(&ast).map_err(|span| ParseError::Notation { span })?;
+        return Ok(std::mem::take(&mut self.rpn));
+    }
+
+    // the call owning the group on top of the operator stack, None if the group is not the
+    // argument list of a call
+    // expects the `LPar` of the group on top of the stack, as `reduce_group` leaves it
+    fn innermost_call(&mut self) -> Option<&mut StackEntry> {
+        debug_assert!(matches!(
+            self.operator_stack.last(),
+            Some(StackEntry::LPar(_))
+        ));
+        let below = self.operator_stack.len().checked_sub(2)?;
+        return match self.operator_stack.get_mut(below) {
+            Some(entry @ StackEntry::Call { .. }) => Some(entry),
+            _ => None,
+        };
+    }
+}
+
+impl<'a> Parser for ShuntiyardParser<'a> {
     // parsing of the input tokens using the Shunting Yard algorithm until EOF
-    pub fn parse(&mut self) -> Result<ASTNode> {
-        while let Ok(token) = self.lexer.next_token() {
-            match token {
-                // Converting Zero, One, True, False to ASTNode & push to output_queue 
-                Token::Zero | Token::One => self
-                    .output_queue
-                    .push(ASTNode::Number(token.to_string().parse().unwrap())),
-                Token::True => self.output_queue.push(ASTNode::Bool(true)),
-                Token::False => self.output_queue.push(ASTNode::Bool(false)),
-                // Add, Mult, Or: checks the topmost operator on the operator_stack 
+    fn parse(&mut self) -> Result<ASTNode, ParseError> {
+        // true at the start, after `(`, `,` and after operators: the next token has to be an operand
+        let mut expect_operand = true;
+        // operator or comma that is still waiting for its right operand
+        let mut pending: Option<SpannedToken> = None;
+        let end = loop {
+            let spanned = self.lexer.next_token()?;
+            let span = spanned.span;
+            let operand = literal(&spanned.token);
+            match &spanned.token {
+                // Converting Int, Float, Rational, True, False to ASTNode & push to output_queue
+                _ if operand.is_some() => {
+                    if !expect_operand {
+                        return Err(self.adjacent_operands(span));
+                    }
+                    let kind = operand.unwrap();
+                    self.output_queue.push(ASTNode::new(kind, span));
+                    self.rpn.push(spanned.clone());
+                    expect_operand = false;
+                    pending = None;
+                }
+                // name: a function if its arguments in parentheses follow, otherwise a variable
+                Token::Ident(name) => {
+                    if !expect_operand {
+                        return Err(self.adjacent_operands(span));
+                    }
+                    pending = None;
+                    if self.lexer.peek()?.token != Token::LPar {
+                        let kind = NodeKind::Var(name.clone());
+                        self.output_queue.push(ASTNode::new(kind, span));
+                        self.rpn.push(spanned.clone());
+                        expect_operand = false;
+                        continue;
+                    }
+                    self.operator_stack.push(StackEntry::Call {
+                        name: Some(name.clone()),
+                        span,
+                        commas: 0,
+                    });
+                }
+                // `if` starts a conditional where an operand is expected, its condition follows
+                Token::If => {
+                    if !expect_operand {
+                        return Err(self.adjacent_operands(span));
+                    }
+                    pending = Some(spanned.clone());
+                    self.operator_stack.push(StackEntry::Construct {
+                        token: spanned,
+                        expected: Some(Token::Then),
+                    });
+                }
+                // `let` binds the name after it to the value after `=`, its variable is pushed
+                // to the output_queue in front of value and body
+                Token::Let => {
+                    if !expect_operand {
+                        return Err(self.adjacent_operands(span));
+                    }
+                    let name_span = self.lexer.peek()?.span;
+                    let variable = self.parse_name()?;
+                    let assign = self.expect_token(Token::Assign)?;
+                    self.output_queue
+                        .push(ASTNode::new(NodeKind::Var(variable), name_span));
+                    pending = Some(assign);
+                    self.operator_stack.push(StackEntry::Construct {
+                        token: spanned,
+                        expected: Some(Token::In),
+                    });
+                }
+                // `def name(x, y) =` starts a definition, only allowed in front of the main expression
+                Token::Def => {
+                    if !expect_operand {
+                        return Err(self.adjacent_operands(span));
+                    }
+                    if !self.operator_stack.is_empty() || !self.output_queue.is_empty() {
+                        return Err(ParseError::UnexpectedToken {
+                            token: Token::Def,
+                            span,
+                        });
+                    }
+                    self.program.get_or_insert(span);
+                    let name = self.parse_name()?;
+                    let parameters = self.parse_parameters()?;
+                    pending = Some(self.expect_token(Token::Assign)?);
+                    self.operator_stack
+                        .push(StackEntry::Definition { name, parameters });
+                }
+                // `print` only starts statements of scripts
+                Token::Print => {
+                    if !expect_operand {
+                        return Err(self.adjacent_operands(span));
+                    }
+                    return Err(ParseError::UnexpectedToken {
+                        token: Token::Print,
+                        span,
+                    });
+                }
+                // `;` ends the body of a definition, a definition or the main expression follows
+                Token::Semicolon => {
+                    if let Some(operator) = pending.take() {
+                        return Err(missing_operand(&operator, span));
+                    }
+                    if expect_operand {
+                        return Err(ParseError::MissingOperand {
+                            operator: Token::Semicolon,
+                            span,
+                        });
+                    }
+                    self.end_definition(span)?;
+                    expect_operand = true;
+                }
+                // `\x ->` and `fn(x, y) =>` start a function, its body follows and extends
+                // as far as possible
+                Token::Lambda | Token::Fn => {
+                    if !expect_operand {
+                        return Err(self.adjacent_operands(span));
+                    }
+                    let (parameters, arrow) = if spanned.token == Token::Lambda {
+                        (vec![self.parse_name()?], self.expect_token(Token::Arrow)?)
+                    } else {
+                        (
+                            self.parse_parameters()?,
+                            self.expect_token(Token::FatArrow)?,
+                        )
+                    };
+                    pending = Some(arrow);
+                    self.operator_stack.push(StackEntry::Lambda {
+                        token: spanned,
+                        parameters,
+                    });
+                }
+                // `?` binds loosest: the whole operand before it is the condition,
+                // conditionals in an else branch stay open, so `a ? b : c ? d : e` nests to the right
+                Token::Question => {
+                    if expect_operand {
+                        return Err(ParseError::MissingOperand {
+                            operator: Token::Question,
+                            span,
+                        });
+                    }
+                    while let Some(top) = self.operator_stack.last() {
+                        if top.precedence().is_none() {
+                            break;
+                        }
+                        let op = self.operator_stack.pop().unwrap();
+                        self.reduce(&op, span)?;
+                    }
+                    expect_operand = true;
+                    pending = Some(spanned.clone());
+                    self.operator_stack.push(StackEntry::Construct {
+                        token: spanned,
+                        expected: Some(Token::Colon),
+                    });
+                }
+                // `then`, `else`, `:` and `in` end a part of the innermost construct
+                Token::Then | Token::Else | Token::Colon | Token::In => {
+                    if let Some(operator) = pending.take() {
+                        return Err(missing_operand(&operator, span));
+                    }
+                    if expect_operand {
+                        return Err(ParseError::MissingOperand {
+                            operator: spanned.token,
+                            span,
+                        });
+                    }
+                    self.continue_construct(&spanned)?;
+                    expect_operand = true;
+                    pending = Some(spanned);
+                }
+                // prefix operators where an operand is expected (start, after `(` or another operator):
+                // apply to the operand that follows, nothing to pop yet
+                token if expect_operand && prefix_operator(token).is_some() => {
+                    pending = Some(spanned.clone());
+                    self.operator_stack.push(StackEntry::Prefix(spanned));
+                }
+                // postfix operators after an operand: operators binding tighter are applied first,
+                // then the postfix operator applies to the operand on top of the output_queue
+                token if !expect_operand && postfix_operator(token).is_some() => {
+                    let precedence = postfix_operator(token).unwrap().precedence;
+                    while let Some(top) = self.operator_stack.last() {
+                        if top.precedence().is_none_or(|o2| o2 <= precedence) {
+                            break;
+                        }
+                        let op = self.operator_stack.pop().unwrap();
+                        self.reduce(&op, span)?;
+                    }
+                    self.add_postfix_node(&spanned)?;
+                }
+                // binary operators: checks the topmost operator on the operator_stack
                 // and compares its precedence with the current operator
-                Token::Add(o1) | Token::Mult(o1) | Token::Or(o1) => {
-                    while self.operator_stack.len() > 0 && self.operator_stack.last() != None {
-                        match self.operator_stack.last() {
-                            Some(Token::Add(o2)) | Some(Token::Mult(o2)) | Some(Token::Or(o2)) => {
-                                if o1 <= *o2 {
-                                    let op = self.operator_stack.pop().unwrap();
-                                    self.add_node(&op);
-                                } else {
-                                    break;
-                                }
-                            }
-                            _ => break,
+                token if binary_operator(token).is_some() => {
+                    if expect_operand {
+                        return Err(ParseError::MissingOperand {
+                            operator: token.clone(),
+                            span,
+                        });
+                    }
+                    let o1 = binary_operator(token).unwrap();
+                    // a comparison popping a comparison continues its chain
+                    let mut chained = false;
+                    while let Some(top) = self.operator_stack.last() {
+                        let Some(o2) = top.precedence() else {
+                            break;
+                        };
+                        if !o1.pops(o2, span)? {
+                            break;
                         }
+                        chained = o1.associativity == Associativity::Chain && o1.precedence == o2;
+                        let op = self.operator_stack.pop().unwrap();
+                        self.reduce(&op, span)?;
                     }
-                    self.operator_stack.push(token)
+                    expect_operand = true;
+                    pending = Some(spanned.clone());
+                    self.operator_stack.push(match chained {
+                        true => StackEntry::Chained(spanned),
+                        false => StackEntry::Binary(spanned),
+                    })
+                }
+                // prefix-only operator after an operand, e.g. `1 !true`
+                token if prefix_operator(token).is_some() => {
+                    return Err(self.adjacent_operands(span));
                 }
                 // left parenthesis pushed to operator stack
-                Token::LPar => self.operator_stack.push(token),
+                Token::LPar => {
+                    // after an operand it applies the value of the operand to the arguments,
+                    // application binds tighter than all operators: `-f(1)` is `-(f(1))`
+                    if !expect_operand {
+                        let function = self.output_queue.last().map_or(span, |node| node.span);
+                        self.operator_stack.push(StackEntry::Call {
+                            name: None,
+                            span: function,
+                            commas: 0,
+                        });
+                        expect_operand = true;
+                    }
+                    let empty = self.lexer.peek()?.token == Token::RPar;
+                    let call = matches!(self.operator_stack.last(), Some(StackEntry::Call { .. }));
+                    // opens an argument list: unless it is empty, the first argument is missing
+                    // until an operand follows
+                    if call && !empty {
+                        pending = Some(spanned.clone());
+                    }
+                    // an empty group is no operand, without an operator waiting for one the
+                    // group is reported at its `(`
+                    if !call && empty && pending.is_none() {
+                        return Err(ParseError::EmptyInput { span });
+                    }
+                    self.operator_stack.push(StackEntry::LPar(span))
+                }
+                // comma: ends an argument of the innermost call
+                Token::Comma => {
+                    if let Some(operator) = pending.take() {
+                        return Err(missing_operand(&operator, span));
+                    }
+                    if !self.reduce_group(span)? {
+                        return Err(ParseError::UnexpectedToken {
+                            token: Token::Comma,
+                            span,
+                        });
+                    }
+                    let Some(StackEntry::Call { commas, .. }) = self.innermost_call() else {
+                        return Err(ParseError::UnexpectedToken {
+                            token: Token::Comma,
+                            span,
+                        });
+                    };
+                    *commas += 1;
+                    if expect_operand {
+                        return Err(ParseError::MissingArgument { span });
+                    }
+                    expect_operand = true;
+                    pending = Some(spanned);
+                }
                 // right parenthesis
-                Token::RPar => loop {
-                    match self.operator_stack.last() {
-                        // pops operators from the operator_stack until LPar found
-                        Some(&Token::LPar) => {
-                            self.operator_stack.pop().unwrap();
-                            break;
-                        }
-                        // add_node to create the corresponding AST nodes
-                        _ => {
-                            let op = &self.operator_stack.pop().unwrap();
-                            self.add_node(op);
+                Token::RPar => {
+                    if let Some(operator) = pending.take() {
+                        return Err(missing_operand(&operator, span));
+                    }
+                    // pops operators from the operator_stack until LPar found
+                    // reduce to create the corresponding AST nodes
+                    if !self.reduce_group(span)? {
+                        return Err(ParseError::UnbalancedParenthesis {
+                            paren: Token::RPar,
+                            span,
+                        });
+                    }
+                    self.operator_stack.pop();
+                    // closes an argument list: commas separate the arguments, `f()` has none
+                    if let Some(StackEntry::Call { .. }) = self.operator_stack.last() {
+                        let Some(StackEntry::Call {
+                            name,
+                            span: name_span,
+                            commas,
+                        }) = self.operator_stack.pop()
+                        else {
+                            unreachable!("Call expected")
+                        };
+                        let count = if expect_operand { 0 } else { commas + 1 };
+                        match name {
+                            Some(name) => self.add_call_node(name, count, name_span.to(span))?,
+                            None => self.add_apply_node(count, name_span.to(span))?,
                         }
+                        expect_operand = false;
                     }
-                },
-                Token::Eof => break,
+                }
+                // `=` outside of `let`, `->` and `=>` outside of functions
+                Token::Assign | Token::Arrow | Token::FatArrow => {
+                    if let Some(operator) = pending.take() {
+                        return Err(missing_operand(&operator, span));
+                    }
+                    if expect_operand {
+                        return Err(ParseError::MissingOperand {
+                            operator: spanned.token,
+                            span,
+                        });
+                    }
+                    return Err(ParseError::UnexpectedToken {
+                        token: spanned.token,
+                        span,
+                    });
+                }
+                Token::Eof => {
+                    if let Some(operator) = pending {
+                        return Err(missing_operand(&operator, span));
+                    }
+                    break span;
+                }
+                _ => unreachable!("Operator without precedence"),
             }
             // println!(
             //     "Current Token {:?} & Current Stack {:?} & Current output queue {:?}",
             //     token, self.operator_stack, self.output_queue
             // )
-        }
-        while self.operator_stack.len() > 0 {
-            // Pop them off and push them to the output_queue
-            let op = &self.operator_stack.pop().unwrap();
-            self.add_node(op);
+        };
+        while let Some(op) = self.operator_stack.pop() {
+            // unclosed parenthesis, otherwise pop them off and push them to the output_queue
+            if let StackEntry::LPar(span) = op {
+                return Err(ParseError::UnbalancedParenthesis {
+                    paren: Token::LPar,
+                    span,
+                });
+            }
+            self.reduce(&op, end)?;
         }
         // println!(
         //     "End Stack {:?} & End output queue {:?}",
         //     self.operator_stack, self.output_queue
         // );
-        Ok(self.output_queue.pop().unwrap())
+        let main = self
+            .output_queue
+            .pop()
+            .ok_or(ParseError::EmptyInput { span: end })?;
+        let Some(start) = self.program else {
+            return Ok(main);
+        };
+        let span = start.to(main.span);
+        let definitions = std::mem::take(&mut self.definitions);
+        return Ok(ASTNode::new(
+            NodeKind::Program(definitions, Box::new(main)),
+            span,
+        ));
     }
 }
 
@@ -131,21 +1320,21 @@ impl ShuntiyardParser {
 mod test {
     use ::anyhow::Result;
 
-    use crate::{lexer::lexer::Lexer, parser::parser::ASTNode};
+    use crate::{
+        lexer::lexer::{Lexer, Span, Token},
+        parser::parser::{ASTNode, Definition, NodeKind, ParseError, OPERATORS, PREFIX_OPERATORS},
+    };
 
-    use super::ShuntiyardParser;
+    use super::{operator, Associativity, Parser, ShuntiyardParser};
 
     #[test]
     fn parsing_mult_add_test() -> Result<()> {
         let input = "1 + 1 * 0";
-        let exp_result = ASTNode::Add(
-            Box::new(ASTNode::Multiply(
-                Box::new(ASTNode::Number(0)),
-                Box::new(ASTNode::Number(1)),
-            )),
-            Box::new(ASTNode::Number(1)),
-        );
-        let lexer = Lexer::new(input.into());
+        let exp_result = ASTNode::from(NodeKind::Add(
+            NodeKind::Number(1).into(),
+            NodeKind::Multiply(NodeKind::Number(1).into(), NodeKind::Number(0).into()).into(),
+        ));
+        let lexer = Lexer::new(input);
         let mut parser = ShuntiyardParser::new(lexer);
         let result = parser.parse();
         let ast = match result {
@@ -159,17 +1348,15 @@ mod test {
     #[test]
     fn parsing_or_test() -> Result<()> {
         let input = "1 + 1 * 0 || true";
-        let exp_result = ASTNode::Or(
-            Box::new(ASTNode::Add(
-                Box::new(ASTNode::Multiply(
-                    Box::new(ASTNode::Number(0)),
-                    Box::new(ASTNode::Number(1)),
-                )),
-                Box::new(ASTNode::Number(1)),
-            )),
-            Box::new(ASTNode::Bool(true)),
-        );
-        let lexer = Lexer::new(input.into());
+        let exp_result = ASTNode::from(NodeKind::Or(
+            NodeKind::Add(
+                NodeKind::Number(1).into(),
+                NodeKind::Multiply(NodeKind::Number(1).into(), NodeKind::Number(0).into()).into(),
+            )
+            .into(),
+            NodeKind::Bool(true).into(),
+        ));
+        let lexer = Lexer::new(input);
         let mut parser = ShuntiyardParser::new(lexer);
         let result = parser.parse();
         let ast = match result {
@@ -182,9 +1369,9 @@ mod test {
 
     #[test]
     fn parsing_parenthesis_test() -> Result<()> {
-        let input = "(()(()()()(1)))";
-        let exp_result = ASTNode::Number(1);
-        let lexer = Lexer::new(input.into());
+        let input = "((((1))))";
+        let exp_result = ASTNode::from(NodeKind::Number(1));
+        let lexer = Lexer::new(input);
         let mut parser = ShuntiyardParser::new(lexer);
         let result = parser.parse();
         let ast = match result {
@@ -192,6 +1379,736 @@ mod test {
             Err(_) => panic!("Error while parsing {:?}", input),
         };
         assert_eq!(ast, exp_result);
+        // empty groups are no operands
+        let error = ShuntiyardParser::new(Lexer::new("(()(()()()(1)))")).parse();
+        assert_eq!(error.unwrap_err().to_string(), "empty input at 1:2");
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_int_literals_test() -> Result<()> {
+        let input = "42 * 0x10";
+        let exp_result = ASTNode::from(NodeKind::Multiply(
+            NodeKind::Number(42).into(),
+            NodeKind::Number(16).into(),
+        ));
+        let lexer = Lexer::new(input);
+        let mut parser = ShuntiyardParser::new(lexer);
+        let ast = parser.parse()?;
+        assert_eq!(ast, exp_result);
+
+        // overflowing literals are reported by the lexer instead of panicking
+        let lexer = Lexer::new("1 + 99999999999999999999");
+        let mut parser = ShuntiyardParser::new(lexer);
+        assert!(parser.parse().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_spans_test() -> Result<()> {
+        let input = "1 + (20 * 3)";
+        let lexer = Lexer::new(input);
+        let mut parser = ShuntiyardParser::new(lexer);
+        let ast = parser.parse()?;
+        assert_eq!(
+            ast.span,
+            Span {
+                start: 0,
+                end: 11,
+                line: 1,
+                column: 1
+            }
+        );
+
+        let NodeKind::Add(one, mult) = &ast.kind else {
+            panic!("Expected addition, received {:?}", ast);
+        };
+        assert_eq!(&input[one.span.start..one.span.end], "1");
+        assert_eq!(&input[mult.span.start..mult.span.end], "20 * 3");
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_operators_test() -> Result<()> {
+        let input = "!(8 - 2 - 1 < 10 / 2 % 3) && 2 ^ 3 << 1 != 1 | 6 & 3";
+        let number = |value| -> Box<ASTNode> { NodeKind::Number(value).into() };
+        let exp_result = ASTNode::from(NodeKind::And(
+            NodeKind::Not(
+                NodeKind::Less(
+                    NodeKind::Subtract(NodeKind::Subtract(number(8), number(2)).into(), number(1))
+                        .into(),
+                    NodeKind::Modulo(NodeKind::Divide(number(10), number(2)).into(), number(3))
+                        .into(),
+                )
+                .into(),
+            )
+            .into(),
+            NodeKind::NotEqual(
+                NodeKind::ShiftLeft(NodeKind::Power(number(2), number(3)).into(), number(1)).into(),
+                NodeKind::BitOr(number(1), NodeKind::BitAnd(number(6), number(3)).into()).into(),
+            )
+            .into(),
+        ));
+        let lexer = Lexer::new(input);
+        let mut parser = ShuntiyardParser::new(lexer);
+        let ast = parser.parse()?;
+        assert_eq!(ast, exp_result);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors_test() {
+        let span = |start, end| Span {
+            start,
+            end,
+            line: 1,
+            column: start + 1,
+        };
+        let inputs = vec![
+            (
+                "1 +",
+                ParseError::MissingOperand {
+                    operator: Token::Add(6),
+                    span: span(3, 3),
+                },
+            ),
+            (
+                "* 1",
+                ParseError::MissingOperand {
+                    operator: Token::Mult(7),
+                    span: span(0, 1),
+                },
+            ),
+            (
+                "(1 + ) * 2",
+                ParseError::MissingOperand {
+                    operator: Token::Add(6),
+                    span: span(5, 6),
+                },
+            ),
+            (
+                ")",
+                ParseError::UnbalancedParenthesis {
+                    paren: Token::RPar,
+                    span: span(0, 1),
+                },
+            ),
+            (
+                "2 * (1",
+                ParseError::UnbalancedParenthesis {
+                    paren: Token::LPar,
+                    span: span(4, 5),
+                },
+            ),
+            ("1 1", ParseError::AdjacentOperands { span: span(2, 3) }),
+            ("(1) 1", ParseError::AdjacentOperands { span: span(4, 5) }),
+            (
+                "true !false",
+                ParseError::AdjacentOperands { span: span(6, 11) },
+            ),
+            ("", ParseError::EmptyInput { span: span(0, 0) }),
+            ("  ()", ParseError::EmptyInput { span: span(2, 3) }),
+        ];
+
+        for (input, exp_error) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            let result = parser.parse();
+            println!("Expression {:?} Result {:?}", input, result);
+            assert_eq!(result, Err(exp_error));
+        }
+    }
+
+    // every combination of a few tokens either parses or returns an error, but never panics
+    #[test]
+    fn parse_never_panics_test() {
+        let pieces = ["1", "+", "-", "!", "(", ")", "*"];
+        let mut inputs = vec![String::new()];
+        for _ in 0..5 {
+            let mut longer = Vec::new();
+            for input in &inputs {
+                for piece in pieces {
+                    let next = format!("{} {}", input, piece);
+                    let _ = ShuntiyardParser::new(Lexer::new(&next)).parse();
+                    longer.push(next);
+                }
+            }
+            inputs = longer;
+        }
+    }
+
+    #[test]
+    fn parsing_associativity_test() -> Result<()> {
+        let number = |value| -> Box<ASTNode> { NodeKind::Number(value).into() };
+        let inputs = vec![
+            (
+                "8 - 2 - 1",
+                NodeKind::Subtract(NodeKind::Subtract(number(8), number(2)).into(), number(1)),
+            ),
+            (
+                "2 ^ 3 ^ 2",
+                NodeKind::Power(number(2), NodeKind::Power(number(3), number(2)).into()),
+            ),
+            (
+                "1 + 2 * 3 ^ 2 ^ 1 - 4",
+                NodeKind::Subtract(
+                    NodeKind::Add(
+                        number(1),
+                        NodeKind::Multiply(
+                            number(2),
+                            NodeKind::Power(
+                                number(3),
+                                NodeKind::Power(number(2), number(1)).into(),
+                            )
+                            .into(),
+                        )
+                        .into(),
+                    )
+                    .into(),
+                    number(4),
+                ),
+            ),
+            (
+                "(1 < 2) == true",
+                NodeKind::Equal(
+                    NodeKind::Less(number(1), number(2)).into(),
+                    NodeKind::Bool(true).into(),
+                ),
+            ),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(parser.parse()?, ASTNode::from(exp_result));
+        }
+
+        // comparisons chain, the operand between two comparisons belongs to both
+        let var = |name: &str| -> Box<ASTNode> { NodeKind::Var(name.into()).into() };
+        let b_plus_1 = || -> Box<ASTNode> { NodeKind::Add(var("b"), number(1)).into() };
+        let inputs = vec![
+            (
+                "0 < x <= 10",
+                NodeKind::Chain(vec![
+                    NodeKind::Less(number(0), var("x")).into(),
+                    NodeKind::LessEqual(var("x"), number(10)).into(),
+                ]),
+            ),
+            (
+                "1 == 2 != 3 > 4",
+                NodeKind::Chain(vec![
+                    NodeKind::Equal(number(1), number(2)).into(),
+                    NodeKind::NotEqual(number(2), number(3)).into(),
+                    NodeKind::Greater(number(3), number(4)).into(),
+                ]),
+            ),
+            (
+                "a < b + 1 >= c || d",
+                NodeKind::Or(
+                    NodeKind::Chain(vec![
+                        NodeKind::Less(var("a"), b_plus_1()).into(),
+                        NodeKind::GreaterEqual(b_plus_1(), var("c")).into(),
+                    ])
+                    .into(),
+                    var("d"),
+                ),
+            ),
+            (
+                "a < b && b < c",
+                NodeKind::And(
+                    NodeKind::Less(var("a"), var("b")).into(),
+                    NodeKind::Less(var("b"), var("c")).into(),
+                ),
+            ),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(parser.parse()?, ASTNode::from(exp_result), "{}", input);
+        }
+        let mut parser = ShuntiyardParser::new(Lexer::new("0 < x <= 10"));
+        let ast = parser.parse()?;
+        let NodeKind::Chain(links) = &ast.kind else {
+            panic!("Expected chain, received {:?}", ast);
+        };
+        assert_eq!((links[1].span.start, links[1].span.end), (4, 11));
+        assert_eq!((ast.span.start, ast.span.end), (0, 11));
+
+        // the table can declare non-associative operators, they cannot follow each other
+        let span = Span {
+            start: 6,
+            end: 7,
+            line: 1,
+            column: 7,
+        };
+        let non = operator(Token::Lt(2), 2, Associativity::Non, NodeKind::Less);
+        assert_eq!(non.pops(3, span), Ok(true));
+        assert_eq!(non.pops(1, span), Ok(false));
+        let error = non.pops(2, span).unwrap_err();
+        assert!(matches!(
+            error,
+            ParseError::NonAssociative {
+                operator: Token::Lt(_),
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "operator '<' at 1:7 cannot be chained without parentheses"
+        );
+        Ok(())
+    }
+
+    // the operator table agrees with the precedences the lexer stores in the tokens
+    #[test]
+    fn operator_table_test() {
+        let operand = || Box::new(ASTNode::from(NodeKind::Error));
+        for op in OPERATORS.iter() {
+            assert_eq!(op.token.precedence(), Some(op.precedence));
+            // the nodes built by an entry lead back to it
+            let node = (op.build)(operand(), operand());
+            assert!(std::ptr::eq(node.binary().unwrap().0, op), "{:?}", op.token);
+        }
+        // `!` is prefix only and stores its prefix precedence
+        assert_eq!(Token::Not(8).precedence(), Some(8));
+    }
+
+    #[test]
+    fn parsing_prefix_test() -> Result<()> {
+        let number = |value| -> Box<ASTNode> { NodeKind::Number(value).into() };
+        let neg = |node: Box<ASTNode>| -> Box<ASTNode> { NodeKind::Neg(node).into() };
+        let inputs = vec![
+            ("-1", *neg(number(1))),
+            ("--1", *neg(neg(number(1)))),
+            (
+                "1 - -1",
+                NodeKind::Subtract(number(1), neg(number(1))).into(),
+            ),
+            ("-2 ^ 2", *neg(NodeKind::Power(number(2), number(2)).into())),
+            ("2 ^ -2", NodeKind::Power(number(2), neg(number(2))).into()),
+            (
+                "-2 * 3",
+                NodeKind::Multiply(neg(number(2)), number(3)).into(),
+            ),
+            ("(-(1))", *neg(number(1))),
+            (
+                "!true && !!false",
+                NodeKind::And(
+                    NodeKind::Not(NodeKind::Bool(true).into()).into(),
+                    NodeKind::Not(NodeKind::Not(NodeKind::Bool(false).into()).into()).into(),
+                )
+                .into(),
+            ),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(parser.parse()?, exp_result);
+        }
+
+        // the prefix node spans operator and operand
+        let mut parser = ShuntiyardParser::new(Lexer::new("1 + -2"));
+        let NodeKind::Add(_, neg) = parser.parse()?.kind else {
+            panic!("Expected addition");
+        };
+        assert_eq!((neg.span.start, neg.span.end), (4, 6));
+        assert!(PREFIX_OPERATORS.iter().all(|op| op.precedence > 7));
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_conditional_test() -> Result<()> {
+        let number = |value| -> Box<ASTNode> { NodeKind::Number(value).into() };
+        let boolean = |value| -> Box<ASTNode> { NodeKind::Bool(value).into() };
+        let cond = |c, t, e| -> Box<ASTNode> { NodeKind::If(c, t, e).into() };
+        let inputs = vec![
+            (
+                "if true then 1 else 2",
+                cond(boolean(true), number(1), number(2)),
+            ),
+            ("true ? 1 : 2", cond(boolean(true), number(1), number(2))),
+            // the else branch reaches as far as possible, `?` binds loosest
+            (
+                "if true then 1 else 2 + 3",
+                cond(
+                    boolean(true),
+                    number(1),
+                    NodeKind::Add(number(2), number(3)).into(),
+                ),
+            ),
+            (
+                "1 < 2 || false ? 1 : 2",
+                cond(
+                    NodeKind::Or(NodeKind::Less(number(1), number(2)).into(), boolean(false))
+                        .into(),
+                    number(1),
+                    number(2),
+                ),
+            ),
+            (
+                "1 + if true then 2 else 3",
+                NodeKind::Add(number(1), cond(boolean(true), number(2), number(3))).into(),
+            ),
+            // nested conditionals group to the right
+            (
+                "true ? 1 : false ? 2 : 3",
+                cond(
+                    boolean(true),
+                    number(1),
+                    cond(boolean(false), number(2), number(3)),
+                ),
+            ),
+            (
+                "true ? false ? 1 : 2 : 3",
+                cond(
+                    boolean(true),
+                    cond(boolean(false), number(1), number(2)),
+                    number(3),
+                ),
+            ),
+            (
+                "if if true then false else true then 1 else (true ? 2 : 3)",
+                cond(
+                    cond(boolean(true), boolean(false), boolean(true)),
+                    number(1),
+                    cond(boolean(true), number(2), number(3)),
+                ),
+            ),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(parser.parse()?, *exp_result, "{}", input);
+        }
+
+        let errors = [
+            ("true ? 1", "expected ':' at 1:9"),
+            ("if true 1 else 2", "expected 'then' at 1:9"),
+            ("if true then 1 : 2", "expected 'else' at 1:16"),
+            ("(true ? 1) : 2", "expected ':' at 1:10"),
+            ("1 : 2", "unexpected ':' at 1:3"),
+            ("if true then else 2", "missing operand for 'then' at 1:14"),
+            ("? 1 : 2", "missing operand for '?' at 1:1"),
+        ];
+        for (input, exp_error) in errors {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(
+                parser.parse().unwrap_err().to_string(),
+                exp_error,
+                "{}",
+                input
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_let_test() -> Result<()> {
+        let number = |value| -> Box<ASTNode> { NodeKind::Number(value).into() };
+        let var = |name: &str| -> Box<ASTNode> { NodeKind::Var(name.into()).into() };
+        let bind = |name: &str, value, body| -> Box<ASTNode> {
+            NodeKind::Let(name.into(), value, body).into()
+        };
+        let inputs = vec![
+            ("x", var("x")),
+            (
+                "let x = 1 in x + 1",
+                bind("x", number(1), NodeKind::Add(var("x"), number(1)).into()),
+            ),
+            (
+                "let x = 1 in let y = x in y",
+                bind("x", number(1), bind("y", var("x"), var("y"))),
+            ),
+            (
+                "let x = let y = 1 in y in x * 2",
+                bind(
+                    "x",
+                    bind("y", number(1), var("y")),
+                    NodeKind::Multiply(var("x"), number(2)).into(),
+                ),
+            ),
+            (
+                "2 * (let x = 1 in x) + f(x)",
+                NodeKind::Add(
+                    NodeKind::Multiply(number(2), bind("x", number(1), var("x"))).into(),
+                    NodeKind::Call("f".into(), vec![*var("x")]).into(),
+                )
+                .into(),
+            ),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(parser.parse()?, *exp_result, "{}", input);
+        }
+
+        // the binding spans `let` and its body
+        let mut parser = ShuntiyardParser::new(Lexer::new("1 + let x = 2 in x"));
+        let NodeKind::Add(_, binding) = parser.parse()?.kind else {
+            panic!("Expected addition");
+        };
+        assert_eq!((binding.span.start, binding.span.end), (4, 18));
+
+        let errors = [
+            ("let 1 = 2 in 3", "expected a variable name at 1:5"),
+            ("let x 2 in 3", "expected '=' at 1:7"),
+            ("let x = 2 x", "expected 'in' at 1:11"),
+            ("let x = in 3", "missing operand for '=' at 1:9"),
+            ("let x = 2", "expected 'in' at 1:10"),
+            ("x = 2", "unexpected '=' at 1:3"),
+        ];
+        for (input, exp_error) in errors {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(
+                parser.parse().unwrap_err().to_string(),
+                exp_error,
+                "{}",
+                input
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_lambda_test() -> Result<()> {
+        let number = |value| -> Box<ASTNode> { NodeKind::Number(value).into() };
+        let var = |name: &str| -> Box<ASTNode> { NodeKind::Var(name.into()).into() };
+        let lambda = |parameters: &[&str], body| -> Box<ASTNode> {
+            let parameters = parameters.iter().map(|name| name.to_string()).collect();
+            NodeKind::Lambda(parameters, body).into()
+        };
+        let apply =
+            |function, arguments| -> Box<ASTNode> { NodeKind::Apply(function, arguments).into() };
+        let inputs = vec![
+            (
+                "\\x -> x + 1",
+                lambda(&["x"], NodeKind::Add(var("x"), number(1)).into()),
+            ),
+            (
+                "fn(x, y) => x * y",
+                lambda(&["x", "y"], NodeKind::Multiply(var("x"), var("y")).into()),
+            ),
+            ("fn() => 1", lambda(&[], number(1))),
+            ("λx → \\y -> x", lambda(&["x"], lambda(&["y"], var("x")))),
+            (
+                "(\\x -> x)(2)",
+                apply(lambda(&["x"], var("x")), vec![*number(2)]),
+            ),
+            (
+                "f(1)(2, 3)",
+                apply(
+                    NodeKind::Call("f".into(), vec![*number(1)]).into(),
+                    vec![*number(2), *number(3)],
+                ),
+            ),
+            // application binds tighter than all operators
+            (
+                "-(f)(1)! ^ 2",
+                NodeKind::Neg(
+                    NodeKind::Power(
+                        NodeKind::Factorial(apply(var("f"), vec![*number(1)])).into(),
+                        number(2),
+                    )
+                    .into(),
+                )
+                .into(),
+            ),
+            ("x()", NodeKind::Call("x".into(), vec![]).into()),
+            ("1 ()", apply(number(1), vec![])),
+            // the body extends as far as possible
+            (
+                "let f = \\x -> x in f(1)",
+                NodeKind::Let(
+                    "f".into(),
+                    lambda(&["x"], var("x")),
+                    NodeKind::Call("f".into(), vec![*number(1)]).into(),
+                )
+                .into(),
+            ),
+            (
+                "\\f -> true ? f : 1",
+                lambda(
+                    &["f"],
+                    NodeKind::If(NodeKind::Bool(true).into(), var("f"), number(1)).into(),
+                ),
+            ),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(parser.parse()?, *exp_result, "{}", input);
+        }
+
+        // the function spans `\` and its body, the application its function and `)`,
+        // parentheses around the function are not part of either span
+        let mut parser = ShuntiyardParser::new(Lexer::new("1 + (\\x -> x)(2)"));
+        let NodeKind::Add(_, application) = parser.parse()?.kind else {
+            panic!("Expected addition");
+        };
+        assert_eq!((application.span.start, application.span.end), (5, 16));
+        let NodeKind::Apply(function, _) = application.kind else {
+            panic!("Expected application");
+        };
+        assert_eq!((function.span.start, function.span.end), (5, 12));
+
+        let errors = [
+            ("\\1 -> 2", "expected a variable name at 1:2"),
+            ("\\x 1", "expected '->' at 1:4"),
+            ("\\x ->", "missing operand for '->' at 1:6"),
+            ("fn x => x", "expected '(' at 1:4"),
+            ("fn(x 1) => x", "expected ')' at 1:6"),
+            ("fn(x,) => x", "expected a variable name at 1:6"),
+            ("fn(x) -> x", "expected '=>' at 1:7"),
+            ("1 -> 2", "unexpected '->' at 1:3"),
+            ("(1, 2)(3)", "unexpected ',' at 1:3"),
+        ];
+        for (input, exp_error) in errors {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(
+                parser.parse().unwrap_err().to_string(),
+                exp_error,
+                "{}",
+                input
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_program_test() -> Result<()> {
+        let number = |value| -> ASTNode { NodeKind::Number(value).into() };
+        let var = |name: &str| -> Box<ASTNode> { NodeKind::Var(name.into()).into() };
+        let call =
+            |name: &str, arguments| -> ASTNode { NodeKind::Call(name.into(), arguments).into() };
+        let input = "def fact(n) = if n == 0 then 1 else n * fact(n - 1); fact(5)";
+        let exp_result = ASTNode::from(NodeKind::Program(
+            vec![Definition {
+                name: "fact".into(),
+                parameters: vec!["n".into()],
+                body: NodeKind::If(
+                    NodeKind::Equal(var("n"), number(0).into()).into(),
+                    number(1).into(),
+                    NodeKind::Multiply(
+                        var("n"),
+                        call(
+                            "fact",
+                            vec![NodeKind::Subtract(var("n"), number(1).into()).into()],
+                        )
+                        .into(),
+                    )
+                    .into(),
+                )
+                .into(),
+            }],
+            call("fact", vec![number(5)]).into(),
+        ));
+        let mut parser = ShuntiyardParser::new(Lexer::new(input));
+        assert_eq!(parser.parse()?, exp_result);
+
+        // the program spans the first `def` and the main expression
+        let input =
+            "  def even(n) = n == 0 || odd(n - 1); def odd(n) = n != 0 && even(n - 1); odd(7)";
+        let mut parser = ShuntiyardParser::new(Lexer::new(input));
+        let program = parser.parse()?;
+        let NodeKind::Program(definitions, main) = &program.kind else {
+            panic!("Expected program");
+        };
+        let names: Vec<&str> = definitions.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["even", "odd"]);
+        assert_eq!(**main, call("odd", vec![number(7)]));
+        assert_eq!((program.span.start, program.span.end), (2, input.len()));
+
+        let errors = [
+            ("def f() = 1", "expected ';' at 1:12"),
+            ("() def f() = 1; f()", "empty input at 1:1"),
+            ("def f() = 1;", "empty input at 1:13"),
+            ("def f() = ; 1", "missing operand for '=' at 1:11"),
+            ("def f() 1; 2", "expected '=' at 1:9"),
+            ("def f = 1; 2", "expected '(' at 1:7"),
+            ("def 1() = 1; 2", "expected a variable name at 1:5"),
+            (
+                "def f(x) = x 1; 2",
+                "missing operator before the operand at 1:14",
+            ),
+            ("def f(x) = (x; 1", "unbalanced parenthesis '(' at 1:12"),
+            ("def f(x) = if x; 1", "expected 'then' at 1:16"),
+            ("1; 2", "unexpected ';' at 1:2"),
+            ("1 + def f() = 1; 2", "unexpected 'def' at 1:5"),
+            ("def f() = 1; 2; 3", "unexpected ';' at 1:15"),
+        ];
+        for (input, exp_error) in errors {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(
+                parser.parse().unwrap_err().to_string(),
+                exp_error,
+                "{}",
+                input
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_call_test() -> Result<()> {
+        let number = |value| -> ASTNode { NodeKind::Number(value).into() };
+        let call =
+            |name: &str, arguments| -> ASTNode { NodeKind::Call(name.into(), arguments).into() };
+        let inputs = vec![
+            ("f()", call("f", vec![])),
+            (
+                "abs(-1)",
+                call("abs", vec![NodeKind::Neg(number(1).into()).into()]),
+            ),
+            (
+                "max(1, 2 * (3 + 4))",
+                call(
+                    "max",
+                    vec![
+                        number(1),
+                        NodeKind::Multiply(
+                            number(2).into(),
+                            NodeKind::Add(number(3).into(), number(4).into()).into(),
+                        )
+                        .into(),
+                    ],
+                ),
+            ),
+            (
+                "clamp(x(), min(1, 2), 3)! + 1",
+                NodeKind::Add(
+                    NodeKind::Factorial(
+                        call(
+                            "clamp",
+                            vec![
+                                call("x", vec![]),
+                                call("min", vec![number(1), number(2)]),
+                                number(3),
+                            ],
+                        )
+                        .into(),
+                    )
+                    .into(),
+                    number(1).into(),
+                )
+                .into(),
+            ),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(parser.parse()?, exp_result);
+        }
+
+        // the call spans name and parentheses
+        let mut parser = ShuntiyardParser::new(Lexer::new("1 + f(2, 3)"));
+        let NodeKind::Add(_, call) = parser.parse()?.kind else {
+            panic!("Expected addition");
+        };
+        assert_eq!((call.span.start, call.span.end), (4, 11));
+
+        let errors = [
+            ("f(1,)", "missing function argument at 1:5"),
+            ("f(, 1)", "missing function argument at 1:3"),
+            ("f(())", "missing function argument at 1:4"),
+            ("f 1", "missing operator before the operand at 1:3"),
+            ("(1, 2)", "unexpected ',' at 1:3"),
+            ("f(1 2)", "missing operator before the operand at 1:5"),
+        ];
+        for (input, exp_error) in errors {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            assert_eq!(parser.parse().unwrap_err().to_string(), exp_error);
+        }
         Ok(())
     }
 }
@@ -0,0 +1,991 @@
+use crate::{
+    lexer::lexer::{Lexer, Span, SpannedToken, Token},
+    parser::parser::{
+        binary_operator, chain, literal, postfix_operator, prefix_operator, ASTNode, Associativity,
+        Definition, NodeKind, ParseError, Parser, Statement,
+    },
+};
+
+// Pratt (precedence climbing) parser: builds the same trees as the ShuntiyardParser
+// from the same operator tables, but parses recursively instead of using an operator stack
+pub struct PrattParser<'a> {
+    lexer: Lexer<'a>,
+    // true while the body of a definition or a statement of a script is parsed,
+    // it ends with `;` instead of Eof
+    terminated: bool,
+    // recovering mode: errors are collected in `diagnostics` instead of stopping the parse
+    recovering: bool,
+    diagnostics: Vec<ParseError>,
+}
+
+impl<'a> PrattParser<'a> {
+    // initializing pratt parser with the provided lexer
+    pub fn new(lexer: Lexer<'a>) -> PrattParser<'a> {
+        return PrattParser {
+            lexer,
+            terminated: false,
+            recovering: false,
+            diagnostics: Vec::new(),
+        };
+    }
+
+    // recovering mode: parses the whole input even if it is malformed and returns a partial AST
+    // with Error placeholders for missing operands together with every error found
+    // stray tokens are skipped and unclosed parentheses are closed at the end of the input
+    pub fn parse_recovering(&mut self) -> (ASTNode, Vec<ParseError>) {
+        self.recovering = true;
+        let ast = match self.parse() {
+            Ok(ast) => ast,
+            Err(error) => {
+                let span = error.span();
+                self.diagnostics.push(error);
+                ASTNode::new(NodeKind::Error, span)
+            }
+        };
+        return (ast, std::mem::take(&mut self.diagnostics));
+    }
+
+    // parses a script: statements that each end with `;`, up to the end of the input
+    pub fn parse_script(&mut self) -> Result<Vec<Statement>, ParseError> {
+        let mut statements = Vec::new();
+        loop {
+            let next = self.peek()?;
+            if next.token == Token::Eof {
+                return Ok(statements);
+            }
+            statements.push(self.parse_statement(next)?);
+        }
+    }
+
+    // parses the statement starting with the token `start`: a definition, `print e;`,
+    // an assignment `x = e;` or an expression `e;`
+    fn parse_statement(&mut self, start: SpannedToken) -> Result<Statement, ParseError> {
+        if start.token == Token::Def {
+            self.next()?;
+            return Ok(Statement::Definition(self.parse_definition()?));
+        }
+        if start.token == Token::Print {
+            self.next()?;
+            let value = self.parse_expression(0, Some(&start))?;
+            return Ok(Statement::Print(self.terminate(value)?));
+        }
+        let assignment = matches!(self.lexer.peek_nth(1), Ok(next) if next.token == Token::Assign);
+        if let (Token::Ident(name), true) = (start.token, assignment) {
+            self.next()?;
+            let value = self.parse_part(Token::Assign)?;
+            return Ok(Statement::Assign(name, self.terminate(value)?));
+        }
+        let value = self.parse_expression(0, None)?;
+        return Ok(Statement::Expression(self.terminate(value)?));
+    }
+
+    // stops the parse with the error, in recovering mode it is collected and parsing goes on
+    fn report(&mut self, error: ParseError) -> Result<(), ParseError> {
+        if !self.recovering {
+            return Err(error);
+        }
+        self.diagnostics.push(error);
+        return Ok(());
+    }
+
+    // in recovering mode lexical errors are collected and the offending input is skipped
+    fn skip_lex_errors(&mut self) {
+        while self.recovering {
+            let Err(error) = self.lexer.peek() else {
+                break;
+            };
+            self.diagnostics.push(ParseError::Lex(error));
+            let _ = self.lexer.next_token();
+        }
+    }
+
+    fn next(&mut self) -> Result<SpannedToken, ParseError> {
+        self.skip_lex_errors();
+        return Ok(self.lexer.next_token()?);
+    }
+
+    fn peek(&mut self) -> Result<SpannedToken, ParseError> {
+        self.skip_lex_errors();
+        return Ok(self.lexer.peek()?.clone());
+    }
+
+    // parses an operand followed by all operators binding at least as tight as `min_precedence`
+    // `pending` is the operator or argument separator waiting for this operand, it is reported if the operand is missing
+    fn parse_expression(
+        &mut self,
+        min_precedence: u8,
+        pending: Option<&SpannedToken>,
+    ) -> Result<ASTNode, ParseError> {
+        let left = self.parse_prefix(pending)?;
+        return self.parse_operators(left, min_precedence);
+    }
+
+    // applies postfix and binary operators binding at least as tight as `min_precedence` to `left`
+    fn parse_operators(
+        &mut self,
+        mut left: ASTNode,
+        min_precedence: u8,
+    ) -> Result<ASTNode, ParseError> {
+        // precedence of the comparison that built `left`, the next comparison continues its chain
+        let mut chained = None;
+        // precedence of the last non-associative operator, it cannot be chained
+        let mut non_associative = None;
+        loop {
+            let next = self.peek()?;
+            // `?` binds loosest, the whole expression parsed so far is its condition
+            if next.token == Token::Question {
+                if min_precedence > 0 {
+                    break;
+                }
+                self.next()?;
+                let then = self.parse_expression(0, Some(&next))?;
+                let otherwise = self.parse_part(Token::Colon)?;
+                left = conditional(&next, left, then, otherwise);
+                chained = None;
+                non_associative = None;
+                continue;
+            }
+            // `(` after an operand applies its value to the arguments, application binds tighter
+            // than all operators: `-f(1)` is `-(f(1))` and `f(1)(2)` applies the result of `f(1)`
+            if next.token == Token::LPar {
+                self.next()?;
+                let (arguments, close) = self.parse_arguments(&next)?;
+                let span = left.span.to(close);
+                left = ASTNode::new(NodeKind::Apply(Box::new(left), arguments), span);
+                continue;
+            }
+            // postfix operators apply to everything parsed so far at this level
+            if let Some(op) = postfix_operator(&next.token) {
+                if op.precedence < min_precedence {
+                    break;
+                }
+                self.next()?;
+                let span = left.span.to(next.span);
+                left = ASTNode::new((op.build)(Box::new(left)), span);
+                continue;
+            }
+            let Some(op) = binary_operator(&next.token) else {
+                break;
+            };
+            if op.precedence < min_precedence {
+                break;
+            }
+            if non_associative == Some(op.precedence) {
+                self.report(ParseError::NonAssociative {
+                    operator: next.token.clone(),
+                    span: next.span,
+                })?;
+            }
+            self.next()?;
+            // left associative operators only take tighter operators into their right operand
+            let right_precedence = match op.associativity {
+                Associativity::Right => op.precedence,
+                Associativity::Left | Associativity::Non | Associativity::Chain => {
+                    op.precedence + 1
+                }
+            };
+            let right = self.parse_expression(right_precedence, Some(&next))?;
+            if chained == Some(op.precedence) {
+                left = chain(left, op.build, right);
+                continue;
+            }
+            let span = left.span.to(right.span);
+            left = ASTNode::new((op.build)(Box::new(left), Box::new(right)), span);
+            chained = (op.associativity == Associativity::Chain).then_some(op.precedence);
+            non_associative = (op.associativity == Associativity::Non).then_some(op.precedence);
+        }
+        return Ok(left);
+    }
+
+    // parses a literal, a prefix operator with its operand or a parenthesized expression
+    fn parse_prefix(&mut self, pending: Option<&SpannedToken>) -> Result<ASTNode, ParseError> {
+        let spanned = self.peek()?;
+        let span = spanned.span;
+        if let Some(kind) = literal(&spanned.token) {
+            self.next()?;
+            return Ok(ASTNode::new(kind, span));
+        }
+        if let Some(op) = prefix_operator(&spanned.token) {
+            self.next()?;
+            let operand = self.parse_expression(op.precedence, Some(&spanned))?;
+            let span = span.to(operand.span);
+            return Ok(ASTNode::new((op.build)(Box::new(operand)), span));
+        }
+        if spanned.token == Token::LPar {
+            self.next()?;
+            // an empty group is no operand, without an operator waiting for one the group is
+            // reported at its `(`
+            if pending.is_none() && self.peek()?.token == Token::RPar {
+                self.report(ParseError::EmptyInput { span })?;
+                self.next()?;
+                return Ok(placeholder(span));
+            }
+            let inner = self.parse_expression(0, pending)?;
+            return self.close(inner, Some(span));
+        }
+        if spanned.token == Token::If {
+            self.next()?;
+            let condition = self.parse_expression(0, Some(&spanned))?;
+            let then = self.parse_part(Token::Then)?;
+            let otherwise = self.parse_part(Token::Else)?;
+            return Ok(conditional(&spanned, condition, then, otherwise));
+        }
+        if spanned.token == Token::Let {
+            self.next()?;
+            return self.parse_let(&spanned);
+        }
+        if matches!(spanned.token, Token::Lambda | Token::Fn) {
+            self.next()?;
+            return self.parse_lambda(&spanned);
+        }
+        if let Token::Ident(name) = spanned.token {
+            self.next()?;
+            return self.parse_call(name, span);
+        }
+        // stray comma, definition or `print` inside an expression: skipped in recovering mode
+        if (pending.is_none() && spanned.token == Token::Comma)
+            || matches!(spanned.token, Token::Def | Token::Print)
+        {
+            self.report(ParseError::UnexpectedToken {
+                token: spanned.token,
+                span,
+            })?;
+            self.next()?;
+            return self.parse_prefix(pending);
+        }
+        // no operand: reports the operator waiting for it,
+        // the placeholder takes its place in front of the offending token
+        self.report(match (pending, spanned.token) {
+            (Some(operator), _) if matches!(operator.token, Token::Comma | Token::LPar) => {
+                ParseError::MissingArgument { span }
+            }
+            (Some(operator), _) => ParseError::MissingOperand {
+                operator: operator.token.clone(),
+                span,
+            },
+            (None, Token::Eof) => ParseError::EmptyInput { span },
+            (None, Token::RPar) => ParseError::UnbalancedParenthesis {
+                paren: Token::RPar,
+                span,
+            },
+            (None, token) => ParseError::MissingOperand {
+                operator: token,
+                span,
+            },
+        })?;
+        return Ok(placeholder(span));
+    }
+
+    // consumes `separator` (`then`, `else`, `:`, `=`, `in`, `->` or `=>`) and parses the part of the
+    // conditional or binding after it
+    // a missing separator is reported, in recovering mode the parse goes on as if it was there
+    fn parse_part(&mut self, separator: Token) -> Result<ASTNode, ParseError> {
+        let next = self.peek()?;
+        if next.token == separator {
+            self.next()?;
+            return self.parse_expression(0, Some(&next));
+        }
+        self.report(ParseError::Expected {
+            expected: separator,
+            span: next.span,
+        })?;
+        if ends_part(&next.token) {
+            return Ok(placeholder(next.span));
+        }
+        return self.parse_expression(0, None);
+    }
+
+    // parses `name = value in body` after `let`
+    // in recovering mode a missing name is replaced by an empty one and a missing `=` is inserted
+    fn parse_let(&mut self, start: &SpannedToken) -> Result<ASTNode, ParseError> {
+        let name = self.parse_name()?;
+        let value = self.parse_part(Token::Assign)?;
+        let body = self.parse_part(Token::In)?;
+        let span = start.span.to(body.span);
+        return Ok(ASTNode::new(
+            NodeKind::Let(name, Box::new(value), Box::new(body)),
+            span,
+        ));
+    }
+
+    // parses `name(x, y) = body;` after `def`
+    // in recovering mode a missing `;` is inserted
+    fn parse_definition(&mut self) -> Result<Definition, ParseError> {
+        let name = self.parse_name()?;
+        let parameters = self.parse_parameters()?;
+        let body = self.parse_part(Token::Assign)?;
+        return Ok(Definition {
+            name,
+            parameters,
+            body: self.terminate(body)?,
+        });
+    }
+
+    // ends the body of a definition or a statement with `;`
+    // in recovering mode a missing `;` is inserted
+    fn terminate(&mut self, ast: ASTNode) -> Result<ASTNode, ParseError> {
+        self.terminated = true;
+        let ast = self.close(ast, None);
+        self.terminated = false;
+        if self.peek()?.token == Token::Semicolon {
+            self.next()?;
+        }
+        return ast;
+    }
+
+    // parses `x -> body` after `\` or `(x, y) => body` after `fn`
+    fn parse_lambda(&mut self, start: &SpannedToken) -> Result<ASTNode, ParseError> {
+        let (parameters, arrow) = if start.token == Token::Lambda {
+            (vec![self.parse_name()?], Token::Arrow)
+        } else {
+            (self.parse_parameters()?, Token::FatArrow)
+        };
+        let body = self.parse_part(arrow)?;
+        let span = start.span.to(body.span);
+        return Ok(ASTNode::new(
+            NodeKind::Lambda(parameters, Box::new(body)),
+            span,
+        ));
+    }
+
+    // parses the parameter list `(x, y)` after `fn`
+    // in recovering mode the list ends at the first token that does not fit
+    fn parse_parameters(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut parameters = Vec::new();
+        let open = self.peek()?;
+        if open.token != Token::LPar {
+            self.report(ParseError::Expected {
+                expected: Token::LPar,
+                span: open.span,
+            })?;
+            return Ok(parameters);
+        }
+        self.next()?;
+        if self.peek()?.token == Token::RPar {
+            self.next()?;
+            return Ok(parameters);
+        }
+        loop {
+            parameters.push(self.parse_name()?);
+            let next = self.peek()?;
+            match next.token {
+                Token::Comma => {
+                    self.next()?;
+                }
+                Token::RPar => {
+                    self.next()?;
+                    return Ok(parameters);
+                }
+                _ => {
+                    self.report(ParseError::Expected {
+                        expected: Token::RPar,
+                        span: next.span,
+                    })?;
+                    return Ok(parameters);
+                }
+            }
+        }
+    }
+
+    // parses the name of a variable or parameter, in recovering mode a missing name is
+    // replaced by an empty one
+    fn parse_name(&mut self) -> Result<String, ParseError> {
+        let next = self.peek()?;
+        let Token::Ident(name) = next.token else {
+            self.report(ParseError::ExpectedName { span: next.span })?;
+            return Ok(String::new());
+        };
+        self.next()?;
+        return Ok(name);
+    }
+
+    // parses the arguments of a call to `name`, without arguments `name` is a variable
+    fn parse_call(&mut self, name: String, span: Span) -> Result<ASTNode, ParseError> {
+        let open = self.peek()?;
+        if open.token != Token::LPar {
+            return Ok(ASTNode::new(NodeKind::Var(name), span));
+        }
+        self.next()?;
+        let (arguments, close) = self.parse_arguments(&open)?;
+        return Ok(ASTNode::new(
+            NodeKind::Call(name, arguments),
+            span.to(close),
+        ));
+    }
+
+    // parses the comma separated arguments after the `(` at `open` up to the `)`,
+    // returns them with the span of the `)`
+    fn parse_arguments(&mut self, open: &SpannedToken) -> Result<(Vec<ASTNode>, Span), ParseError> {
+        let mut arguments = Vec::new();
+        if self.peek()?.token == Token::RPar {
+            let close = self.next()?;
+            return Ok((arguments, close.span));
+        }
+        // `(` or the comma in front of the next argument
+        let mut separator = open.clone();
+        loop {
+            let argument = self.parse_expression(0, Some(&separator))?;
+            arguments.push(argument);
+            // a comma continues the arguments, `)` ends them
+            let close = loop {
+                let next = self.peek()?;
+                match next.token {
+                    Token::Comma => {
+                        separator = self.next()?;
+                        break None;
+                    }
+                    Token::RPar => {
+                        self.next()?;
+                        break Some(next.span);
+                    }
+                    Token::Eof | Token::Semicolon => {
+                        self.report(ParseError::UnbalancedParenthesis {
+                            paren: Token::LPar,
+                            span: open.span,
+                        })?;
+                        break Some(next.span);
+                    }
+                    Token::Then
+                    | Token::Else
+                    | Token::Colon
+                    | Token::In
+                    | Token::Assign
+                    | Token::Arrow
+                    | Token::FatArrow => {
+                        self.report(ParseError::UnexpectedToken {
+                            token: next.token,
+                            span: next.span,
+                        })?;
+                        self.next()?;
+                    }
+                    // another operand: it is parsed, but cannot be part of the tree
+                    _ => {
+                        self.report(ParseError::AdjacentOperands { span: next.span })?;
+                        self.parse_expression(0, None)?;
+                    }
+                }
+            };
+            if let Some(close) = close {
+                return Ok((arguments, close));
+            }
+        }
+    }
+
+    // ends the group opened at `open` with `)`, or the whole input with Eof if `open` is None
+    // the body of a definition or a statement ends with `;` instead, which is left for the caller
+    // in recovering mode stray tokens are skipped and a missing `)` is inserted at the end
+    fn close(&mut self, mut ast: ASTNode, open: Option<Span>) -> Result<ASTNode, ParseError> {
+        loop {
+            let next = self.peek()?;
+            match (next.token, open) {
+                (Token::RPar, Some(_)) => {
+                    self.next()?;
+                    return Ok(ast);
+                }
+                (Token::Semicolon, None) if self.terminated => return Ok(ast),
+                (Token::Eof, None) if self.terminated => {
+                    self.report(ParseError::Expected {
+                        expected: Token::Semicolon,
+                        span: next.span,
+                    })?;
+                    return Ok(ast);
+                }
+                (Token::Eof, None) => return Ok(ast),
+                (Token::Eof | Token::Semicolon, Some(span)) => {
+                    self.report(ParseError::UnbalancedParenthesis {
+                        paren: Token::LPar,
+                        span,
+                    })?;
+                    return Ok(ast);
+                }
+                (Token::RPar, None) => {
+                    self.report(ParseError::UnbalancedParenthesis {
+                        paren: Token::RPar,
+                        span: next.span,
+                    })?;
+                    self.next()?;
+                }
+                // comma outside of an argument list, `then`, `else`, `:`, `=`, `in`, `->` or `=>`
+                // outside of a conditional, binding or function, `;` outside of a definition
+                (
+                    token @ (Token::Comma
+                    | Token::Semicolon
+                    | Token::Then
+                    | Token::Else
+                    | Token::Colon
+                    | Token::In
+                    | Token::Assign
+                    | Token::Arrow
+                    | Token::FatArrow),
+                    _,
+                ) => {
+                    self.report(ParseError::UnexpectedToken {
+                        token,
+                        span: next.span,
+                    })?;
+                    self.next()?;
+                }
+                // another operand: it is parsed, but cannot be part of the tree
+                _ => {
+                    self.report(ParseError::AdjacentOperands { span: next.span })?;
+                    self.parse_expression(0, None)?;
+                }
+            }
+            ast = self.parse_operators(ast, 0)?;
+        }
+    }
+}
+
+// conditional node spanning its first token (`if` or the condition before `?`) and all parts
+fn conditional(
+    start: &SpannedToken,
+    condition: ASTNode,
+    then: ASTNode,
+    otherwise: ASTNode,
+) -> ASTNode {
+    let span = start.span.to(condition.span).to(otherwise.span);
+    let kind = NodeKind::If(Box::new(condition), Box::new(then), Box::new(otherwise));
+    return ASTNode::new(kind, span);
+}
+
+// tokens that end a part of an expression, no operand can start there
+fn ends_part(token: &Token) -> bool {
+    return matches!(
+        token,
+        Token::Eof
+            | Token::RPar
+            | Token::Comma
+            | Token::Then
+            | Token::Else
+            | Token::Colon
+            | Token::In
+            | Token::Assign
+            | Token::Arrow
+            | Token::FatArrow
+            | Token::Semicolon
+    );
+}
+
+// placeholder for a missing operand in front of the token at `span`
+fn placeholder(span: Span) -> ASTNode {
+    let span = Span {
+        end: span.start,
+        ..span
+    };
+    return ASTNode::new(NodeKind::Error, span);
+}
+
+impl<'a> Parser for PrattParser<'a> {
+    // parses the definitions of a program and one expression, the rest of the input has to be empty
+    fn parse(&mut self) -> Result<ASTNode, ParseError> {
+        let mut definitions = Vec::new();
+        let mut start = None;
+        loop {
+            if self.peek()?.token != Token::Def {
+                break;
+            }
+            start.get_or_insert(self.next()?.span);
+            definitions.push(self.parse_definition()?);
+        }
+        let ast = self.parse_expression(0, None)?;
+        let main = self.close(ast, None)?;
+        let Some(start) = start else {
+            return Ok(main);
+        };
+        let span = start.to(main.span);
+        return Ok(ASTNode::new(
+            NodeKind::Program(definitions, Box::new(main)),
+            span,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{
+        lexer::lexer::{Lexer, Token},
+        parser::parser::{
+            ASTNode, Definition, NodeKind, ParseError, Parser, ShuntiyardParser, Statement,
+        },
+    };
+
+    use super::PrattParser;
+
+    // parses the input with both front ends
+    fn parse_both(input: &str) -> (Result<ASTNode, ParseError>, Result<ASTNode, ParseError>) {
+        let shunting_yard = ShuntiyardParser::new(Lexer::new(input)).parse();
+        let pratt = PrattParser::new(Lexer::new(input)).parse();
+        return (shunting_yard, pratt);
+    }
+
+    #[test]
+    fn pratt_postfix_test() -> Result<()> {
+        let number = |value| -> Box<ASTNode> { NodeKind::Number(value).into() };
+        let inputs = vec![
+            ("3!", NodeKind::Factorial(number(3))),
+            ("-3!", NodeKind::Neg(NodeKind::Factorial(number(3)).into())),
+            (
+                "2 ^ 3!!",
+                NodeKind::Power(
+                    number(2),
+                    NodeKind::Factorial(NodeKind::Factorial(number(3)).into()).into(),
+                ),
+            ),
+            (
+                "(1 + 2)! != 6",
+                NodeKind::NotEqual(
+                    NodeKind::Factorial(NodeKind::Add(number(1), number(2)).into()).into(),
+                    number(6),
+                ),
+            ),
+        ];
+        for (input, exp_result) in inputs {
+            let mut parser = PrattParser::new(Lexer::new(input));
+            assert_eq!(parser.parse()?, ASTNode::from(exp_result));
+        }
+        Ok(())
+    }
+
+    // both parsers build identical trees and agree on the spans and errors of handwritten inputs
+    #[test]
+    fn differential_test() {
+        let inputs = vec![
+            "1 + 1 * 0",
+            "1 + 1 * 0 || true",
+            "((((1))))",
+            "!(8 - 2 - 1 < 10 / 2 % 3) && 2 ^ 3 << 1 != 1 | 6 & 3",
+            "2 ^ 3 ^ 2",
+            "0 < x <= 10",
+            "1 == 2 != 3 < 4 <= 5 > 6 >= 7",
+            "a < b + 1 >= c || d < e == f && g",
+            "(1 < 2) < 3 < (4 < 5)",
+            "-1 < f(x) < 3! ? 0 < 1 < 2 : 2 > 1",
+            "0 < x < ",
+            "0 < < 1",
+            "-2 ^ -2 * 3! - --1",
+            "1 < 2 + 3 < 4",
+            "(1 < 2) == (3 > 4)",
+            "0.5r * 1e3 >= 7 % 3",
+            "1 +",
+            "* 1",
+            "(1 + ) * 2",
+            ")",
+            "2 * (1",
+            "1 1",
+            "(1) (1)",
+            "(1) 1",
+            "true !false",
+            "",
+            "  ()",
+            "() + 1",
+            "(1 + (()))",
+            "1 $ 2",
+            "max(1, 2) + min(3, -4) * abs(-(5))!",
+            "f()",
+            "f(())",
+            "f(g(1), (2, 3))",
+            "f(1,)",
+            "f(,1)",
+            "f(1 2)",
+            "f(1",
+            "f 1",
+            "1, 2",
+            "(1, 2)",
+            "if 1 < 2 then 3 else 4 + 5",
+            "1 + (true ? 2 : false ? 3 : 4)! * 5",
+            "true ? false ? 1 : 2 : if true then 3 else 4 ? 5 : 6",
+            "f(true ? 1 : 2, if false then 3 else 4)",
+            "true ? 1",
+            "(true ? 1)",
+            "f(true ? 1, 2)",
+            "if true 1 else 2",
+            "if true else 2",
+            "if true then 1 : 2",
+            "true ? 1 then 2",
+            "1 : 2",
+            "(1 then 2)",
+            "f(1 else 2)",
+            "if true then else 2",
+            "? 1 : 2",
+            "1 if true then 2 else 3",
+            "let x = 1 in let y = x + 1 in x * y",
+            "f(let x = 1 in x, x) ? x : let y = 2 in y!",
+            "let 1 = 2 in 3",
+            "let x 2 in 3",
+            "let x = 2 x",
+            "let x = in 3",
+            "let x = 2",
+            "x = 2",
+            "= 2",
+            "(x in 2)",
+            "f(x = 1)",
+            "\\x -> fn(y, z) => x + y * z",
+            "(\\x -> x)(1) + f(1)(2, 3)! ^ (g)()",
+            "-(f)(1) < 2 ? \\x -> x : fn() => 1",
+            "let f = λx → x in f(f)(1)",
+            "1 (2)(3)",
+            "\\1 -> 2",
+            "\\x 1",
+            "\\x ->",
+            "fn x => x",
+            "fn(x 1) => x",
+            "fn(x,) => x",
+            "fn(x) -> x",
+            "1 -> 2",
+            "-> 1",
+            "f(1 => 2)",
+            "(\\x -> x",
+            "1 fn() => 2",
+            "def fact(n) = if n == 0 then 1 else n * fact(n - 1); fact(5)",
+            "def f(x, y) = x; def g() = \\x -> f(x, 1); g()(2)",
+            "() def f() = 1; f()",
+            "def f() = 1",
+            "def f() = 1;",
+            "def f() = ; 1",
+            "def f() 1; 2",
+            "def f = 1; 2",
+            "def 1() = 1; 2",
+            "def f(x) = x 1; 2",
+            "def f(x) = (x; 1",
+            "def f(x) = g(x; 1",
+            "def f(x) = if x; 1",
+            "def f(x) = x then 1; 2",
+            "def f(x) = x); 2",
+            "def f(x) = x, 1; 2",
+            "1; 2",
+            "; 1",
+            "(;",
+            "1 + def f() = 1; 2",
+            "(def f() = 1; 2)",
+            "print 1",
+            "1 print",
+            "1 + print(1)",
+            "def f() = def g() = 1; 2",
+            "def f() = 1; 2; 3",
+        ];
+        for input in inputs {
+            let (shunting_yard, pratt) = parse_both(input);
+            println!("Expression {:?} Result {:?}", input, pratt);
+            assert_eq!(shunting_yard, pratt);
+            if let (Ok(left), Ok(right)) = (shunting_yard, pratt) {
+                assert_eq!(left.span, right.span);
+            }
+        }
+    }
+
+    // both parsers accept the same token sequences and build identical trees for them
+    #[test]
+    fn differential_generated_test() {
+        let pieces = ["1", "2", "+", "-", "*", "^", "!", "<", "(", ")"];
+        let mut inputs = vec![String::new()];
+        for _ in 0..5 {
+            let mut longer = Vec::new();
+            for input in &inputs {
+                for piece in pieces {
+                    let next = format!("{} {}", input, piece);
+                    let (shunting_yard, pratt) = parse_both(&next);
+                    assert_eq!(
+                        shunting_yard.as_ref().ok(),
+                        pratt.as_ref().ok(),
+                        "Expression {:?}",
+                        next
+                    );
+                    longer.push(next);
+                }
+            }
+            inputs = longer;
+        }
+    }
+
+    #[test]
+    fn recovering_test() {
+        let number = |value| -> Box<ASTNode> { NodeKind::Number(value).into() };
+        let error = || -> Box<ASTNode> { NodeKind::Error.into() };
+        let inputs = vec![
+            (
+                "(1 + * true",
+                NodeKind::Add(
+                    number(1),
+                    NodeKind::Multiply(error(), NodeKind::Bool(true).into()).into(),
+                ),
+                vec![
+                    "missing operand for '+' at 1:6",
+                    "unbalanced parenthesis '(' at 1:1",
+                ],
+            ),
+            (
+                "1 + ) * 2",
+                NodeKind::Multiply(NodeKind::Add(number(1), error()).into(), number(2)),
+                vec![
+                    "missing operand for '+' at 1:5",
+                    "unbalanced parenthesis ')' at 1:5",
+                ],
+            ),
+            (
+                "1 $ 2 < 3 < 4",
+                NodeKind::Number(1),
+                vec![
+                    "unexpected character '$' at 1:3",
+                    "missing operator before the operand at 1:5",
+                ],
+            ),
+            ("", NodeKind::Error, vec!["empty input at 1:1"]),
+            (
+                "let = 1 x + 2",
+                NodeKind::Let(
+                    String::new(),
+                    number(1),
+                    NodeKind::Add(NodeKind::Var("x".into()).into(), number(2)).into(),
+                ),
+                vec!["expected a variable name at 1:5", "expected 'in' at 1:9"],
+            ),
+            (
+                "if true 1 else 2 ? 3",
+                NodeKind::If(
+                    NodeKind::Bool(true).into(),
+                    number(1),
+                    NodeKind::If(number(2), number(3), error()).into(),
+                ),
+                vec!["expected 'then' at 1:9", "expected ':' at 1:21"],
+            ),
+            (
+                "max(1, , 2 3",
+                NodeKind::Call("max".into(), vec![*number(1), *error(), *number(2)]),
+                vec![
+                    "missing function argument at 1:8",
+                    "missing operator before the operand at 1:12",
+                    "unbalanced parenthesis '(' at 1:4",
+                ],
+            ),
+            (
+                "\\ -> x(1",
+                NodeKind::Lambda(
+                    vec![String::new()],
+                    NodeKind::Call("x".into(), vec![*number(1)]).into(),
+                ),
+                vec![
+                    "expected a variable name at 1:3",
+                    "unbalanced parenthesis '(' at 1:7",
+                ],
+            ),
+            (
+                "-(2 ^ 3)!",
+                NodeKind::Neg(
+                    NodeKind::Factorial(NodeKind::Power(number(2), number(3)).into()).into(),
+                ),
+                vec![],
+            ),
+        ];
+        for (input, exp_result, exp_diagnostics) in inputs {
+            let (ast, diagnostics) = PrattParser::new(Lexer::new(input)).parse_recovering();
+            let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+            println!(
+                "Expression {:?} AST {:?} Diagnostics {:?}",
+                input, ast, messages
+            );
+            assert_eq!(ast, ASTNode::from(exp_result));
+            assert_eq!(messages, exp_diagnostics);
+        }
+
+        // the placeholder sits in front of the offending token
+        let (ast, diagnostics) = PrattParser::new(Lexer::new("2 *")).parse_recovering();
+        let NodeKind::Multiply(_, placeholder) = ast.kind else {
+            panic!("Expected multiplication");
+        };
+        assert_eq!((placeholder.span.start, placeholder.span.end), (3, 3));
+        assert!(matches!(
+            diagnostics[..],
+            [ParseError::MissingOperand {
+                operator: Token::Mult(_),
+                ..
+            }]
+        ));
+        assert!(matches!(
+            PrattParser::new(Lexer::new("1 +")).parse(),
+            Err(ParseError::MissingOperand { .. })
+        ));
+    }
+
+    #[test]
+    fn parsing_script_test() -> Result<()> {
+        let number = |value| -> ASTNode { NodeKind::Number(value).into() };
+        let var = |name: &str| -> Box<ASTNode> { NodeKind::Var(name.into()).into() };
+        let input = "x = 1 + 1; y = x * x;\nprint(y); def f(n) = n; f(x) == y;";
+        let exp_result = vec![
+            Statement::Assign(
+                "x".into(),
+                NodeKind::Add(number(1).into(), number(1).into()).into(),
+            ),
+            Statement::Assign("y".into(), NodeKind::Multiply(var("x"), var("x")).into()),
+            Statement::Print(NodeKind::Var("y".into()).into()),
+            Statement::Definition(Definition {
+                name: "f".into(),
+                parameters: vec!["n".into()],
+                body: NodeKind::Var("n".into()).into(),
+            }),
+            Statement::Expression(
+                NodeKind::Equal(NodeKind::Call("f".into(), vec![*var("x")]).into(), var("y"))
+                    .into(),
+            ),
+        ];
+        assert_eq!(
+            PrattParser::new(Lexer::new(input)).parse_script()?,
+            exp_result
+        );
+        assert_eq!(
+            PrattParser::new(Lexer::new("# empty\n")).parse_script()?,
+            vec![]
+        );
+
+        let errors = [
+            ("x = 1", "expected ';' at 1:6"),
+            ("x = ;", "missing operand for '=' at 1:5"),
+            ("print;", "missing operand for 'print' at 1:6"),
+            (
+                "print 1 print 2;",
+                "missing operator before the operand at 1:9",
+            ),
+            ("1 = 2;", "unexpected '=' at 1:3"),
+            ("x = (1; 2;", "unbalanced parenthesis '(' at 1:5"),
+            ("x = 1 + print 2;", "unexpected 'print' at 1:9"),
+            (";", "missing operand for ';' at 1:1"),
+        ];
+        for (input, exp_error) in errors {
+            let error = PrattParser::new(Lexer::new(input))
+                .parse_script()
+                .unwrap_err();
+            assert_eq!(error.to_string(), exp_error, "{}", input);
+        }
+        Ok(())
+    }
+
+    // recovering parses always return a tree, without diagnostics it is the tree of a normal parse
+    #[test]
+    fn recovering_generated_test() {
+        let pieces = ["1", "+", "-", "!", "<", "(", ")", "$"];
+        let mut inputs = vec![String::new()];
+        for _ in 0..5 {
+            let mut longer = Vec::new();
+            for input in &inputs {
+                for piece in pieces {
+                    let next = format!("{} {}", input, piece);
+                    let (ast, diagnostics) = PrattParser::new(Lexer::new(&next)).parse_recovering();
+                    let result = PrattParser::new(Lexer::new(&next)).parse();
+                    assert_eq!(
+                        diagnostics.is_empty(),
+                        result.is_ok(),
+                        "Expression {:?}",
+                        next
+                    );
+                    if let Ok(exp_ast) = result {
+                        assert_eq!(ast, exp_ast);
+                    }
+                    longer.push(next);
+                }
+            }
+            inputs = longer;
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// documents of Wadler's "prettier printer": text with optional line breaks, a group is
+// written on one line if it fits into the width, otherwise its line breaks are taken
+#[derive(Debug, PartialEq, Clone)]
+pub enum Doc {
+    Text(String),
+    // a space, or a line break if the enclosing group is broken
+    Line,
+    // nothing, or a line break if the enclosing group is broken
+    SoftLine,
+    // always a line break, the enclosing groups are broken, e.g. after a line comment
+    HardLine,
+    Concat(Vec<Doc>),
+    // line breaks inside are indented by further columns
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+}
+
+pub fn text(text: impl Into<String>) -> Doc {
+    return Doc::Text(text.into());
+}
+
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    return Doc::Concat(docs);
+}
+
+pub fn nest(indent: usize, doc: Doc) -> Doc {
+    return Doc::Nest(indent, Box::new(doc));
+}
+
+pub fn group(doc: Doc) -> Doc {
+    return Doc::Group(Box::new(doc));
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+impl Doc {
+    // the document on as few lines as possible: only hard line breaks are taken
+    pub fn flat(&self) -> String {
+        let mut output = String::new();
+        self.write_flat(&mut output);
+        return output;
+    }
+
+    fn write_flat(&self, output: &mut String) {
+        match self {
+            Doc::Text(text) => output.push_str(text),
+            Doc::Line => output.push(' '),
+            Doc::SoftLine => {}
+            Doc::HardLine => output.push('\n'),
+            Doc::Concat(docs) => docs.iter().for_each(|doc| doc.write_flat(output)),
+            Doc::Nest(_, doc) | Doc::Group(doc) => doc.write_flat(output),
+        }
+    }
+
+    // lays the document out in lines of at most `width` characters where possible: every
+    // group is written flat if the rest of its line fits, outer groups break first
+    // line breaks outside of groups are always taken
+    pub fn pretty(&self, width: usize) -> String {
+        let mut output = String::new();
+        let mut column = 0;
+        // the documents still to write, the next one on top
+        let mut stack: Vec<(usize, Mode, &Doc)> = vec![(0, Mode::Break, self)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(text) => {
+                    output.push_str(text);
+                    column += text.chars().count();
+                }
+                Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                    if *doc == Doc::Line {
+                        output.push(' ');
+                        column += 1;
+                    }
+                }
+                Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                    // no trailing spaces in front of the line break, and no empty line after
+                    // a hard line break: the new line is only indented again
+                    output.truncate(output.trim_end_matches(' ').len());
+                    if !output.ends_with('\n') {
+                        output.push('\n');
+                    }
+                    output.push_str(&" ".repeat(indent));
+                    column = indent;
+                }
+                Doc::Concat(docs) => {
+                    stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                }
+                Doc::Nest(more, doc) => stack.push((indent + more, mode, doc)),
+                Doc::Group(doc) => {
+                    let flat = (indent, Mode::Flat, doc.as_ref());
+                    let fits =
+                        mode == Mode::Flat || fits(width as isize - column as isize, flat, &stack);
+                    stack.push((indent, if fits { Mode::Flat } else { Mode::Break }, doc));
+                }
+            }
+        }
+        return output;
+    }
+}
+
+// whether `next` followed by the `rest` of its line fits into `remaining` columns
+fn fits(mut remaining: isize, next: (usize, Mode, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut stack = vec![next];
+    let mut rest = rest.iter().rev();
+    while remaining >= 0 {
+        let Some((indent, mode, doc)) = stack.pop().or_else(|| rest.next().copied()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            // the line ends before the width is exceeded
+            Doc::Line | Doc::SoftLine => return true,
+            // a group with a hard line break cannot be flat
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+            Doc::Nest(more, doc) => stack.push((indent + more, mode, doc)),
+            Doc::Group(doc) => stack.push((indent, mode, doc)),
+        }
+    }
+    return false;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::pretty::{concat, group, nest, text, Doc};
+
+    // `f(a, b, c)` with the arguments on their own lines if they do not fit
+    fn call(arguments: &[&str]) -> Doc {
+        let mut docs = vec![Doc::SoftLine];
+        for (i, argument) in arguments.iter().enumerate() {
+            if i > 0 {
+                docs.extend([text(","), Doc::Line]);
+            }
+            docs.push(text(*argument));
+        }
+        return group(concat(vec![
+            text("f("),
+            nest(2, concat(docs)),
+            Doc::SoftLine,
+            text(")"),
+        ]));
+    }
+
+    #[test]
+    fn pretty_doc_test() {
+        let doc = call(&["aaa", "bbb", "ccc"]);
+        assert_eq!(doc.flat(), "f(aaa, bbb, ccc)");
+        assert_eq!(doc.pretty(16), "f(aaa, bbb, ccc)");
+        assert_eq!(doc.pretty(15), "f(\n  aaa,\n  bbb,\n  ccc\n)");
+
+        // outer groups break first, inner groups stay flat if they fit
+        let nested = group(concat(vec![
+            text("f("),
+            nest(
+                2,
+                concat(vec![
+                    Doc::SoftLine,
+                    text("aaa,"),
+                    Doc::Line,
+                    call(&["bbb", "ccc"]),
+                ]),
+            ),
+            Doc::SoftLine,
+            text(")"),
+        ]));
+        assert_eq!(nested.pretty(19), "f(aaa, f(bbb, ccc))");
+        assert_eq!(nested.pretty(13), "f(\n  aaa,\n  f(bbb, ccc)\n)");
+        assert_eq!(
+            nested.pretty(12),
+            "f(\n  aaa,\n  f(\n    bbb,\n    ccc\n  )\n)"
+        );
+
+        // text after a group counts up to the next line break
+        let doc = concat(vec![
+            group(concat(vec![text("a"), Doc::Line, text("b")])),
+            text("cc"),
+        ]);
+        assert_eq!(doc.pretty(5), "a bcc");
+        assert_eq!(doc.pretty(4), "a\nbcc");
+
+        // hard line breaks break the enclosing groups
+        let doc = group(concat(vec![
+            text("# a"),
+            Doc::HardLine,
+            text("b"),
+            Doc::Line,
+            text("c"),
+        ]));
+        assert_eq!(doc.pretty(80), "# a\nb\nc");
+        assert_eq!(doc.flat(), "# a\nb c");
+
+        // a line break right after a hard one only indents the line
+        let doc = group(concat(vec![
+            text("a # b"),
+            nest(2, Doc::HardLine),
+            Doc::Line,
+            text("c"),
+        ]));
+        assert_eq!(doc.pretty(80), "a # b\nc");
+    }
+}
@@ -0,0 +1,612 @@
+use std::fmt;
+
+use crate::{
+    lexer::lexer::{Lexer, Span, Token, Trivia},
+    parser::{
+        parser::{
+            binary_operator, postfix_operator, prefix_operator, ASTNode, Associativity, NodeKind,
+            ParseError, Parser, ResultEval, ShuntiyardParser,
+        },
+        pretty::{concat, group, nest, text, Doc},
+    },
+};
+
+// how tightly a printed node binds, an operand binding less tightly than its position
+// requires is put in parentheses: binary operators bind with their precedence + 1
+// conditionals, bindings and functions reach as far as possible to the right
+const OPEN: u8 = 0;
+// atoms: literals, variables, calls, applications and parenthesized expressions
+const ATOM: u8 = u8::MAX;
+// columns by which continued lines and the insides of parentheses are indented
+const INDENT: usize = 2;
+
+fn prefix() -> u8 {
+    return prefix_operator(&Token::Sub(6)).unwrap().precedence + 1;
+}
+
+fn postfix() -> u8 {
+    return postfix_operator(&Token::Not(8)).unwrap().precedence + 1;
+}
+
+fn strength(node: &ASTNode) -> u8 {
+    return match &node.kind {
+        NodeKind::If(..) | NodeKind::Let(..) | NodeKind::Lambda(..) | NodeKind::Program(..) => OPEN,
+        NodeKind::Chain(_) => binary_operator(&Token::Eq(2)).unwrap().precedence + 1,
+        NodeKind::Neg(_) | NodeKind::Not(_) => prefix(),
+        NodeKind::Factorial(_) => postfix(),
+        kind => match (kind.binary(), literal_text(kind)) {
+            (Some((op, _, _)), _) => op.precedence + 1,
+            // negative literals are written like a negation, rationals without decimal
+            // form like a division
+            (None, Some(text)) if text.starts_with('-') => prefix(),
+            (None, Some(text)) if text.contains('/') => {
+                binary_operator(&Token::Div(7)).unwrap().precedence + 1
+            }
+            _ => ATOM,
+        },
+    };
+}
+
+// source text of a literal node in the form evaluation results are printed in
+fn literal_text(kind: &NodeKind) -> Option<String> {
+    let value = match kind {
+        NodeKind::Number(value) => ResultEval::Int(*value),
+        NodeKind::Float(value) => ResultEval::Float(*value),
+        NodeKind::Rational(value) => ResultEval::Rational(*value),
+        NodeKind::Bool(value) => ResultEval::Bool(*value),
+        _ => return None,
+    };
+    return Some(value.to_string());
+}
+
+// `open` docs nested inside, with breaks after `open` and before `close`
+fn bracket(open: &str, doc: Doc, close: &str) -> Doc {
+    return group(concat(vec![
+        text(open),
+        nest(INDENT, concat(vec![Doc::SoftLine, doc])),
+        Doc::SoftLine,
+        text(close),
+    ]));
+}
+
+// builds the documents of trees, the comments of the source are written where the token
+// they are anchored to is written: after the token they follow on the same line, otherwise
+// in front of the token after them
+struct Unparser {
+    // offset the comment is anchored to, whether it is on the line of the token before it
+    // and the comment, in source order: the anchor is the end of the token before a comment
+    // on its line, otherwise the start of the token after it
+    comments: Vec<(usize, bool, Trivia)>,
+    // offsets of the `def` tokens, definitions have no node whose span starts there
+    definitions: Vec<usize>,
+    // spans of the operator and closing parenthesis tokens, the AST does not keep them
+    tokens: Vec<(Token, Span)>,
+}
+
+impl Unparser {
+    // the comments anchored up to `offset`, written after a token if `after`, otherwise in
+    // front of one, a line comment ends its line
+    // after a token, comments that were on a line of their own start a new line
+    fn comments(&mut self, offset: usize, after: bool) -> Vec<Doc> {
+        let count = self
+            .comments
+            .iter()
+            .take_while(|(at, _, _)| *at <= offset)
+            .count();
+        let mut docs = Vec::new();
+        for (_, same_line, comment) in self.comments.drain(..count) {
+            if after && !same_line {
+                docs.push(Doc::HardLine);
+            } else if after && docs.last() != Some(&Doc::HardLine) {
+                docs.push(text(" "));
+            }
+            docs.push(text(comment.text().trim_end()));
+            match comment {
+                Trivia::LineComment(_) => docs.push(Doc::HardLine),
+                _ if !after => docs.push(text(" ")),
+                _ => {}
+            }
+        }
+        return docs;
+    }
+
+    // the operator after the operand ending at `end`, the comments in front of the operator
+    // are returned first and written after the operand
+    fn operator(&mut self, end: usize, op: &Token) -> (Doc, Doc) {
+        let start = self.tokens.partition_point(|(_, span)| span.start < end);
+        let span = self.tokens[start..]
+            .iter()
+            .find(|(token, _)| *token != Token::RPar)
+            .map(|(_, span)| *span);
+        let Some(span) = span else {
+            return (concat(Vec::new()), text(format!("{} ", op)));
+        };
+        let before = self.comments(span.start, true);
+        let mut docs = vec![text(op.to_string())];
+        docs.extend(self.comments(span.end, true));
+        if docs.last() != Some(&Doc::HardLine) {
+            docs.push(text(" "));
+        }
+        return (concat(before), concat(docs));
+    }
+
+    // the operand in parentheses if it binds less tightly than `min`
+    // `right`: nothing of the parent is between the operand and the text after the parent,
+    // `followed`: text follows the operand, which would be swallowed by an open construct
+    fn operand(&mut self, node: &ASTNode, min: u8, right: bool, followed: bool) -> Doc {
+        let strength = strength(node);
+        let parens = if strength == OPEN {
+            followed || !right
+        } else if strength == prefix() && right {
+            // a prefix operator starts a new operand, its operand binds tighter than what follows
+            false
+        } else {
+            strength < min
+        };
+        if parens {
+            let doc = self.source(node, false);
+            return bracket("(", doc, ")");
+        }
+        return self.source(node, followed);
+    }
+
+    // the nodes separated by commas, in parentheses
+    fn list(&mut self, open: Doc, nodes: &[ASTNode]) -> Doc {
+        if nodes.is_empty() {
+            return concat(vec![open, text("()")]);
+        }
+        let mut docs = Vec::new();
+        for (i, node) in nodes.iter().enumerate() {
+            if i > 0 {
+                docs.extend([text(","), Doc::Line]);
+            }
+            docs.push(self.source(node, false));
+        }
+        return concat(vec![open, bracket("(", concat(docs), ")")]);
+    }
+
+    // `left op right` with the line broken in front of the operator
+    fn binary(&mut self, left: Doc, op: Doc, right: Doc) -> Doc {
+        let right = concat(vec![Doc::Line, op, right]);
+        return group(concat(vec![left, nest(INDENT, right)]));
+    }
+
+    // document of the node with the comments in front of it and after it, `followed` as
+    // for `operand`
+    fn source(&mut self, node: &ASTNode, followed: bool) -> Doc {
+        let mut docs = self.comments(node.span.start, false);
+        docs.push(self.node(node, followed));
+        docs.extend(self.comments(node.span.end, true));
+        if docs.len() == 1 {
+            return docs.pop().unwrap();
+        }
+        return concat(docs);
+    }
+
+    fn node(&mut self, node: &ASTNode, followed: bool) -> Doc {
+        if let Some(literal) = literal_text(&node.kind) {
+            return text(literal);
+        }
+        if let Some((op, left, right)) = node.kind.binary() {
+            let strength = op.precedence + 1;
+            let (left_min, right_min) = match op.associativity {
+                Associativity::Left => (strength, strength + 1),
+                Associativity::Right => (strength + 1, strength),
+                Associativity::Non | Associativity::Chain => (strength + 1, strength + 1),
+            };
+            let end = left.span.end;
+            let left = self.operand(left, left_min, false, true);
+            let (comments, op) = self.operator(end, &op.token);
+            let right = self.operand(right, right_min, true, followed);
+            return self.binary(concat(vec![left, comments]), op, right);
+        }
+        return match &node.kind {
+            // the shared operands are written once: `0 < x <= 10`
+            NodeKind::Chain(links) => {
+                let min = strength(node) + 1;
+                let mut docs = Vec::new();
+                for (i, link) in links.iter().enumerate() {
+                    let (op, left, right) = link.kind.binary().unwrap();
+                    if i == 0 {
+                        docs.push(self.operand(left, min, false, true));
+                    }
+                    let (comments, op) = self.operator(left.span.end, &op.token);
+                    docs.push(comments);
+                    let last = i + 1 == links.len();
+                    let right = self.operand(right, min, last, !last || followed);
+                    docs.push(nest(INDENT, concat(vec![Doc::Line, op, right])));
+                }
+                group(concat(docs))
+            }
+            NodeKind::Neg(value) | NodeKind::Not(value) => {
+                let symbol = match node.kind {
+                    NodeKind::Neg(_) => "-",
+                    _ => "!",
+                };
+                let value = self.operand(value, prefix(), true, followed);
+                // `- -x`, `--x` would not be two negations
+                match value.flat().starts_with('-') {
+                    true => concat(vec![text(symbol), text(" "), value]),
+                    false => concat(vec![text(symbol), value]),
+                }
+            }
+            NodeKind::Factorial(value) => {
+                concat(vec![self.operand(value, postfix(), false, true), text("!")])
+            }
+            NodeKind::Var(name) => text(name),
+            NodeKind::Call(name, arguments) => self.list(text(name), arguments),
+            // a variable followed by arguments would be a call
+            NodeKind::Apply(function, arguments) => {
+                let function = match function.kind {
+                    NodeKind::Var(_) => {
+                        let doc = self.source(function, false);
+                        bracket("(", doc, ")")
+                    }
+                    _ => self.operand(function, postfix(), false, true),
+                };
+                self.list(function, arguments)
+            }
+            NodeKind::If(condition, then, other) => {
+                let condition = self.operand(condition, OPEN, false, true);
+                let then = self.operand(then, OPEN, false, true);
+                let other = self.operand(other, OPEN, true, followed);
+                group(concat(vec![
+                    text("if "),
+                    nest(INDENT, condition),
+                    Doc::Line,
+                    text("then "),
+                    nest(INDENT, then),
+                    Doc::Line,
+                    text("else "),
+                    nest(INDENT, other),
+                ]))
+            }
+            NodeKind::Let(name, value, body) => {
+                let value = self.operand(value, OPEN, false, true);
+                let body = self.operand(body, OPEN, true, followed);
+                group(concat(vec![
+                    text(format!("let {} = ", name)),
+                    nest(INDENT, value),
+                    Doc::Line,
+                    text("in "),
+                    body,
+                ]))
+            }
+            NodeKind::Lambda(parameters, body) => {
+                let body = self.operand(body, OPEN, true, followed);
+                let head = match parameters.as_slice() {
+                    [parameter] => format!("\\{} ->", parameter),
+                    _ => format!("fn({}) =>", parameters.join(", ")),
+                };
+                group(concat(vec![
+                    text(head),
+                    nest(INDENT, concat(vec![Doc::Line, body])),
+                ]))
+            }
+            // every definition on its own line
+            NodeKind::Program(definitions, main) => {
+                let mut docs = Vec::new();
+                let starts = std::mem::take(&mut self.definitions);
+                for (i, definition) in definitions.iter().enumerate() {
+                    if let Some(start) = starts.get(i) {
+                        docs.extend(self.comments(*start, false));
+                    }
+                    let head = format!(
+                        "def {}({}) =",
+                        definition.name,
+                        definition.parameters.join(", ")
+                    );
+                    let body = self.source(&definition.body, false);
+                    docs.push(group(concat(vec![
+                        text(head),
+                        nest(INDENT, concat(vec![Doc::Line, body])),
+                        text(";"),
+                    ])));
+                    docs.push(Doc::Line);
+                }
+                docs.push(self.source(main, followed));
+                concat(docs)
+            }
+            // placeholder of a recovering parse, it has no source text
+            _ => text("<error>"),
+        };
+    }
+}
+
+impl ASTNode {
+    // document of the tree for the pretty printer
+    pub fn to_doc(&self) -> Doc {
+        let mut unparser = Unparser {
+            comments: Vec::new(),
+            definitions: Vec::new(),
+            tokens: Vec::new(),
+        };
+        return unparser.source(self, false);
+    }
+
+    // source text that parses back to the same tree, with only the parentheses the
+    // precedences and associativities of the parsers require: `(1 + 2) * 3`, `2 ^ 3 ^ 2`
+    // literals print like evaluation results, so negative literals come back as negations
+    pub fn to_source(&self) -> String {
+        return self.to_doc().flat();
+    }
+
+    // source text broken into lines of at most `width` characters where possible:
+    // in front of operators, after `(` and between arguments, nested lines are indented
+    pub fn to_pretty(&self, width: usize) -> String {
+        return self.to_doc().pretty(width);
+    }
+}
+
+impl fmt::Display for ASTNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.to_source());
+    }
+}
+
+// canonical form of an expression or program: the tree of the source pretty printed,
+// the comments of the source are kept after the tokens they follow on the same line and
+// in front of the tokens after them otherwise
+pub fn format_source(source: &str, width: usize) -> Result<String, ParseError> {
+    let mut comments = Vec::new();
+    let mut definitions = Vec::new();
+    let mut tokens = Vec::new();
+    // end of the previous token while no line break follows it
+    let mut line_end = None;
+    for spanned in Lexer::new_lossless(source) {
+        let spanned = spanned?;
+        for trivia in spanned.trivia {
+            let anchor = match spanned.token {
+                // comments at the end of the input are written after everything else
+                Token::Eof => usize::MAX,
+                _ => line_end.unwrap_or(spanned.span.start),
+            };
+            if !matches!(trivia, Trivia::Whitespace(_)) {
+                comments.push((anchor, line_end.is_some(), trivia.clone()));
+            }
+            if matches!(trivia, Trivia::LineComment(_)) || trivia.text().contains('\n') {
+                line_end = None;
+            }
+        }
+        if spanned.token == Token::Def {
+            definitions.push(spanned.span.start);
+        }
+        tokens.push((spanned.token, spanned.span));
+        line_end = Some(spanned.span.end);
+    }
+    let ast = ShuntiyardParser::new(Lexer::new(source)).parse()?;
+    let mut unparser = Unparser {
+        comments,
+        definitions,
+        tokens,
+    };
+    let doc = unparser.source(&ast, false);
+    let rest = unparser.comments(usize::MAX, true);
+    let doc = concat(vec![doc, concat(rest)]);
+    return Ok(format!("{}\n", doc.pretty(width).trim_end()));
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::{
+        lexer::lexer::Lexer,
+        numeric::numeric::Rational,
+        parser::{
+            parser::{ASTNode, Definition, NodeKind, Parser, ShuntiyardParser, OPERATORS},
+            pratt::PrattParser,
+            unparser::format_source,
+        },
+    };
+
+    fn parse(input: &str) -> Result<ASTNode> {
+        return Ok(ShuntiyardParser::new(Lexer::new(input)).parse()?);
+    }
+
+    #[test]
+    fn to_source_test() -> Result<()> {
+        let inputs = [
+            ("1 + 1 * 0", "1 + 1 * 0"),
+            ("((1 + 1)) * 0", "(1 + 1) * 0"),
+            ("(1 - 2) - 3", "1 - 2 - 3"),
+            ("1 - (2 - 3)", "1 - (2 - 3)"),
+            ("(2 ^ 3) ^ 2", "(2 ^ 3) ^ 2"),
+            ("2 ^ (3 ^ 2)", "2 ^ 3 ^ 2"),
+            ("(-2) ^ 2 + -(2 ^ 2)", "(-2) ^ 2 + -2 ^ 2"),
+            ("2 ^ (-3) * -(-x)", "2 ^ -3 * - -x"),
+            ("(-3)! + !(x!)", "(-3)! + !x!"),
+            ("0 < x <= (10)", "0 < x <= 10"),
+            ("(1 < 2) == (3 < 4)", "(1 < 2) == (3 < 4)"),
+            ("max(1, (2)) + f()", "max(1, 2) + f()"),
+            ("(f)(1)(2) + (\\x -> x)(3)", "(f)(1)(2) + (\\x -> x)(3)"),
+            (
+                "1 + (if c then 1 else 2) * 3",
+                "1 + (if c then 1 else 2) * 3",
+            ),
+            ("1 + (if c then 1 else 2 + 3)", "1 + if c then 1 else 2 + 3"),
+            (
+                "(1 + (if c then 1 else 2)) + 3",
+                "1 + (if c then 1 else 2) + 3",
+            ),
+            (
+                "c ? (let x = 1 in x) : fn(a, b) => a",
+                "if c then (let x = 1 in x) else fn(a, b) => a",
+            ),
+            ("0.5 * 2.5r || true", "0.5 * 2.5r || true"),
+            ("def f(x) = x + 1; f(2)", "def f(x) = x + 1; f(2)"),
+        ];
+        for (input, exp_source) in inputs {
+            let ast = parse(input)?;
+            assert_eq!(ast.to_source(), exp_source, "{}", input);
+            assert_eq!(format!("{}", ast), exp_source);
+            assert_eq!(parse(exp_source)?, ast, "{}", input);
+        }
+
+        // literals without literal token print like evaluation results
+        let ast = ASTNode::from(NodeKind::Power(
+            NodeKind::Number(-2).into(),
+            NodeKind::Rational(Rational::new(1, 3).unwrap()).into(),
+        ));
+        assert_eq!(ast.to_source(), "(-2) ^ (1/3r)");
+        Ok(())
+    }
+
+    // xorshift generator, the trees of a seed are the same in every run
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            return (self.0 % n as u64) as usize;
+        }
+
+        fn pick<'a>(&mut self, names: &[&'a str]) -> &'a str {
+            return names[self.below(names.len())];
+        }
+    }
+
+    // random tree of the nodes the parsers build, at most `depth` levels deep
+    fn tree(random: &mut Random, depth: usize) -> ASTNode {
+        let node = |random: &mut Random| Box::new(tree(random, depth - 1));
+        let choice = if depth == 0 {
+            random.below(5)
+        } else {
+            random.below(17)
+        };
+        let kind = match choice {
+            0 => NodeKind::Number(random.below(100) as i64),
+            1 => NodeKind::Float(random.below(20) as f64 * 0.25),
+            2 => NodeKind::Rational(Rational::new(random.below(20) as i128, 4).unwrap()),
+            3 => NodeKind::Bool(random.below(2) == 0),
+            4 => NodeKind::Var(random.pick(&["x", "y", "z"]).to_string()),
+            5..=8 => {
+                let op = &OPERATORS[random.below(OPERATORS.len())];
+                (op.build)(node(random), node(random))
+            }
+            9 => {
+                // comparisons sharing their operands like the ones the parsers build
+                let operands: Vec<ASTNode> =
+                    (0..random.below(2) + 3).map(|_| *node(random)).collect();
+                let links = operands.windows(2).map(|pair| {
+                    let comparisons = [NodeKind::Less, NodeKind::Equal, NodeKind::GreaterEqual];
+                    let build = comparisons[random.below(3)];
+                    ASTNode::from(build(pair[0].clone().into(), pair[1].clone().into()))
+                });
+                NodeKind::Chain(links.collect())
+            }
+            10 => {
+                [NodeKind::Neg, NodeKind::Not, NodeKind::Factorial][random.below(3)](node(random))
+            }
+            11 => {
+                let arguments = (0..random.below(3)).map(|_| *node(random)).collect();
+                NodeKind::Call(random.pick(&["max", "f"]).to_string(), arguments)
+            }
+            12 => {
+                let arguments = (0..random.below(3)).map(|_| *node(random)).collect();
+                NodeKind::Apply(node(random), arguments)
+            }
+            13 => NodeKind::If(node(random), node(random), node(random)),
+            14 => NodeKind::Let(
+                random.pick(&["x", "y"]).to_string(),
+                node(random),
+                node(random),
+            ),
+            15 => {
+                let parameters = [vec![], vec!["x"], vec!["x", "y"]][random.below(3)].clone();
+                let parameters = parameters.iter().map(|name| name.to_string()).collect();
+                NodeKind::Lambda(parameters, node(random))
+            }
+            _ => NodeKind::Neg(node(random)),
+        };
+        return ASTNode::from(kind);
+    }
+
+    #[test]
+    fn to_source_round_trip_test() -> Result<()> {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let mut ast = tree(&mut random, 4);
+            if random.below(10) == 0 {
+                let definition = Definition {
+                    name: "g".to_string(),
+                    parameters: vec!["x".to_string()],
+                    body: tree(&mut random, 2),
+                };
+                ast = ASTNode::from(NodeKind::Program(vec![definition], Box::new(ast)));
+            }
+            let source = ast.to_source();
+            assert_eq!(parse(&source)?, ast, "{}", source);
+            assert_eq!(
+                PrattParser::new(Lexer::new(&source)).parse()?,
+                ast,
+                "{}",
+                source
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn to_pretty_test() -> Result<()> {
+        let ast = parse("max(100000 + 200000 * 300000, if a < b then f(1, 2) else g(3)) * 9")?;
+        assert_eq!(
+            ast.to_pretty(80),
+            "max(100000 + 200000 * 300000, if a < b then f(1, 2) else g(3)) * 9"
+        );
+        assert_eq!(
+            ast.to_pretty(30),
+            "max(\n\
+            \x20 100000 + 200000 * 300000,\n\
+            \x20 if a < b\n\
+            \x20 then f(1, 2)\n\
+            \x20 else g(3)\n\
+            )\n\
+            \x20 * 9"
+        );
+        // nested parentheses are indented when they do not fit
+        let ast = parse("(1 + 2 + 3) * (4 - 5)")?;
+        assert_eq!(
+            ast.to_pretty(10),
+            "(\n  1 + 2\n    + 3\n)\n  * (\n    4 - 5\n  )"
+        );
+        assert_eq!(ast.to_pretty(12), "(1 + 2 + 3)\n  * (4 - 5)");
+        Ok(())
+    }
+
+    #[test]
+    fn format_test() -> Result<()> {
+        let inputs = [
+            ("((1+1))*0", "(1 + 1) * 0\n"),
+            // comments on lines of their own stay in front of the token after them
+            (
+                "# header\n1 + /* one */ 2 # sum\n",
+                "# header\n1 + /* one */ 2 # sum\n",
+            ),
+            ("1 +\n# two\n2", "1\n  + # two\n  2\n"),
+            // comments on the line of the token before them stay after that token
+            ("2 /* in */ * (3)", "2 /* in */ * 3\n"),
+            ("(1 /* a */) + 2", "1 /* a */ + 2\n"),
+            ("let x = 1 # why\n in x", "let x = 1 # why\nin x\n"),
+            ("(1 + 2) /* c */ * 3", "(1 + 2) /* c */ * 3\n"),
+            ("f(1) # one\n + 2", "f(1) # one\n  + 2\n"),
+            (
+                "def f(x) = x+1; # g\ndef g() = 2; f(g())",
+                "def f(x) = x + 1;\n# g\ndef g() = 2;\nf(g())\n",
+            ),
+        ];
+        for (input, exp_output) in inputs {
+            let output = format_source(input, 80)?;
+            assert_eq!(output, exp_output, "{}", input);
+            // formatting is idempotent
+            assert_eq!(format_source(&output, 80)?, output);
+        }
+        assert_eq!(
+            format_source("1 + (2", 80).unwrap_err().to_string(),
+            "unbalanced parenthesis '(' at 1:5"
+        );
+        Ok(())
+    }
+}
@@ -1,59 +1,139 @@
-use crate::parser::parser::ASTNode;
+use crate::{
+    evaluator::{builtins::Type, evaluator::eval},
+    numeric::numeric::Rational,
+    parser::parser::{ASTNode, NodeKind, ResultEval},
+    typechecker::typechecker::type_of,
+};
 
+// simplified nodes keep the span of the node they replace
+// the expression has to be well-typed, simplify_fix checks it once for the whole tree
 pub fn simplify(ast: &ASTNode) -> ASTNode {
-    match ast {
-        // num & bool: returns same node
-        ASTNode::Number(0) => ASTNode::Number(0),
-        ASTNode::Number(1) => ASTNode::Number(1),
-        ASTNode::Bool(true) => ASTNode::Bool(true),
-        ASTNode::Bool(false) => ASTNode::Bool(false),
-        // Add: recursively simplifies the left and right children
-        ASTNode::Add(left, right) => {
-            ASTNode::Add(Box::new(simplify(left)), Box::new(simplify(right)))
-        }
-        // Mutiplication: checks both nodes for zero values, returns 0 if found
-        ASTNode::Multiply(left, right) => {
-            if (**left == ASTNode::Number(0)) | (**right == ASTNode::Number(0)) {
-                // println!("right: {:?}, left: {:?}", **right, **left);
-                ASTNode::Number(0)
+    let kind = match &ast.kind {
+        // Mutiplication: checks both nodes for zero values, returns the zero of the product
+        NodeKind::Multiply(left, right) => match zero_product(left, right) {
+            Some(zero) => zero,
+            None => ast.kind.map_children(simplify),
+        },
+        // Comparison: two int or two bool literals are replaced by the result
+        kind if kind.comparison().is_some_and(constant_operands) => fold(ast),
+        // Chain: constant if all comparisons are, false as soon as one constant comparison is
+        NodeKind::Chain(links) => {
+            let folded: Vec<ASTNode> = links.iter().map(simplify).collect();
+            if folded.iter().all(|link| link.kind == NodeKind::Bool(true)) {
+                NodeKind::Bool(true)
+            } else if folded.iter().any(|link| link.kind == NodeKind::Bool(false)) {
+                NodeKind::Bool(false)
             } else {
-                ASTNode::Multiply(Box::new(simplify(left)), Box::new(simplify(right)))
+                ast.kind.map_children(simplify)
             }
         }
-        // Or: recursively simplifies the left and right children
-        ASTNode::Or(left, right) => {
-            ASTNode::Or(Box::new(simplify(left)), Box::new(simplify(right)))
+        // And & Or: a constant left operand decides the result or leaves the right one
+        NodeKind::And(left, right) | NodeKind::Or(left, right) => {
+            let short_circuit = matches!(ast.kind, NodeKind::Or(..));
+            match left.kind {
+                NodeKind::Bool(value) if value == short_circuit => NodeKind::Bool(value),
+                // only a bool can replace the result, a variable may hold another value
+                NodeKind::Bool(_) if boolean(right) => simplify(right).kind,
+                _ => ast.kind.map_children(simplify),
+            }
         }
-        _ => unreachable!(),
-    }
+        // If: a constant condition selects its branch
+        NodeKind::If(condition, then, otherwise) => match condition.kind {
+            NodeKind::Bool(true) => simplify(then).kind,
+            NodeKind::Bool(false) => simplify(otherwise).kind,
+            _ => ast.kind.map_children(simplify),
+        },
+        // num & bool: returns same node
+        // other operators: recursively simplifies the children
+        other => other.map_children(simplify),
+    };
+    return ASTNode::new(kind, ast.span);
+}
+
+// zero of the type of the product if one operand is the int 0, ints and floats or rationals
+// are promoted like in the evaluator; None if the other operand has no known kind of number
+fn zero_product(left: &ASTNode, right: &ASTNode) -> Option<NodeKind> {
+    let other = if left.kind == NodeKind::Number(0) {
+        right
+    } else if right.kind == NodeKind::Number(0) {
+        left
+    } else {
+        return None;
+    };
+    return match type_of(other) {
+        Ok(Type::Int) => Some(NodeKind::Number(0)),
+        Ok(Type::Rational) => Some(NodeKind::Rational(Rational::from_int(0))),
+        Ok(Type::Float) => Some(NodeKind::Float(0.0)),
+        _ => None,
+    };
+}
+
+// true for the nodes whose value is a bool by their kind alone: bool literals, comparisons
+// and logical operators
+fn boolean(node: &ASTNode) -> bool {
+    return node.kind.comparison().is_some()
+        || matches!(
+            node.kind,
+            NodeKind::Bool(_)
+                | NodeKind::Chain(_)
+                | NodeKind::And(..)
+                | NodeKind::Or(..)
+                | NodeKind::Not(_)
+        );
+}
+
+// true for two int or two bool literals, the operands of comparisons that can be folded
+fn constant_operands((left, right): (&ASTNode, &ASTNode)) -> bool {
+    return matches!(
+        (&left.kind, &right.kind),
+        (NodeKind::Number(_), NodeKind::Number(_)) | (NodeKind::Bool(_), NodeKind::Bool(_))
+    );
 }
+
+// comparison of constant operands replaced by its value
+fn fold(ast: &ASTNode) -> NodeKind {
+    return match eval(ast) {
+        Some(ResultEval::Bool(value)) => NodeKind::Bool(value),
+        _ => unreachable!("Comparison of constants is a bool"),
+    };
+}
+
 // application of "simplify" until return input AST as the final result
+// ill-typed expressions are returned as they are, so evaluating them still reports the type
+// error instead of e.g. `0 * (1 * false)` being simplified to 0
 pub fn simplify_fix(ast: ASTNode) -> ASTNode {
+    if type_of(&ast).is_err() {
+        return ast;
+    }
+    return fixpoint(ast);
+}
+
+fn fixpoint(ast: ASTNode) -> ASTNode {
     let ast2 = simplify(&ast);
     if ast2 == ast {
-        return ast;
+        ast
     } else {
-        simplify_fix(ast2)
+        fixpoint(ast2)
     }
 }
 
-
 #[cfg(test)]
 mod test {
     use anyhow::{Ok, Result};
 
-    use crate::{parser::parser::ASTNode, simplifier::simplifier::simplify_fix};
+    use crate::{
+        lexer::lexer::{Lexer, Span},
+        parser::parser::{ASTNode, NodeKind, Parser, ShuntiyardParser},
+        simplifier::simplifier::simplify_fix,
+    };
     // testing simplification of ASTNode::Multiply expression with a Number node of 0
     #[test]
     fn simplify_fix_mult_zero_test() -> Result<()> {
-        let ast1 = ASTNode::Multiply(
-            Box::new(ASTNode::Number(0)),
-            Box::new(ASTNode::Add(
-                Box::new(ASTNode::Number(1)),
-                Box::new(ASTNode::Number(1)),
-            )),
-        );
-        let exp_ast = ASTNode::Number(0);
+        let ast1 = ASTNode::from(NodeKind::Multiply(
+            NodeKind::Number(0).into(),
+            NodeKind::Add(NodeKind::Number(1).into(), NodeKind::Number(1).into()).into(),
+        ));
+        let exp_ast = ASTNode::from(NodeKind::Number(0));
         let simp_ast = simplify_fix(ast1);
         println!(
             "Expected AST: {:?}, Simplified AST: {:?}",
@@ -62,24 +142,18 @@ mod test {
         assert_eq!(simp_ast, exp_ast);
         Ok(())
     }
-    // testing general behavior of simplify_fix with an ASTNode::Multiply expression 
+    // testing general behavior of simplify_fix with an ASTNode::Multiply expression
     // (expecting result to be same as input)
     #[test]
     fn simplify_fix_test() -> Result<()> {
-        let ast1 = ASTNode::Multiply(
-            Box::new(ASTNode::Number(1)),
-            Box::new(ASTNode::Add(
-                Box::new(ASTNode::Number(1)),
-                Box::new(ASTNode::Number(1)),
-            )),
-        );
-        let exp_ast = ASTNode::Multiply(
-            Box::new(ASTNode::Number(1)),
-            Box::new(ASTNode::Add(
-                Box::new(ASTNode::Number(1)),
-                Box::new(ASTNode::Number(1)),
-            )),
-        );
+        let ast1 = ASTNode::from(NodeKind::Multiply(
+            NodeKind::Number(1).into(),
+            NodeKind::Add(NodeKind::Number(1).into(), NodeKind::Number(1).into()).into(),
+        ));
+        let exp_ast = ASTNode::from(NodeKind::Multiply(
+            NodeKind::Number(1).into(),
+            NodeKind::Add(NodeKind::Number(1).into(), NodeKind::Number(1).into()).into(),
+        ));
         let simp_ast = simplify_fix(ast1);
         println!(
             "Expected AST: {:?}, Simplified AST: {:?}",
@@ -88,33 +162,125 @@ mod test {
         assert_eq!(exp_ast, simp_ast);
         Ok(())
     }
-    // testing ASTNode::Or expression with a complex nested structure
+    // testing that ill-typed products like 0 * (1 * false) are kept
     #[test]
     fn simplify_or_test() -> Result<()> {
-        let ast1 = ASTNode::Multiply(
-            Box::new(ASTNode::Number(0)),
-            Box::new(ASTNode::Multiply(
-                Box::new(ASTNode::Number(1)),
-                Box::new(ASTNode::Bool(false)),
-            )),
-        );
-        let exp_ast = ASTNode::Multiply(
-            Box::new(ASTNode::Number(0)),
-            Box::new(ASTNode::Multiply(
-                Box::new(ASTNode::Number(1)),
-                Box::new(ASTNode::Bool(false)),
-            )),
-        );
+        let ast1 = ASTNode::from(NodeKind::Multiply(
+            NodeKind::Number(0).into(),
+            NodeKind::Multiply(NodeKind::Number(1).into(), NodeKind::Bool(false).into()).into(),
+        ));
+        let exp_ast = ASTNode::from(NodeKind::Multiply(
+            NodeKind::Number(0).into(),
+            NodeKind::Multiply(NodeKind::Number(1).into(), NodeKind::Bool(false).into()).into(),
+        ));
         let simp_ast = simplify_fix(ast1);
         println!(
             "Expected AST: {:?}, Simplified AST: {:?}",
             exp_ast, simp_ast
         );
-        println!(
-            "\x1b[33mIt's expected that this test fails, \
-            because the simplification simplifies ill-typed expressions\x1b[0m"
-        );
         assert_eq!(exp_ast, simp_ast);
         Ok(())
     }
+    // testing that multiplication by zero keeps the type of the product
+    #[test]
+    fn simplify_mult_zero_types_test() -> Result<()> {
+        let parse = |input| ShuntiyardParser::new(Lexer::new(input)).parse();
+        let inputs = [
+            ("0 * 2.5", "0.0"),
+            ("(1 + 0.5) * 0", "0.0"),
+            ("0 * 0.5r", "0r"),
+            ("0.5r * 2 * 0", "0r"),
+            ("0 * (2 + 3)", "0"),
+            // `x` may hold a float, so the product is only known at runtime
+            ("0 * x", "0 * x"),
+        ];
+        for (input, exp_result) in inputs {
+            assert_eq!(simplify_fix(parse(input)?), parse(exp_result)?, "{}", input);
+        }
+        Ok(())
+    }
+    // testing folding of conditionals with a constant condition
+    #[test]
+    fn simplify_if_test() -> Result<()> {
+        let simplify = |input| -> Result<ASTNode> {
+            let mut parser = ShuntiyardParser::new(Lexer::new(input));
+            return Ok(simplify_fix(parser.parse()?));
+        };
+        let simp_ast = simplify("if true then 1 + 2 else 0 * 3")?;
+        assert_eq!(
+            simp_ast,
+            ASTNode::from(NodeKind::Add(
+                NodeKind::Number(1).into(),
+                NodeKind::Number(2).into()
+            ))
+        );
+        assert_eq!((simp_ast.span.start, simp_ast.span.end), (0, 29));
+        let simp_ast = simplify("false ? 1 : (true ? 0 * 2 : 3)")?;
+        assert_eq!(simp_ast, ASTNode::from(NodeKind::Number(0)));
+        // ill-typed conditionals are kept, evaluating them reports the type error
+        let simp_ast = simplify("if true then 1 else false")?;
+        assert!(matches!(simp_ast.kind, NodeKind::If(..)));
+        Ok(())
+    }
+    // testing folding of comparisons, chains and logical operators with constant operands
+    #[test]
+    fn simplify_comparison_test() -> Result<()> {
+        let parse = |input| ShuntiyardParser::new(Lexer::new(input)).parse();
+        let inputs = [
+            ("1 < 2", "true"),
+            ("true == false", "false"),
+            ("0 * 2 <= 0", "true"),
+            ("0 < 1 <= 1 != 2", "true"),
+            ("0 < x <= 1 < 0", "false"),
+            ("0 < x <= 10", "0 < x <= 10"),
+            ("1 < 2 && x > 0", "x > 0"),
+            ("1 > 2 && x", "false"),
+            ("1 == 1 || x", "true"),
+            ("false || x < 1", "x < 1"),
+            // `x` may hold a number, so it cannot be the result of `true && x`
+            ("true && x", "true && x"),
+            ("true && !x", "!x"),
+            // floats and rationals are not folded, ill-typed comparisons are kept
+            ("0.5 < 1.5", "0.5 < 1.5"),
+            ("1 < true", "1 < true"),
+            ("false && 1", "false && 1"),
+        ];
+        for (input, exp_result) in inputs {
+            assert_eq!(simplify_fix(parse(input)?), parse(exp_result)?, "{}", input);
+        }
+        Ok(())
+    }
+
+    // testing that simplified nodes still point to the original input
+    #[test]
+    fn simplify_keeps_spans_test() -> Result<()> {
+        let input = "1 + 0 * (1 + 1)";
+        let mut parser = ShuntiyardParser::new(Lexer::new(input));
+        let simp_ast = simplify_fix(parser.parse()?);
+
+        let NodeKind::Add(one, zero) = &simp_ast.kind else {
+            panic!("Expected addition, received {:?}", simp_ast);
+        };
+        assert_eq!(zero.kind, NodeKind::Number(0));
+        assert_eq!(
+            zero.span,
+            Span {
+                start: 4,
+                end: 14,
+                line: 1,
+                column: 5
+            }
+        );
+        assert_eq!(&input[one.span.start..one.span.end], "1");
+        assert_eq!(
+            simp_ast.span,
+            Span {
+                start: 0,
+                end: 14,
+                line: 1,
+                column: 1
+            }
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,501 @@
+use std::fmt;
+
+use crate::{
+    evaluator::{
+        builtins::Type,
+        env::{Bound, Env},
+    },
+    lexer::lexer::Span,
+    parser::parser::{ASTNode, NodeKind},
+};
+
+// errors found while inferring types, each carries the span of the offending sub-expression
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypeError {
+    // operand or condition of the wrong type, e.g. `1 + true`
+    Mismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    // conditional whose branches have different types, e.g. `if c then 1 else false`
+    Branches {
+        then_type: Type,
+        else_type: Type,
+        span: Span,
+    },
+}
+
+impl TypeError {
+    pub fn span(&self) -> Span {
+        return match self {
+            TypeError::Mismatch { span, .. } | TypeError::Branches { span, .. } => *span,
+        };
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            TypeError::Mismatch {
+                expected,
+                found,
+                span,
+            } => write!(f, "expected {} but found {} at {}", expected, found, span),
+            TypeError::Branches {
+                then_type,
+                else_type,
+                span,
+            } => write!(
+                f,
+                "branches of the conditional at {} have different types {} and {}",
+                span, then_type, else_type
+            ),
+        };
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+// infers the type of an expression without evaluating it
+// the typing is gradual: results of function calls, parameters of functions, placeholders and
+// free variables have type `any`, which fits every type and is only checked during evaluation
+pub fn type_of(node: &ASTNode) -> Result<Type, TypeError> {
+    return infer(node, &mut Scope::new(Vec::new(), false));
+}
+
+// infers the type of an expression whose free variables have the values in `env`
+pub fn type_with(node: &ASTNode, env: &Env) -> Result<Type, TypeError> {
+    return infer(node, &mut Scope::of(env, false));
+}
+
+// checks the conditionals of an expression whose free variables have the values in `env`:
+// every conditional has to follow all type rules, outside of conditionals operands of the
+// wrong type are left to the evaluator, e.g. `true || 1` is true
+pub fn check_conditionals(node: &ASTNode, env: &Env) -> Result<(), TypeError> {
+    return infer(node, &mut Scope::of(env, true)).map(|_| ());
+}
+
+// types of the variables bound around an expression, inner bindings come last
+struct Scope {
+    variables: Vec<(String, Type)>,
+    // operands of the wrong type are accepted as `any`, except inside of conditionals
+    lenient: bool,
+}
+
+impl Scope {
+    fn new(variables: Vec<(String, Type)>, lenient: bool) -> Scope {
+        return Scope { variables, lenient };
+    }
+
+    // scope with the types of the values in `env`
+    fn of(env: &Env, lenient: bool) -> Scope {
+        // the innermost binding is looked up first, so it is the last one in the scope
+        let mut variables: Vec<(String, Type)> = env
+            .iter()
+            .map(|(name, bound)| match bound {
+                Bound::Value(value) => (name.to_string(), Type::of(value)),
+                Bound::Thunk(..) => (name.to_string(), Type::Any),
+                Bound::Definition(_) => (name.to_string(), Type::Function),
+            })
+            .collect();
+        variables.reverse();
+        return Scope::new(variables, lenient);
+    }
+}
+
+fn infer(node: &ASTNode, scope: &mut Scope) -> Result<Type, TypeError> {
+    return match &node.kind {
+        NodeKind::Number(_) => Ok(Type::Int),
+        NodeKind::Float(_) => Ok(Type::Float),
+        NodeKind::Rational(_) => Ok(Type::Rational),
+        NodeKind::Bool(_) => Ok(Type::Bool),
+        NodeKind::Error => Ok(Type::Any),
+        NodeKind::Var(name) => Ok(scope
+            .variables
+            .iter()
+            .rev()
+            .find(|(bound, _)| bound == name)
+            .map_or(Type::Any, |(_, t)| *t)),
+        // the variable has the type of its value inside the body
+        NodeKind::Let(name, value, body) => {
+            let value_type = infer(value, scope)?;
+            scope.variables.push((name.clone(), value_type));
+            let body_type = infer(body, scope);
+            scope.variables.pop();
+            body_type
+        }
+        // binary operators: left nested operators like `1 + 2 + 3` are inferred in a loop
+        // instead of recursively, so long generated expressions fit on the stack
+        NodeKind::Add(..)
+        | NodeKind::Subtract(..)
+        | NodeKind::Multiply(..)
+        | NodeKind::Divide(..)
+        | NodeKind::Modulo(..)
+        | NodeKind::Power(..)
+        | NodeKind::ShiftLeft(..)
+        | NodeKind::ShiftRight(..)
+        | NodeKind::BitAnd(..)
+        | NodeKind::BitOr(..)
+        | NodeKind::Equal(..)
+        | NodeKind::NotEqual(..)
+        | NodeKind::Less(..)
+        | NodeKind::LessEqual(..)
+        | NodeKind::Greater(..)
+        | NodeKind::GreaterEqual(..)
+        | NodeKind::And(..)
+        | NodeKind::Or(..) => {
+            let mut spine = Vec::new();
+            let mut leftmost = node;
+            while let Some((_, left, _)) = leftmost.kind.binary() {
+                spine.push(leftmost);
+                leftmost = left;
+            }
+            let mut left_type = infer(leftmost, scope)?;
+            for operator in spine.into_iter().rev() {
+                left_type = binary(operator, left_type, scope)?;
+            }
+            Ok(left_type)
+        }
+        NodeKind::Chain(links) => {
+            for link in links {
+                infer(link, scope)?;
+            }
+            Ok(Type::Bool)
+        }
+        NodeKind::Neg(operand) => Ok(meet(expect(operand, Type::Number, scope)?, Type::Number)),
+        NodeKind::Not(operand) => expect(operand, Type::Bool, scope),
+        NodeKind::Factorial(operand) => expect(operand, Type::Int, scope),
+        // arguments are checked against the signature of the function when it is called,
+        // a variable called as function has to hold one
+        NodeKind::Call(name, arguments) => {
+            let bound = scope
+                .variables
+                .iter()
+                .rev()
+                .find(|(bound, _)| bound == name);
+            if let Some(&(_, found)) = bound {
+                require(node, found, Type::Function, scope)?;
+            }
+            for argument in arguments {
+                infer(argument, scope)?;
+            }
+            Ok(Type::Any)
+        }
+        NodeKind::Apply(function, arguments) => {
+            expect(function, Type::Function, scope)?;
+            for argument in arguments {
+                infer(argument, scope)?;
+            }
+            Ok(Type::Any)
+        }
+        // the body is checked with parameters of any type
+        NodeKind::Lambda(parameters, body) => {
+            check_function(parameters, body, scope)?;
+            Ok(Type::Function)
+        }
+        // every function sees all functions of the program
+        NodeKind::Program(definitions, main) => {
+            let depth = scope.variables.len();
+            scope.variables.extend(
+                definitions
+                    .iter()
+                    .map(|definition| (definition.name.clone(), Type::Function)),
+            );
+            let checked = definitions.iter().try_for_each(|definition| {
+                check_function(&definition.parameters, &definition.body, scope)
+            });
+            let main_type = checked.and_then(|_| infer(main, scope));
+            scope.variables.truncate(depth);
+            main_type
+        }
+        // all type rules apply inside of a conditional
+        NodeKind::If(condition, then, otherwise) => {
+            let lenient = std::mem::replace(&mut scope.lenient, false);
+            let checked = conditional(node, condition, then, otherwise, scope);
+            scope.lenient = lenient;
+            checked
+        }
+    };
+}
+
+// type of the binary operator `node` whose left operand has the type `l`
+fn binary(node: &ASTNode, l: Type, scope: &mut Scope) -> Result<Type, TypeError> {
+    return match &node.kind {
+        // arithmetic: numbers of any kind, promoted along the numeric tower
+        NodeKind::Add(left, right)
+        | NodeKind::Subtract(left, right)
+        | NodeKind::Multiply(left, right)
+        | NodeKind::Divide(left, right)
+        | NodeKind::Modulo(left, right) => Ok(promote(
+            require(left, l, Type::Number, scope)?,
+            expect(right, Type::Number, scope)?,
+        )),
+        // rationals raised to a fraction become floats, so their kind is only known at runtime
+        NodeKind::Power(left, right) => {
+            let (l, r) = (
+                require(left, l, Type::Number, scope)?,
+                expect(right, Type::Number, scope)?,
+            );
+            return Ok(match (l, r) {
+                (Type::Float, _) | (_, Type::Float) => Type::Float,
+                (Type::Int | Type::Rational, Type::Int) => l,
+                _ => Type::Number,
+            });
+        }
+        NodeKind::ShiftLeft(left, right) | NodeKind::ShiftRight(left, right) => {
+            require(left, l, Type::Int, scope)?;
+            expect(right, Type::Int, scope)?;
+            Ok(Type::Int)
+        }
+        // bitwise on ints, logical on bools
+        NodeKind::BitAnd(left, right) | NodeKind::BitOr(left, right) => {
+            let r = infer(right, scope)?;
+            if l == Type::Bool || r == Type::Bool {
+                return Ok(meet(
+                    require(left, l, Type::Bool, scope)?,
+                    require(right, r, Type::Bool, scope)?,
+                ));
+            }
+            if l == Type::Any && r == Type::Any {
+                return Ok(Type::Any);
+            }
+            require(left, l, Type::Int, scope)?;
+            require(right, r, Type::Int, scope)?;
+            Ok(Type::Int)
+        }
+        // comparisons of two numbers or two bools
+        NodeKind::Equal(left, right)
+        | NodeKind::NotEqual(left, right)
+        | NodeKind::Less(left, right)
+        | NodeKind::LessEqual(left, right)
+        | NodeKind::Greater(left, right)
+        | NodeKind::GreaterEqual(left, right) => {
+            let r = infer(right, scope)?;
+            let operands = if l == Type::Bool || r == Type::Bool {
+                Type::Bool
+            } else {
+                Type::Number
+            };
+            require(left, l, operands, scope)?;
+            require(right, r, operands, scope)?;
+            Ok(Type::Bool)
+        }
+        NodeKind::And(left, right) | NodeKind::Or(left, right) => {
+            require(left, l, Type::Bool, scope)?;
+            expect(right, Type::Bool, scope)?;
+            Ok(Type::Bool)
+        }
+        _ => unreachable!("Binary operator expected"),
+    };
+}
+
+// the condition is a bool and both branches have the same type
+fn conditional(
+    node: &ASTNode,
+    condition: &ASTNode,
+    then: &ASTNode,
+    otherwise: &ASTNode,
+    scope: &mut Scope,
+) -> Result<Type, TypeError> {
+    expect(condition, Type::Bool, scope)?;
+    let (then_type, else_type) = (infer(then, scope)?, infer(otherwise, scope)?);
+    if !fits(then_type, else_type) {
+        return Err(TypeError::Branches {
+            then_type,
+            else_type,
+            span: node.span,
+        });
+    }
+    return Ok(meet(then_type, else_type));
+}
+
+// checks the body of a function with parameters of any type
+fn check_function(
+    parameters: &[String],
+    body: &ASTNode,
+    scope: &mut Scope,
+) -> Result<(), TypeError> {
+    let depth = scope.variables.len();
+    scope
+        .variables
+        .extend(parameters.iter().map(|name| (name.clone(), Type::Any)));
+    let body_type = infer(body, scope);
+    scope.variables.truncate(depth);
+    return body_type.map(|_| ());
+}
+
+// infers the type of `node` and checks that it fits `expected`
+fn expect(node: &ASTNode, expected: Type, scope: &mut Scope) -> Result<Type, TypeError> {
+    let found = infer(node, scope)?;
+    return require(node, found, expected, scope);
+}
+
+// checks that the type `found` inferred for `node` fits `expected`
+// a lenient scope accepts other types, the value is then only known at runtime
+fn require(node: &ASTNode, found: Type, expected: Type, scope: &Scope) -> Result<Type, TypeError> {
+    if !fits(expected, found) {
+        if scope.lenient {
+            return Ok(Type::Any);
+        }
+        return Err(TypeError::Mismatch {
+            expected,
+            found,
+            span: node.span,
+        });
+    }
+    return Ok(found);
+}
+
+// true if values of the two types can meet: equal types, `any` with every type
+// and `number` with every kind of number
+fn fits(left: Type, right: Type) -> bool {
+    let numeric = |t| matches!(t, Type::Int | Type::Rational | Type::Float | Type::Number);
+    return left == right
+        || left == Type::Any
+        || right == Type::Any
+        || (left == Type::Number && numeric(right))
+        || (right == Type::Number && numeric(left));
+}
+
+// the more precise of two fitting types
+fn meet(left: Type, right: Type) -> Type {
+    return match (left, right) {
+        (Type::Any, other) | (other, Type::Any) => other,
+        (Type::Number, other) => other,
+        (other, _) => other,
+    };
+}
+
+// kind of an arithmetic result on the numeric tower Int -> Rational -> Float
+fn promote(left: Type, right: Type) -> Type {
+    return match (left, right) {
+        (Type::Int, Type::Int) => Type::Int,
+        (Type::Float, _) | (_, Type::Float) => Type::Float,
+        (Type::Any | Type::Number, _) | (_, Type::Any | Type::Number) => Type::Number,
+        _ => Type::Rational,
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        evaluator::builtins::Type,
+        evaluator::env::Env,
+        lexer::lexer::Lexer,
+        parser::parser::{Parser, ShuntiyardParser},
+        typechecker::typechecker::{check_conditionals, type_of, TypeError},
+    };
+    use anyhow::Result;
+
+    fn check(input: &str) -> Result<Result<Type, TypeError>> {
+        let ast = ShuntiyardParser::new(Lexer::new(input)).parse()?;
+        return Ok(type_of(&ast));
+    }
+
+    #[test]
+    fn type_of_test() -> Result<()> {
+        let inputs = [
+            ("1 + 2 * 3", Type::Int),
+            ("1 + 0.5r", Type::Rational),
+            ("1 + 0.5r * 2.0", Type::Float),
+            ("2 ^ 0.5r", Type::Number),
+            ("1 < 2 && !(true == false)", Type::Bool),
+            ("6 & 3 | 1", Type::Int),
+            ("true & false", Type::Bool),
+            ("max(1, 2) + 1", Type::Number),
+            ("if 1 < 2 then 1 else 2", Type::Int),
+            ("true ? 1.5 : abs(-2.5)", Type::Float),
+            ("let x = 1 in x + x", Type::Int),
+            ("let x = true in let x = 1.5 in x", Type::Float),
+            ("y + 1", Type::Number),
+            ("\\x -> x + 1", Type::Function),
+            ("true ? \\x -> x : fn(x, y) => x", Type::Function),
+            ("(\\x -> x)(1) + 1", Type::Number),
+            ("let f = \\x -> x in f(1)", Type::Any),
+            ("def f(x) = x + 1; f", Type::Function),
+            ("def f(x) = g(x); def g(x) = x; f(1)", Type::Any),
+        ];
+        for (input, exp_type) in inputs {
+            assert_eq!(check(input)?, Ok(exp_type), "{}", input);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn type_errors_test() -> Result<()> {
+        let inputs = [
+            ("1 + true", "expected number but found bool at 1:5"),
+            ("0 * (1 * false)", "expected number but found bool at 1:10"),
+            ("if 1 then 2 else 3", "expected bool but found int at 1:4"),
+            (
+                "1 < 2 ? 1 : false",
+                "branches of the conditional at 1:1 have different types int and bool",
+            ),
+            (
+                "if true then 1 else 1.0",
+                "branches of the conditional at 1:1 have different types int and float",
+            ),
+            (
+                "let x = 1 in if true then x else false",
+                "branches of the conditional at 1:14 have different types int and bool",
+            ),
+            ("1(2)", "expected function but found int at 1:1"),
+            (
+                "let f = 1 in f(2)",
+                "expected function but found int at 1:14",
+            ),
+            ("\\x -> x + true", "expected number but found bool at 1:11"),
+            (
+                "def f(x) = x + true; 1",
+                "expected number but found bool at 1:16",
+            ),
+            (
+                "def f() = 1; f + 1",
+                "expected number but found function at 1:14",
+            ),
+            ("0 < 1 <= true", "expected bool but found int at 1:5"),
+        ];
+        for (input, exp_error) in inputs {
+            let error = check(input)?.unwrap_err();
+            assert_eq!(error.to_string(), exp_error, "{}", input);
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn check_conditionals_test() -> Result<()> {
+        let check = |input| -> Result<Result<(), TypeError>> {
+            let ast = ShuntiyardParser::new(Lexer::new(input)).parse()?;
+            return Ok(check_conditionals(&ast, &Env::new()));
+        };
+        // operands of the wrong type outside of conditionals are left to the evaluator
+        for input in [
+            "true || 1",
+            "1(2)",
+            "1 + (2 > 1 ? 1 : 2)",
+            "\\x -> x ? x : 1",
+        ] {
+            assert_eq!(check(input)?, Ok(()), "{}", input);
+        }
+        let inputs = [
+            (
+                "1 < 2 ? 1 : false",
+                "branches of the conditional at 1:1 have different types int and bool",
+            ),
+            (
+                "true || (if 1 then 2 else 3)",
+                "expected bool but found int at 1:13",
+            ),
+            (
+                "1 + (true ? 1 + false : 2)",
+                "expected number but found bool at 1:17",
+            ),
+        ];
+        for (input, exp_error) in inputs {
+            let error = check(input)?.unwrap_err();
+            assert_eq!(error.to_string(), exp_error, "{}", input);
+        }
+        return Ok(());
+    }
+}